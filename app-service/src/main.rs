@@ -57,8 +57,12 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
     // Is there a better way to do this without having to initialize a new client builder everytime protected is called?
     let api_client = reqwest::Client::builder().build().unwrap();
 
+    // the auth service checks the token was issued for us, to at least a member
+    let audience = env::var("JWT_AUDIENCE_NAME").unwrap_or("app-service".to_owned());
     let verify_token_body = serde_json::json!({
         "token": &jwt_cookie.value(),
+        "audience": audience,
+        "role": "member",
     });
 
     // TODO: Research on what's the difference between AUTH_SERVICE_HOST_NAME and AUTH_SERVICE_IP?
@@ -76,6 +80,8 @@ async fn protected(jar: CookieJar) -> impl IntoResponse {
         reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::BAD_REQUEST => {
             StatusCode::UNAUTHORIZED.into_response()
         }
+        reqwest::StatusCode::FORBIDDEN => StatusCode::FORBIDDEN.into_response(),
+        reqwest::StatusCode::TOO_MANY_REQUESTS => StatusCode::TOO_MANY_REQUESTS.into_response(),
         reqwest::StatusCode::OK => Json(ProtectedRouteResponse {
             img_url: "https://i.ibb.co/YP90j68/Light-Live-Bootcamp-Certificate.png".to_owned(),
         })
@@ -161,4 +161,159 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// -----------------------------------------------------
+
+const forgotPasswordSection = document.getElementById("forgot-password-section");
+const resetPasswordSection = document.getElementById("reset-password-section");
+
+const forgotPasswordLink = document.getElementById("forgot-password-link");
+const forgotPasswordLoginLink = document.getElementById("forgot-password-login-link");
+
+forgotPasswordLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    loginSection.style.display = "none";
+    forgotPasswordSection.style.display = "block";
+});
+
+forgotPasswordLoginLink.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    loginSection.style.display = "block";
+    forgotPasswordSection.style.display = "none";
+});
+
+const forgotPasswordForm = document.getElementById("forgot-password-form");
+const forgotPasswordButton = document.getElementById("forgot-password-form-submit");
+const forgotPasswordErrAlter = document.getElementById("forgot-password-err-alert");
+
+forgotPasswordButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = forgotPasswordForm.email.value;
+
+    fetch('/forgot-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email }),
+    }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                forgotPasswordForm.email.value = "";
+                forgotPasswordErrAlter.style.display = "none";
+                alert(data.message);
+                loginSection.style.display = "block";
+                forgotPasswordSection.style.display = "none";
+            } else if (data.error) {
+                forgotPasswordErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                forgotPasswordErrAlter.style.display = "block";
+            }
+        });
+    });
+});
+
+const resetPasswordForm = document.getElementById("reset-password-form");
+const resetPasswordButton = document.getElementById("reset-password-form-submit");
+const resetPasswordErrAlter = document.getElementById("reset-password-err-alert");
+
+// the reset link in the email points back to this page with the token in the query string
+const resetParams = new URLSearchParams(window.location.search);
+if (resetParams.has("resetToken")) {
+    resetPasswordForm.email.value = resetParams.get("email");
+    resetPasswordForm.reset_token.value = resetParams.get("resetToken");
+
+    loginSection.style.display = "none";
+    resetPasswordSection.style.display = "block";
+}
+
+resetPasswordButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const email = resetPasswordForm.email.value;
+    const resetToken = resetPasswordForm.reset_token.value;
+    const newPassword = resetPasswordForm.password.value;
+
+    fetch('/reset-password', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ email, resetToken, newPassword }),
+    }).then(response => {
+        if (response.ok) {
+            resetPasswordForm.password.value = "";
+            resetPasswordErrAlter.style.display = "none";
+            alert("Your password was reset, please log in again.");
+            window.history.replaceState({}, "", "/");
+            loginSection.style.display = "block";
+            resetPasswordSection.style.display = "none";
+        } else {
+            response.json().then(data => {
+                let error_msg = data.error;
+                if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+                    resetPasswordErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+                    resetPasswordErrAlter.style.display = "block";
+                } else {
+                    resetPasswordErrAlter.style.display = "none";
+                }
+            });
+        }
+    });
+});
+
+// -----------------------------------------------------
+
+const changeEmailSection = document.getElementById("change-email-section");
+const changeEmailForm = document.getElementById("change-email-form");
+const changeEmailButton = document.getElementById("change-email-form-submit");
+const changeEmailErrAlter = document.getElementById("change-email-err-alert");
+
+// the links in the change email emails point back to this page, the change only happens once
+// the user clicks through, so link scanners opening them can't confirm or cancel anything
+const changeEmailParams = new URLSearchParams(window.location.search);
+if (["confirm", "cancel"].includes(changeEmailParams.get("changeEmail"))) {
+    const action = changeEmailParams.get("changeEmail");
+    changeEmailForm.change_action.value = action;
+    changeEmailForm.token.value = changeEmailParams.get("token");
+    document.getElementById("change-email-title").textContent =
+        action === "confirm" ? "Confirm Email Change" : "Cancel Email Change";
+    document.getElementById("change-email-text").textContent =
+        action === "confirm"
+            ? "Move your account to this email address?"
+            : "Cancel the email change and log out every session?";
+
+    loginSection.style.display = "none";
+    changeEmailSection.style.display = "block";
+}
+
+changeEmailButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    const action = changeEmailForm.change_action.value;
+    const token = changeEmailForm.token.value;
+
+    fetch(`/change-email/${action}`, {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ token }),
+    }).then(response => {
+        response.json().then(data => {
+            if (response.ok) {
+                changeEmailErrAlter.style.display = "none";
+                alert(data.message);
+                window.history.replaceState({}, "", "/");
+                loginSection.style.display = "block";
+                changeEmailSection.style.display = "none";
+            } else if (data.error) {
+                changeEmailErrAlter.innerHTML = `<span><strong>Error: </strong>${data.error}</span>`;
+                changeEmailErrAlter.style.display = "block";
+            }
+        });
+    });
+});
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- accounts created before verification existed are treated as verified, new ones start unverified.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE users ALTER COLUMN email_verified SET DEFAULT FALSE;
@@ -0,0 +1,8 @@
+-- Add up migration script here
+-- codes follow their account when the email changes and go away with it.
+CREATE TABLE IF NOT EXISTS recovery_codes (
+    id SERIAL PRIMARY KEY,
+    email TEXT NOT NULL REFERENCES users (email) ON UPDATE CASCADE ON DELETE CASCADE,
+    code_hash TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS recovery_codes_email_idx ON recovery_codes (email);
@@ -0,0 +1,9 @@
+-- Add up migration script here
+-- the JWT keyring, private keys are encrypted with SIGNING_KEY_ENCRYPTION_KEY.
+CREATE TABLE IF NOT EXISTS signing_keys (
+    kid TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    private_key TEXT NOT NULL,
+    state TEXT NOT NULL,
+    created_at BIGINT NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Add up migration script here
+-- no foreign key, sessions are moved and removed along with the account by the routes like every other session store.
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    email TEXT NOT NULL,
+    created_at BIGINT NOT NULL,
+    last_seen_at BIGINT NOT NULL,
+    ip TEXT,
+    user_agent TEXT
+);
+CREATE INDEX IF NOT EXISTS sessions_email_idx ON sessions (email);
@@ -0,0 +1,3 @@
+-- Add up migration script here
+-- bumped to log the user out everywhere, tokens carrying an older version are rejected.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS token_version INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- existing accounts become members, like the ones signing up from now on.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS role TEXT NOT NULL DEFAULT 'member';
+ALTER TABLE users ADD CONSTRAINT users_role_check CHECK (role IN ('none', 'guest', 'member', 'admin'));
@@ -1,7 +1,12 @@
 use crate::domain::{
-    data_store::{BannedTokenStore, TwoFACodeStore, UserStore},
+    data_store::{
+        AccountLockoutStore, BannedTokenStore, EmailChangeStore, EmailVerificationTokenStore,
+        MagicLinkStore, PasswordResetTokenStore, RateLimitStore, RefreshTokenStore, SessionStore,
+        TwoFACodeStore, UserStore,
+    },
     EmailClient,
 };
+use crate::services::{keyring::Keyring, token_version_cache::TokenVersionCache};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
@@ -9,6 +14,16 @@ pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore>>;
 pub type TwoFAStoreType = Arc<RwLock<dyn TwoFACodeStore>>;
 pub type EmailClientType = Arc<dyn EmailClient>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore>>;
+pub type PasswordResetTokenStoreType = Arc<RwLock<dyn PasswordResetTokenStore>>;
+pub type EmailVerificationTokenStoreType = Arc<RwLock<dyn EmailVerificationTokenStore>>;
+pub type EmailChangeStoreType = Arc<RwLock<dyn EmailChangeStore>>;
+pub type MagicLinkStoreType = Arc<RwLock<dyn MagicLinkStore>>;
+pub type KeyringType = Arc<RwLock<Keyring>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore>>;
+pub type TokenVersionCacheType = Arc<RwLock<TokenVersionCache>>;
+pub type AccountLockoutStoreType = Arc<RwLock<dyn AccountLockoutStore>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -16,20 +31,51 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFAStoreType,
     pub email_client: EmailClientType,
+    pub refresh_token_store: RefreshTokenStoreType,
+    pub password_reset_token_store: PasswordResetTokenStoreType,
+    pub email_verification_token_store: EmailVerificationTokenStoreType,
+    pub email_change_store: EmailChangeStoreType,
+    pub magic_link_store: MagicLinkStoreType,
+    pub keyring: KeyringType,
+    pub session_store: SessionStoreType,
+    pub token_version_cache: TokenVersionCacheType,
+    pub account_lockout_store: AccountLockoutStoreType,
+    pub rate_limit_store: RateLimitStoreType,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         user_store: UserStoreType,
         banned_token_store: BannedTokenStoreType,
         two_fa_code_store: TwoFAStoreType,
         email_client: EmailClientType,
+        refresh_token_store: RefreshTokenStoreType,
+        password_reset_token_store: PasswordResetTokenStoreType,
+        email_verification_token_store: EmailVerificationTokenStoreType,
+        email_change_store: EmailChangeStoreType,
+        magic_link_store: MagicLinkStoreType,
+        keyring: KeyringType,
+        session_store: SessionStoreType,
+        token_version_cache: TokenVersionCacheType,
+        account_lockout_store: AccountLockoutStoreType,
+        rate_limit_store: RateLimitStoreType,
     ) -> Self {
         Self {
             user_store,
             banned_token_store,
             two_fa_code_store,
             email_client,
+            refresh_token_store,
+            password_reset_token_store,
+            email_verification_token_store,
+            email_change_store,
+            magic_link_store,
+            keyring,
+            session_store,
+            token_version_cache,
+            account_lockout_store,
+            rate_limit_store,
         }
     }
 }
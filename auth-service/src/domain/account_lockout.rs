@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+// the longest a lock can get, no matter how many came before it
+const MAX_LOCK_DOUBLINGS: u32 = 32;
+
+// Failed logins of one account, kept until it logs in successfully or the record expires.
+// Failures go on counting through the locks, every `threshold` of them earns the next one.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockoutRecord {
+    pub failed_attempts: u32,
+    pub locked_until: Option<i64>, // unix timestamp
+}
+
+impl LockoutRecord {
+    // seconds left on the lock, if there is one
+    pub fn locked_for(&self, now: i64) -> Option<i64> {
+        self.locked_until
+            .map(|until| until - now)
+            .filter(|seconds| *seconds > 0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    pub threshold: u32,
+    pub base_seconds: i64,
+    pub max_seconds: i64,
+}
+
+impl LockoutPolicy {
+    // The first lock lasts base_seconds, every one after it twice as long as the one before.
+    pub fn lock_duration(&self, lockouts: u32) -> i64 {
+        let doublings = lockouts.saturating_sub(1).min(MAX_LOCK_DOUBLINGS);
+        self.base_seconds
+            .saturating_mul(1 << doublings)
+            .min(self.max_seconds)
+    }
+
+    // Takes the number of failed logins on record, the one just made included, and returns how
+    // many seconds the account gets locked for. Only every `threshold`-th failure locks it, so
+    // failures counted at the same time by several instances lock it once.
+    pub fn register_failure(&self, failed_attempts: u32) -> Option<i64> {
+        if failed_attempts == 0 || !failed_attempts.is_multiple_of(self.threshold) {
+            return None;
+        }
+        Some(self.lock_duration(failed_attempts / self.threshold))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LockoutPolicy, LockoutRecord};
+
+    const POLICY: LockoutPolicy = LockoutPolicy {
+        threshold: 3,
+        base_seconds: 60,
+        max_seconds: 600,
+    };
+
+    #[test]
+    fn account_should_lock_at_threshold() {
+        assert_eq!(POLICY.register_failure(1), None);
+        assert_eq!(POLICY.register_failure(2), None);
+        assert_eq!(POLICY.register_failure(3), Some(60));
+
+        // the next lock takes another threshold of failures, and lasts twice as long
+        assert_eq!(POLICY.register_failure(4), None);
+        assert_eq!(POLICY.register_failure(5), None);
+        assert_eq!(POLICY.register_failure(6), Some(120));
+    }
+
+    #[test]
+    fn locked_for_should_count_down_to_none() {
+        let record = LockoutRecord {
+            failed_attempts: 3,
+            locked_until: Some(1060),
+        };
+        assert_eq!(record.locked_for(1000), Some(60));
+        assert_eq!(record.locked_for(1060), None);
+        assert_eq!(LockoutRecord::default().locked_for(1000), None);
+    }
+
+    #[test]
+    fn lock_duration_should_double_up_to_max() {
+        let durations: Vec<i64> = (1..=6).map(|n| POLICY.lock_duration(n)).collect();
+        assert_eq!(durations, vec![60, 120, 240, 480, 600, 600]);
+        assert_eq!(POLICY.lock_duration(u32::MAX), 600);
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+// How a user proved who they are, reported in the `amr` claim (RFC 8176).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthMethod {
+    #[serde(rename = "pwd")]
+    Password,
+    // emailed 2FA codes, authenticator app codes, recovery codes and magic links
+    #[serde(rename = "otp")]
+    OneTimePassword,
+    // added once a second factor was checked
+    #[serde(rename = "mfa")]
+    MultiFactor,
+}
+
+impl AuthMethod {
+    // The methods of a login that passed `first_factor` and then a second factor.
+    pub fn with_second_factor(first_factor: &[AuthMethod]) -> Vec<AuthMethod> {
+        let mut methods = first_factor.to_vec();
+        for method in [AuthMethod::OneTimePassword, AuthMethod::MultiFactor] {
+            if !methods.contains(&method) {
+                methods.push(method);
+            }
+        }
+        methods
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_should_use_rfc_8176_values() {
+        let methods = vec![
+            AuthMethod::Password,
+            AuthMethod::OneTimePassword,
+            AuthMethod::MultiFactor,
+        ];
+        assert_eq!(
+            serde_json::to_string(&methods).unwrap(),
+            r#"["pwd","otp","mfa"]"#
+        );
+    }
+
+    #[test]
+    fn second_factor_should_be_added_once() {
+        assert_eq!(
+            AuthMethod::with_second_factor(&[AuthMethod::Password]),
+            vec![
+                AuthMethod::Password,
+                AuthMethod::OneTimePassword,
+                AuthMethod::MultiFactor
+            ]
+        );
+        assert_eq!(
+            AuthMethod::with_second_factor(&[AuthMethod::OneTimePassword]),
+            vec![AuthMethod::OneTimePassword, AuthMethod::MultiFactor]
+        );
+    }
+}
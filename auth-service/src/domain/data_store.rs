@@ -1,10 +1,26 @@
+use chrono::Utc;
 use color_eyre::eyre::Report;
-use serde::Deserialize;
 use thiserror::Error;
 
+use crate::utils::constants::{TWO_FACTOR_POLICY, TWO_FA_RESEND_COOLDOWN_SECONDS};
+
 use super::{
-    email::Email, login_attempt_id::LoginAttemptId, password::Password, two_fa_code::TwoFACode,
-    user::User,
+    account_lockout::LockoutRecord,
+    auth_method::AuthMethod,
+    email::Email,
+    email_change_token::EmailChangeToken,
+    email_verification_token::EmailVerificationToken,
+    login_attempt_id::LoginAttemptId,
+    password::Password,
+    password_reset_token::PasswordResetToken,
+    rate_limit::{RateLimit, RateLimitDecision},
+    recovery_code::RecoveryCode,
+    refresh_token::RefreshToken,
+    signing_key::{KeyState, SigningKey},
+    totp_secret::EncryptedTotpSecret,
+    two_fa_code::{StoredTwoFACode, TwoFACode},
+    unlock_token::UnlockToken,
+    user::{User, UserRole},
 };
 
 #[derive(Debug, Error)]
@@ -15,6 +31,8 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    #[error("TOTP code was already used")]
+    TotpCodeReused,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -26,6 +44,7 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::TotpCodeReused, Self::TotpCodeReused)
                 // This trick was used to help resolve partialEq compiliation error
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
@@ -42,6 +61,83 @@ pub trait UserStore: Send + Sync {
         password: &Password,
     ) -> Result<User, UserStoreError>;
     async fn delete_user(&mut self, email: Email) -> Result<(), UserStoreError>;
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    async fn verify_email(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // moves the account to a new (already confirmed) address.
+    async fn update_email(&mut self, email: &Email, new_email: Email)
+        -> Result<(), UserStoreError>;
+    // stores a new unconfirmed authenticator app secret, replacing any previous one.
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError>;
+    async fn get_totp(&self, email: &Email) -> Result<Option<TotpRecord>, UserStoreError>;
+    async fn confirm_totp(&mut self, email: &Email) -> Result<(), UserStoreError>;
+    // records the time step a code was accepted for, fails if that step (or a later one) was already used.
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError>;
+    // replaces every recovery code of the account with a new set.
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), UserStoreError>;
+    // checks the code against the recovery codes of the account without burning it.
+    async fn check_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError>;
+    // burns the matching recovery code and returns how many are left.
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, UserStoreError>;
+    // every JWT carries the version it was issued under, tokens with an older one were logged out.
+    async fn get_token_version(&self, email: &Email) -> Result<i32, UserStoreError>;
+    // logs the user out everywhere and returns the new version.
+    async fn increment_token_version(&mut self, email: &Email) -> Result<i32, UserStoreError>;
+    // a page of users ordered by email, optionally only those whose email contains the search term.
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserList, UserStoreError>;
+    // applies the fields that are set and returns the updated user.
+    // tokens carry the role they were issued with, a new one only shows up in new tokens.
+    async fn update_user(
+        &mut self,
+        email: &Email,
+        update: UserUpdate,
+    ) -> Result<User, UserStoreError>;
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserListQuery {
+    pub search: Option<String>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserList {
+    pub users: Vec<User>,
+    pub total: usize, // users matching the search, across all pages
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserUpdate {
+    pub requires_2fa: Option<bool>,
+    pub role: Option<UserRole>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotpRecord {
+    pub secret: EncryptedTotpSecret,
+    pub confirmed: bool,
+    pub last_step: Option<u64>,
 }
 
 #[derive(Debug, Error)]
@@ -67,8 +163,307 @@ impl PartialEq for BannedTokenStoreError {
 
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync {
-    async fn add_token(&mut self, token: &str) -> Result<(), BannedTokenStoreError>;
-    async fn check_token(&self, token: &str) -> bool;
+    // tokens are banned by their jti claim, only until the token expires (unix timestamp)
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError>;
+    async fn check_token(&self, jti: &str) -> bool;
+    // how many tokens are banned right now, for metrics
+    async fn count(&self) -> Result<usize, BannedTokenStoreError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyringEntry {
+    pub key: SigningKey,
+    pub state: KeyState,
+    pub created_at: i64, // unix timestamp
+}
+
+// Every kid of a keyring with its state, in kid order. Two keyrings with the same states are the same keyring.
+pub fn keyring_states(entries: &[KeyringEntry]) -> Vec<(String, String)> {
+    let mut states: Vec<(String, String)> = entries
+        .iter()
+        .map(|entry| (entry.key.kid().to_owned(), entry.state.to_string()))
+        .collect();
+    states.sort();
+    states
+}
+
+#[derive(Debug, Error)]
+pub enum SigningKeyStoreError {
+    #[error("Keyring was changed by someone else")]
+    Conflict,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SigningKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::Conflict, Self::Conflict) | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SigningKeyStore: Send + Sync {
+    async fn get_keys(&self) -> Result<Vec<KeyringEntry>, SigningKeyStoreError>;
+    // replaces the whole keyring, so a rotation is saved all at once. `current` is the keyring
+    // the change was made to, when the stored one differs from it nothing is saved (Conflict).
+    async fn save_keys(
+        &mut self,
+        current: &[KeyringEntry],
+        keys: &[KeyringEntry],
+    ) -> Result<(), SigningKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkStoreError {
+    #[error("Magic link was already used")]
+    LinkAlreadyUsed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for MagicLinkStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::LinkAlreadyUsed, Self::LinkAlreadyUsed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait MagicLinkStore: Send + Sync {
+    // Marks the link as used until it expires at `expires_at`, fails if it already was.
+    async fn consume_link(
+        &mut self,
+        link_id: &str,
+        expires_at: i64,
+    ) -> Result<(), MagicLinkStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RefreshTokenStoreError {
+    #[error("Refresh token not found")]
+    TokenNotFound,
+    #[error("Refresh token was already used")]
+    TokenReused(String), // family id of the reused token, so the caller can revoke it
+    #[error("Refresh token family was revoked")]
+    FamilyRevoked,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RefreshTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::TokenReused(_), Self::TokenReused(_))
+                | (Self::FamilyRevoked, Self::FamilyRevoked)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Every refresh token issued from the same login shares a family id.
+// Rotating a token keeps the family, so reusing an old token can revoke the whole chain.
+#[derive(Debug, Clone)]
+pub struct RefreshTokenRecord {
+    pub email: Email,
+    pub family_id: String,
+    pub amr: Vec<AuthMethod>, // how the login that started the family was authenticated
+}
+
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        token: &RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError>;
+    // marks the token as used and returns the record it was issued with.
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError>;
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError>;
+    // revokes every family issued to this user, e.g. after a password reset.
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError>;
+    // hands every family of this user over to their new email address.
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), RefreshTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SessionStoreError {
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SessionStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::SessionNotFound, Self::SessionNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// A login as the server sees it. The id is the `sid` claim of every JWT issued to the session
+// and the family id of its refresh tokens, so removing the session logs it out right away.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub email: Email,
+    pub created_at: i64,   // unix timestamp
+    pub last_seen_at: i64, // unix timestamp, sessions unused for REFRESH_TOKEN_TTL_SECONDS are gone
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError>;
+    // marks the session as used now, fails with SessionNotFound once it was removed or went idle.
+    async fn touch_session(&mut self, id: &str) -> Result<Session, SessionStoreError>;
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError>;
+    // fails with SessionNotFound unless the session belongs to this user.
+    async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError>;
+    // removes every session of the user and returns how many there were.
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<usize, SessionStoreError>;
+    // hands every session of this user over to their new email address.
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), SessionStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PasswordResetTokenStoreError {
+    #[error("Password reset token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PasswordResetTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait PasswordResetTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError>;
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError>;
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailVerificationTokenStoreError {
+    #[error("Email verification token not found")]
+    TokenNotFound,
+    #[error("A verification email was sent recently")]
+    CooldownActive,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailVerificationTokenStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::CooldownActive, Self::CooldownActive)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait EmailVerificationTokenStore: Send + Sync {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+    // removes the token and returns the email it was issued for, links are single use.
+    async fn consume_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError>;
+    // fails with CooldownActive if a verification email was sent to this address too recently.
+    async fn start_cooldown(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum EmailChangeStoreError {
+    #[error("Email change request not found")]
+    RequestNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for EmailChangeStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RequestNotFound, Self::RequestNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// A pending move from one address to another. The new address gets the confirm token,
+// the old one gets the cancel token in case the request wasn't made by the owner.
+#[derive(Debug, Clone)]
+pub struct EmailChangeRequest {
+    pub email: Email,
+    pub new_email: Email,
+    pub confirm_token: EmailChangeToken,
+    pub cancel_token: EmailChangeToken,
+}
+
+#[async_trait::async_trait]
+pub trait EmailChangeStore: Send + Sync {
+    // a user only has one pending request, adding a new one replaces the old one.
+    async fn add_request(
+        &mut self,
+        request: EmailChangeRequest,
+    ) -> Result<(), EmailChangeStoreError>;
+    // both remove the request the token belongs to, each only accepts its own kind of token.
+    async fn take_confirmed(
+        &mut self,
+        confirm_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError>;
+    async fn take_cancelled(
+        &mut self,
+        cancel_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -92,20 +487,148 @@ impl PartialEq for TwoFACodeStoreError {
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Clone)]
 pub struct TwoFARecord {
     pub id: LoginAttemptId,
-    pub code: TwoFACode,
+    // the user logging in, a login attempt ID is only good together with its email
+    pub email: Email,
+    // only a keyed hash of the code that was sent is kept
+    pub code: StoredTwoFACode,
+    // the first factor of the login attempt
+    pub amr: Vec<AuthMethod>,
+    // codes guessed for this login attempt so far, a right one ends the login
+    pub failed_attempts: u32,
+    // fresh codes sent after the first one, and when the last code was sent (unix timestamp)
+    pub resends: u32,
+    pub last_sent_at: i64,
+    pub created_at: i64, // unix timestamp
+    pub expires_at: i64, // unix timestamp, pushed back when a fresh code is sent
+    // where the login came from
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+impl TwoFARecord {
+    // a login attempt whose code is sent right now
+    pub fn new(email: Email, id: LoginAttemptId, code: &TwoFACode, amr: Vec<AuthMethod>) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            id,
+            email,
+            code: StoredTwoFACode::hash(code),
+            amr,
+            failed_attempts: 0,
+            resends: 0,
+            last_sent_at: now,
+            created_at: now,
+            expires_at: now + TWO_FACTOR_POLICY.ttl_seconds,
+            ip: None,
+            user_agent: None,
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+
+    // seconds left before another code may be sent for this login attempt
+    pub fn resend_cooldown(&self, now: i64) -> Option<i64> {
+        let wait = self.last_sent_at + TWO_FA_RESEND_COOLDOWN_SECONDS - now;
+        (wait > 0).then_some(wait)
+    }
 }
 
+// Pending logins are keyed by their login attempt ID, so a user can log in from several tabs
+// at once. Each store also indexes them by email for cleanup, and keeps at most
+// TWO_FA_MAX_PENDING_ATTEMPTS per user, dropping the oldest first.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore: Send + Sync {
-    async fn add_code(
+    async fn add_code(&mut self, record: TwoFARecord) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(&mut self, id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError>;
+    // drops every pending login of the user, does nothing if there's none.
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
+    async fn get_code(&self, id: &LoginAttemptId) -> Result<TwoFARecord, TwoFACodeStoreError>;
+    // swaps in a fresh code for the pending login attempt and gives it the full TTL of the policy,
+    // wrong guesses so far still count.
+    async fn resend_code(
         &mut self,
-        email: Email,
-        id: LoginAttemptId,
+        id: &LoginAttemptId,
         code: TwoFACode,
+    ) -> Result<TwoFARecord, TwoFACodeStoreError>;
+    // counts a guess against the pending login attempt before its code is checked, and returns
+    // how many there were with this one. Counting and reading back are one step, so concurrent
+    // guesses each get their own number.
+    async fn count_attempt(&mut self, id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError>;
+    // moves the pending logins over to the new email address, does nothing if there's none.
+    async fn move_codes(
+        &mut self,
+        email: &Email,
+        new_email: Email,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
-    async fn get_code(&self, email: &Email) -> Result<TwoFARecord, TwoFACodeStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AccountLockoutStoreError {
+    #[error("Unlock token not found")]
+    TokenNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AccountLockoutStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TokenNotFound, Self::TokenNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait AccountLockoutStore: Send + Sync {
+    // accounts without failed logins on record get an empty one
+    async fn get_record(&self, email: &Email) -> Result<LockoutRecord, AccountLockoutStoreError>;
+    // adds a failed login to the record and returns how many it holds now, atomically so that
+    // no failure is lost when several instances count at once
+    async fn count_failure(&mut self, email: &Email) -> Result<u32, AccountLockoutStoreError>;
+    async fn lock(&mut self, email: &Email, until: i64) -> Result<(), AccountLockoutStoreError>;
+    async fn remove_record(&mut self, email: &Email) -> Result<(), AccountLockoutStoreError>;
+    async fn add_unlock_token(
+        &mut self,
+        email: Email,
+        token: UnlockToken,
+    ) -> Result<(), AccountLockoutStoreError>;
+    // removes the token and returns the email it was issued for, links are single use.
+    async fn consume_unlock_token(
+        &mut self,
+        token: &UnlockToken,
+    ) -> Result<Email, AccountLockoutStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RateLimitStoreError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RateLimitStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    // Takes a request from every bucket if each of them has one left, and from none otherwise,
+    // so a request turned away by one bucket doesn't use up the others. Returns the decision of
+    // each bucket in order, `now` is in unix milliseconds.
+    async fn check(
+        &mut self,
+        buckets: &[(&str, &RateLimit)],
+        now: i64,
+    ) -> Result<Vec<RateLimitDecision>, RateLimitStoreError>;
 }
@@ -16,6 +16,8 @@ pub struct ErrorResponse {
 pub enum AuthAPIError {
     #[error("User already exist!")]
     UserAlreadyExists,
+    #[error("User not found")]
+    UserNotFound,
     #[error("Incorrect credentials was used")]
     IncorrectCredentials,
     #[error("Something terribly happen, may you qualify as a QA tester someday in the future")]
@@ -28,6 +30,28 @@ pub enum AuthAPIError {
     InvalidData(String),
     #[error("Mismatch identification")]
     MismatchIdentification,
+    #[error("Email address is not verified")]
+    EmailNotVerified,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("Authenticator app is already enabled")]
+    TwoFactorAlreadyEnabled,
+    #[error("Signing key not found")]
+    SigningKeyNotFound,
+    #[error("Session not found")]
+    SessionNotFound,
+    #[error("The user's role does not allow this")]
+    Forbidden,
+    #[error("Account is disabled")]
+    AccountDisabled,
+    #[error("Account is locked for {0} seconds")]
+    AccountLocked(i64),
+    #[error("Too many wrong 2FA codes")]
+    TooManyTwoFactorAttempts,
+    #[error("Too many 2FA codes were sent")]
+    TooManyTwoFactorResends,
+    #[error("2FA code expired")]
+    TwoFactorCodeExpired,
 }
 
 impl IntoResponse for AuthAPIError {
@@ -37,6 +61,7 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::UserAlreadyExists => {
                 (StatusCode::CONFLICT, "User already exist".to_owned())
             }
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found".to_owned()),
             AuthAPIError::IncorrectCredentials => {
                 (StatusCode::UNAUTHORIZED, "Invalid credentials".to_owned())
             }
@@ -55,6 +80,48 @@ impl IntoResponse for AuthAPIError {
             AuthAPIError::InvalidData(data) => {
                 (StatusCode::BAD_REQUEST, format!("Invalid data: {data}"))
             }
+            AuthAPIError::EmailNotVerified => (
+                StatusCode::FORBIDDEN,
+                "Please verify your email address first".to_owned(),
+            ),
+            AuthAPIError::TooManyRequests => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many requests, please try again later".to_owned(),
+            ),
+            AuthAPIError::TwoFactorAlreadyEnabled => (
+                StatusCode::CONFLICT,
+                "Authenticator app is already enabled".to_owned(),
+            ),
+            AuthAPIError::SigningKeyNotFound => {
+                (StatusCode::NOT_FOUND, "Signing key not found".to_owned())
+            }
+            AuthAPIError::SessionNotFound => {
+                (StatusCode::NOT_FOUND, "Session not found".to_owned())
+            }
+            AuthAPIError::Forbidden => (
+                StatusCode::FORBIDDEN,
+                "You are not allowed to do this".to_owned(),
+            ),
+            AuthAPIError::AccountDisabled => (
+                StatusCode::FORBIDDEN,
+                "This account has been disabled".to_owned(),
+            ),
+            AuthAPIError::TooManyTwoFactorAttempts => (
+                StatusCode::UNAUTHORIZED,
+                "Too many wrong codes, please log in again".to_owned(),
+            ),
+            AuthAPIError::TooManyTwoFactorResends => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many codes were sent, please log in again".to_owned(),
+            ),
+            AuthAPIError::TwoFactorCodeExpired => (
+                StatusCode::UNAUTHORIZED,
+                "The 2FA code expired, please log in again".to_owned(),
+            ),
+            AuthAPIError::AccountLocked(seconds) => (
+                StatusCode::LOCKED,
+                format!("Too many failed logins, this account is locked for {seconds} seconds"),
+            ),
         };
         let body = Json(ErrorResponse {
             error: error_msg.to_owned(),
@@ -1,9 +1,21 @@
+pub mod account_lockout;
+pub mod auth_method;
 pub mod data_store;
 pub mod email;
+pub mod email_change_token;
+pub mod email_verification_token;
 pub mod error;
 pub mod login_attempt_id;
 pub mod password;
+pub mod password_reset_token;
+pub mod rate_limit;
+pub mod recovery_code;
+pub mod refresh_token;
+pub mod signing_key;
+pub mod totp_secret;
 pub mod two_fa_code;
+pub mod two_factor_policy;
+pub mod unlock_token;
 pub mod user;
 
 pub mod email_client;
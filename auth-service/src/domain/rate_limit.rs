@@ -0,0 +1,244 @@
+use color_eyre::eyre::{eyre, Report, Result};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// `burst` requests every `period_seconds`, refilled one at a time (GCRA).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub period_seconds: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    // milliseconds until the request would have been allowed, 0 when it was
+    pub retry_after: i64,
+    // milliseconds until the whole burst is available again
+    pub reset_after: i64,
+    // the theoretical arrival time to keep for the next request, unix milliseconds
+    pub tat: i64,
+}
+
+impl RateLimit {
+    // milliseconds it takes to earn back one request
+    pub fn interval(&self) -> i64 {
+        (self.tolerance() / i64::from(self.burst)).max(1)
+    }
+
+    // how far ahead of now the theoretical arrival time may run
+    pub fn tolerance(&self) -> i64 {
+        i64::from(self.period_seconds) * 1000
+    }
+
+    // Runs the GCRA for a request arriving at `now` (unix milliseconds), given the theoretical
+    // arrival time stored for its key. The store only keeps `tat` when the request is allowed.
+    pub fn check(&self, tat: Option<i64>, now: i64) -> RateLimitDecision {
+        let interval = self.interval();
+        let tat = tat.unwrap_or(now).max(now);
+        let new_tat = tat + interval;
+        let allow_at = new_tat - self.tolerance();
+
+        if now < allow_at {
+            return RateLimitDecision {
+                allowed: false,
+                limit: self.burst,
+                remaining: 0,
+                retry_after: allow_at - now,
+                reset_after: tat - now,
+                tat,
+            };
+        }
+        RateLimitDecision {
+            allowed: true,
+            limit: self.burst,
+            remaining: ((now - allow_at) / interval) as u32,
+            retry_after: 0,
+            reset_after: new_tat - now,
+            tat: new_tat,
+        }
+    }
+}
+
+// "10/60" is 10 requests a minute
+impl FromStr for RateLimit {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (burst, period) = s
+            .split_once('/')
+            .ok_or_else(|| eyre!("Rate limit must look like <requests>/<seconds>"))?;
+        let limit = Self {
+            burst: burst.trim().parse()?,
+            period_seconds: period.trim().parse()?,
+        };
+        if limit.burst == 0 || limit.period_seconds == 0 {
+            return Err(eyre!("Rate limit requests and seconds must be positive"));
+        }
+        Ok(limit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RouteRateLimits {
+    pub per_ip: Option<RateLimit>,
+    // keyed by the email field of the JSON body
+    pub per_email: Option<RateLimit>,
+}
+
+// "ip:30/60,email:10/60", either one may be left out
+impl FromStr for RouteRateLimits {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut limits = Self::default();
+        for limit in s
+            .split(',')
+            .map(str::trim)
+            .filter(|limit| !limit.is_empty())
+        {
+            match limit.split_once(':') {
+                Some(("ip", limit)) => limits.per_ip = Some(limit.parse()?),
+                Some(("email", limit)) => limits.per_email = Some(limit.parse()?),
+                _ => return Err(eyre!("Unknown rate limit: {limit}")),
+            }
+        }
+        Ok(limits)
+    }
+}
+
+// Rate limits by route path, routes that aren't listed aren't limited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    routes: HashMap<String, RouteRateLimits>,
+}
+
+impl RateLimitConfig {
+    pub fn route(&self, path: &str) -> Option<&RouteRateLimits> {
+        self.routes.get(path)
+    }
+
+    pub fn set_route(&mut self, path: &str, limits: RouteRateLimits) {
+        self.routes.insert(path.to_owned(), limits);
+    }
+
+    // Replaces the limits of the routes listed in "/login=ip:30/60,email:10/60;/signup=ip:10/60".
+    // A route without any limit ("/signup=") is no longer limited.
+    pub fn apply_overrides(&mut self, overrides: &str) -> Result<()> {
+        for route in overrides
+            .split(';')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+        {
+            let (path, limits) = route
+                .split_once('=')
+                .ok_or_else(|| eyre!("Route rate limits must look like <path>=<limits>"))?;
+            let limits: RouteRateLimits = limits.parse()?;
+            match limits == RouteRateLimits::default() {
+                true => self.routes.remove(path.trim()),
+                false => self.routes.insert(path.trim().to_owned(), limits),
+            };
+        }
+        Ok(())
+    }
+}
+
+// Argon2 makes the password routes the expensive ones, and the email sending ones could flood
+// anyone's inbox. /verify-token isn't limited, every call to it comes from the app service.
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        let limits = |per_ip, per_email: Option<u32>| RouteRateLimits {
+            per_ip: Some(RateLimit {
+                burst: per_ip,
+                period_seconds: 60,
+            }),
+            per_email: per_email.map(|burst| RateLimit {
+                burst,
+                period_seconds: 60,
+            }),
+        };
+        let routes = HashMap::from([
+            ("/signup".to_owned(), limits(10, Some(5))),
+            ("/login".to_owned(), limits(30, Some(10))),
+            ("/verify-2fa".to_owned(), limits(30, Some(10))),
+            ("/verify-2fa/recovery".to_owned(), limits(30, Some(10))),
+            ("/resend-2fa".to_owned(), limits(30, Some(10))),
+            ("/login/magic-link".to_owned(), limits(10, Some(3))),
+            ("/forgot-password".to_owned(), limits(10, Some(3))),
+            ("/reset-password".to_owned(), limits(10, Some(5))),
+            ("/resend-verification-email".to_owned(), limits(10, Some(3))),
+            ("/change-password".to_owned(), limits(10, None)),
+            ("/change-email".to_owned(), limits(10, None)),
+            ("/account".to_owned(), limits(10, None)),
+            ("/recovery-codes".to_owned(), limits(10, None)),
+            ("/totp/enroll".to_owned(), limits(10, None)),
+            ("/totp/confirm".to_owned(), limits(10, None)),
+        ]);
+        Self { routes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        burst: 3,
+        period_seconds: 3,
+    };
+
+    #[test]
+    fn burst_should_be_allowed_then_refilled_one_at_a_time() {
+        let now = 1_000_000;
+        let mut tat = None;
+        for remaining in [2, 1, 0] {
+            let decision = LIMIT.check(tat, now);
+            assert!(decision.allowed);
+            assert_eq!(decision.remaining, remaining);
+            tat = Some(decision.tat);
+        }
+
+        let decision = LIMIT.check(tat, now);
+        assert!(!decision.allowed);
+        assert_eq!(decision.retry_after, 1000);
+        assert_eq!(decision.reset_after, 3000);
+
+        let decision = LIMIT.check(tat, now + 1000);
+        assert!(decision.allowed);
+        assert_eq!(decision.remaining, 0);
+        assert_eq!(LIMIT.check(tat, now + 3000).remaining, 2);
+    }
+
+    #[test]
+    fn limits_should_parse() {
+        assert_eq!("10/60".parse::<RateLimit>().unwrap().burst, 10);
+        for test in ["", "10", "0/60", "10/0", "a/60"] {
+            assert!(test.parse::<RateLimit>().is_err());
+        }
+
+        let limits: RouteRateLimits = "ip:30/60, email:10/60".parse().unwrap();
+        assert_eq!(limits.per_ip.unwrap().burst, 30);
+        assert_eq!(limits.per_email.unwrap().burst, 10);
+        assert!("user:10/60".parse::<RouteRateLimits>().is_err());
+    }
+
+    #[test]
+    fn overrides_should_replace_routes() {
+        let mut config = RateLimitConfig::default();
+        config
+            .apply_overrides("/login=ip:5/10;/signup=;/hello=email:1/1")
+            .unwrap();
+
+        let login = config.route("/login").unwrap();
+        assert_eq!(login.per_ip.unwrap().burst, 5);
+        assert_eq!(login.per_email, None);
+        assert_eq!(config.route("/signup"), None);
+        assert!(config.route("/hello").is_some());
+        assert!(config.route("/forgot-password").is_some());
+        assert_eq!(config.route("/verify-token"), None);
+        assert!(config.route("/verify-2fa/recovery").is_some());
+        assert!(config.apply_overrides("/login").is_err());
+    }
+}
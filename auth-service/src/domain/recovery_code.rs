@@ -0,0 +1,92 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Uniform, rngs::OsRng, Rng};
+use secrecy::{ExposeSecret, Secret};
+
+// two groups of 5 separated by a dash, easy to read out and type in
+const RECOVERY_CODE_GROUP_LENGTH: usize = 5;
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+#[derive(Debug, Clone)]
+pub struct RecoveryCode(Secret<String>);
+
+impl RecoveryCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        // people copy these from paper, so case and surrounding spaces don't matter
+        let code = code.expose_secret().trim().to_ascii_lowercase();
+        if !Self::validate(&code) {
+            return Err(eyre!("Invalid recovery code!"));
+        }
+        Ok(Self(Secret::new(code)))
+    }
+
+    fn validate(s: &str) -> bool {
+        let Some((first, second)) = s.split_once('-') else {
+            return false;
+        };
+        [first, second].iter().all(|group| {
+            group.len() == RECOVERY_CODE_GROUP_LENGTH
+                && group
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        })
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        let alphabet = Uniform::from(0..RECOVERY_CODE_ALPHABET.len());
+        let group = || -> String {
+            (0..RECOVERY_CODE_GROUP_LENGTH)
+                .map(|_| char::from(RECOVERY_CODE_ALPHABET[OsRng.sample(alphabet)]))
+                .collect()
+        };
+        Self(Secret::new(format!("{}-{}", group(), group())))
+    }
+}
+
+impl PartialEq for RecoveryCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for RecoveryCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecoveryCode;
+    use secrecy::{ExposeSecret, Secret};
+
+    #[test]
+    fn default_should_parse() {
+        let code = RecoveryCode::default();
+        let secret = code.as_ref().expose_secret().to_owned();
+        assert!(RecoveryCode::parse(Secret::new(secret)).is_ok());
+    }
+
+    #[test]
+    fn parse_should_ignore_case_and_spaces() {
+        let code = RecoveryCode::parse(Secret::new(" AbCd1-2345Z ".to_owned())).unwrap();
+        assert_eq!(code.as_ref().expose_secret(), "abcd1-2345z");
+    }
+
+    #[test]
+    fn invalid_input_should_fail() {
+        let test_case = [
+            "",
+            "abcde12345",
+            "abcd-12345",
+            "abcde-123456",
+            "abcde_12345",
+            "abc!e-12345",
+        ];
+        for test in test_case {
+            let response = RecoveryCode::parse(Secret::new(test.to_owned()));
+            assert!(response.is_err());
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+
+const REFRESH_TOKEN_LENGTH: usize = 64;
+
+// Opaque token - unlike the JWT there's nothing to decode here, the store is the source of truth.
+#[derive(Debug, Clone)]
+pub struct RefreshToken(Secret<String>);
+
+impl RefreshToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if !Self::validate(token.expose_secret()) {
+            return Err(eyre!("Invalid refresh token!"));
+        }
+        Ok(Self(token))
+    }
+
+    fn validate(s: &str) -> bool {
+        s.len() == REFRESH_TOKEN_LENGTH && s.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+}
+
+impl Default for RefreshToken {
+    fn default() -> Self {
+        let token: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(Secret::new(token))
+    }
+}
+
+impl PartialEq for RefreshToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for RefreshToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RefreshToken;
+    use secrecy::{ExposeSecret, Secret};
+
+    #[test]
+    fn default_should_parse() {
+        let token = RefreshToken::default();
+        let secret = token.as_ref().expose_secret().to_owned();
+        assert!(RefreshToken::parse(Secret::new(secret)).is_ok());
+    }
+
+    #[test]
+    fn default_should_be_unique() {
+        assert_ne!(RefreshToken::default(), RefreshToken::default());
+    }
+
+    #[test]
+    fn invalid_input_should_fail() {
+        let too_long = "a".repeat(65);
+        let special = format!("{}!", "a".repeat(63));
+        let test_case = ["", "token", &too_long, &special];
+        for test in test_case {
+            let response = RefreshToken::parse(Secret::new(test.to_owned()));
+            assert!(response.is_err());
+        }
+    }
+}
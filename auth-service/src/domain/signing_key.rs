@@ -0,0 +1,501 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use jsonwebtoken::{
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header,
+};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use rand::{rngs::OsRng, RngCore};
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs8::{DecodePrivateKey, EncodePrivateKey, LineEnding},
+    traits::PublicKeyParts,
+    RsaPrivateKey,
+};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+// kid of the shared secret key, it is never published so it doesn't need to be unique
+const SHARED_SECRET_KEY_ID: &str = "default";
+const RSA_KEY_BITS: usize = 2048;
+const SHARED_SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SigningAlgorithm {
+    // shared secret, only for deployments that didn't set up a key pair
+    HS256,
+    RS256,
+    ES256,
+    EdDSA,
+}
+
+impl SigningAlgorithm {
+    fn key_algorithm(self) -> KeyAlgorithm {
+        match self {
+            Self::HS256 => KeyAlgorithm::HS256,
+            Self::RS256 => KeyAlgorithm::RS256,
+            Self::ES256 => KeyAlgorithm::ES256,
+            Self::EdDSA => KeyAlgorithm::EdDSA,
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            Self::HS256 => Algorithm::HS256,
+            Self::RS256 => Algorithm::RS256,
+            Self::ES256 => Algorithm::ES256,
+            Self::EdDSA => Algorithm::EdDSA,
+        }
+    }
+}
+
+impl FromStr for SigningAlgorithm {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "hs256" => Ok(Self::HS256),
+            "rs256" => Ok(Self::RS256),
+            "es256" => Ok(Self::ES256),
+            "eddsa" => Ok(Self::EdDSA),
+            _ => Err(eyre!("Unknown signing algorithm: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for SigningAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::HS256 => "HS256",
+            Self::RS256 => "RS256",
+            Self::ES256 => "ES256",
+            Self::EdDSA => "EdDSA",
+        };
+        f.write_str(name)
+    }
+}
+
+// Where a key is in its rotation. Only the active key signs, every key but a retired one is trusted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyState {
+    // published ahead of time so downstream services already cache it when it becomes active
+    Next,
+    Active,
+    // stopped signing, kept until the tokens it signed have expired
+    Retiring,
+    Retired,
+}
+
+impl KeyState {
+    pub fn is_trusted(self) -> bool {
+        self != Self::Retired
+    }
+}
+
+impl FromStr for KeyState {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "next" => Ok(Self::Next),
+            "active" => Ok(Self::Active),
+            "retiring" => Ok(Self::Retiring),
+            "retired" => Ok(Self::Retired),
+            _ => Err(eyre!("Unknown key state: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for KeyState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Next => "next",
+            Self::Active => "active",
+            Self::Retiring => "retiring",
+            Self::Retired => "retired",
+        };
+        f.write_str(name)
+    }
+}
+
+// A key that signs JWTs, along with what it takes to check them.
+#[derive(Clone)]
+pub struct SigningKey {
+    kid: String,
+    algorithm: SigningAlgorithm,
+    // the PEM private key, or the shared secret, kept so the key can be stored
+    material: Secret<String>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    // None for a shared secret, anyone holding it could sign tokens so it is never published
+    jwk: Option<Jwk>,
+}
+
+impl SigningKey {
+    // Reads a PEM private key (PKCS#8, or PKCS#1 for RSA). Without a kid, the RFC 7638 thumbprint of the public key is used.
+    pub fn from_pem(algorithm: SigningAlgorithm, pem: &str, kid: Option<String>) -> Result<Self> {
+        let (encoding_key, parameters) = match algorithm {
+            SigningAlgorithm::HS256 => {
+                return Err(eyre!("HS256 keys are shared secrets, not PEM keys"))
+            }
+            SigningAlgorithm::RS256 => {
+                let key = RsaPrivateKey::from_pkcs8_pem(pem)
+                    .or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+                    .wrap_err("Signing key is not a valid RSA private key")?;
+                let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                    e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+                });
+                let encoding_key = EncodingKey::from_rsa_pem(pem.as_bytes())
+                    .wrap_err("Fail to load RSA signing key")?;
+                (encoding_key, parameters)
+            }
+            SigningAlgorithm::ES256 => {
+                let key = p256::SecretKey::from_pkcs8_pem(pem)
+                    .wrap_err("Signing key is not a valid P-256 PKCS#8 private key")?;
+                let point = key.public_key().to_encoded_point(false);
+                let (Some(x), Some(y)) = (point.x(), point.y()) else {
+                    return Err(eyre!("P-256 public key is not uncompressed"));
+                };
+                let parameters = AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                    y: URL_SAFE_NO_PAD.encode(y),
+                });
+                let encoding_key = EncodingKey::from_ec_pem(pem.as_bytes())
+                    .wrap_err("Fail to load P-256 signing key")?;
+                (encoding_key, parameters)
+            }
+            SigningAlgorithm::EdDSA => {
+                let key = ed25519_dalek::SigningKey::from_pkcs8_pem(pem)
+                    .wrap_err("Signing key is not a valid Ed25519 PKCS#8 private key")?;
+                let parameters = AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(key.verifying_key().as_bytes()),
+                });
+                let encoding_key = EncodingKey::from_ed_pem(pem.as_bytes())
+                    .wrap_err("Fail to load Ed25519 signing key")?;
+                (encoding_key, parameters)
+            }
+        };
+
+        let kid = kid.unwrap_or_else(|| thumbprint(&parameters));
+        let jwk = Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(algorithm.key_algorithm()),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        };
+        let decoding_key =
+            DecodingKey::from_jwk(&jwk).wrap_err("Fail to load signing key public half")?;
+
+        Ok(Self {
+            kid,
+            algorithm,
+            material: Secret::new(pem.to_owned()),
+            encoding_key,
+            decoding_key,
+            jwk: Some(jwk),
+        })
+    }
+
+    // HS256 with a shared secret, for deployments that didn't set up a key pair.
+    pub fn from_secret(secret: &Secret<String>, kid: Option<String>) -> Self {
+        let bytes = secret.expose_secret().as_bytes();
+        Self {
+            kid: kid.unwrap_or_else(|| SHARED_SECRET_KEY_ID.to_owned()),
+            algorithm: SigningAlgorithm::HS256,
+            material: secret.clone(),
+            encoding_key: EncodingKey::from_secret(bytes),
+            decoding_key: DecodingKey::from_secret(bytes),
+            jwk: None,
+        }
+    }
+
+    // Loads a key back from what `material` returned.
+    pub fn from_material(
+        algorithm: SigningAlgorithm,
+        material: &Secret<String>,
+        kid: String,
+    ) -> Result<Self> {
+        match algorithm {
+            SigningAlgorithm::HS256 => Ok(Self::from_secret(material, Some(kid))),
+            _ => Self::from_pem(algorithm, material.expose_secret(), Some(kid)),
+        }
+    }
+
+    // A fresh key, used when rotating.
+    pub fn generate(algorithm: SigningAlgorithm) -> Result<Self> {
+        let pem = match algorithm {
+            SigningAlgorithm::HS256 => {
+                let mut bytes = [0u8; SHARED_SECRET_LENGTH];
+                OsRng.fill_bytes(&mut bytes);
+                // shared secrets can't be told apart by a thumbprint without leaking a hash of them
+                let secret = Secret::new(URL_SAFE_NO_PAD.encode(bytes));
+                return Ok(Self::from_secret(&secret, Some(Uuid::new_v4().to_string())));
+            }
+            SigningAlgorithm::RS256 => RsaPrivateKey::new(&mut OsRng, RSA_KEY_BITS)
+                .wrap_err("Fail to generate RSA key")?
+                .to_pkcs8_pem(LineEnding::LF),
+            SigningAlgorithm::ES256 => {
+                p256::SecretKey::random(&mut OsRng).to_pkcs8_pem(LineEnding::LF)
+            }
+            SigningAlgorithm::EdDSA => {
+                let mut bytes = [0u8; ed25519_dalek::SECRET_KEY_LENGTH];
+                OsRng.fill_bytes(&mut bytes);
+                ed25519_dalek::SigningKey::from_bytes(&bytes).to_pkcs8_pem(LineEnding::LF)
+            }
+        }
+        .wrap_err("Fail to encode generated key as PEM")?;
+        Self::from_pem(algorithm, &pem, None)
+    }
+
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm.algorithm()
+    }
+
+    pub fn signing_algorithm(&self) -> SigningAlgorithm {
+        self.algorithm
+    }
+
+    pub fn material(&self) -> &Secret<String> {
+        &self.material
+    }
+
+    pub fn header(&self) -> Header {
+        let mut header = Header::new(self.algorithm());
+        header.kid = Some(self.kid.clone());
+        header
+    }
+
+    pub fn encoding_key(&self) -> &EncodingKey {
+        &self.encoding_key
+    }
+
+    pub fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+
+    // The public key as published in the JWKS.
+    pub fn jwk(&self) -> Option<&Jwk> {
+        self.jwk.as_ref()
+    }
+}
+
+impl fmt::Debug for SigningKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("kid", &self.kid)
+            .field("algorithm", &self.algorithm)
+            .finish_non_exhaustive()
+    }
+}
+
+// RFC 7638: SHA-256 over the required members of the public key, in lexicographic order.
+fn thumbprint(parameters: &AlgorithmParameters) -> String {
+    let members = match parameters {
+        AlgorithmParameters::RSA(key) => {
+            format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, key.e, key.n)
+        }
+        AlgorithmParameters::EllipticCurve(key) => format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            key.x, key.y
+        ),
+        AlgorithmParameters::OctetKeyPair(key) => {
+            format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, key.x)
+        }
+        AlgorithmParameters::OctetKey(key) => format!(r#"{{"k":"{}","kty":"oct"}}"#, key.value),
+    };
+    URL_SAFE_NO_PAD.encode(Sha256::digest(members.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonwebtoken::{decode, decode_header, encode, Validation};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct TestClaims {
+        sub: String,
+        exp: usize,
+    }
+
+    fn pem(algorithm: SigningAlgorithm) -> String {
+        let key = SigningKey::generate(algorithm).unwrap();
+        key.material().expose_secret().to_owned()
+    }
+
+    fn ec_pem() -> String {
+        pem(SigningAlgorithm::ES256)
+    }
+
+    fn ed_pem() -> String {
+        pem(SigningAlgorithm::EdDSA)
+    }
+
+    fn round_trip(key: &SigningKey) {
+        // a stored key has to come back as the same key
+        let key = &SigningKey::from_material(
+            key.signing_algorithm(),
+            key.material(),
+            key.kid().to_owned(),
+        )
+        .unwrap();
+
+        let claims = TestClaims {
+            sub: "test@test.com".to_owned(),
+            exp: 10_000_000_000,
+        };
+        let token = encode(&key.header(), &claims, key.encoding_key()).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some(key.kid()));
+        assert_eq!(header.alg, key.algorithm());
+
+        // downstream services only get the JWKS, so check with the published key as well
+        let published = DecodingKey::from_jwk(key.jwk().unwrap()).unwrap();
+        for decoding_key in [key.decoding_key(), &published] {
+            let data =
+                decode::<TestClaims>(&token, decoding_key, &Validation::new(key.algorithm()))
+                    .unwrap();
+            assert_eq!(data.claims, claims);
+        }
+    }
+
+    #[test]
+    fn rs256_key_should_sign_and_verify() {
+        let key = SigningKey::generate(SigningAlgorithm::RS256).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::RS256);
+        round_trip(&key);
+    }
+
+    #[test]
+    fn es256_key_should_sign_and_verify() {
+        let key = SigningKey::generate(SigningAlgorithm::ES256).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::ES256);
+        round_trip(&key);
+    }
+
+    #[test]
+    fn eddsa_key_should_sign_and_verify() {
+        let key = SigningKey::generate(SigningAlgorithm::EdDSA).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::EdDSA);
+        round_trip(&key);
+    }
+
+    #[test]
+    fn kid_should_default_to_thumbprint() {
+        let pem = ec_pem();
+        let key = SigningKey::from_pem(SigningAlgorithm::ES256, &pem, None).unwrap();
+        let again = SigningKey::from_pem(SigningAlgorithm::ES256, &pem, None).unwrap();
+        assert_eq!(key.kid(), again.kid());
+        assert_ne!(
+            key.kid(),
+            SigningKey::from_pem(SigningAlgorithm::ES256, &ec_pem(), None)
+                .unwrap()
+                .kid()
+        );
+
+        let key = SigningKey::from_pem(SigningAlgorithm::ES256, &pem, Some("2026-10".to_owned()))
+            .unwrap();
+        assert_eq!(key.kid(), "2026-10");
+        assert_eq!(key.jwk().unwrap().common.key_id.as_deref(), Some("2026-10"));
+    }
+
+    #[test]
+    fn thumbprint_should_match_rfc_7638_example() {
+        // RFC 7638 section 3.1
+        let parameters = AlgorithmParameters::RSA(RSAKeyParameters {
+            key_type: RSAKeyType::RSA,
+            n: "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw".to_owned(),
+            e: "AQAB".to_owned(),
+        });
+        assert_eq!(
+            thumbprint(&parameters),
+            "NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs"
+        );
+    }
+
+    #[test]
+    fn wrong_key_type_should_fail() {
+        assert!(SigningKey::from_pem(SigningAlgorithm::RS256, &ec_pem(), None).is_err());
+        assert!(SigningKey::from_pem(SigningAlgorithm::ES256, &ed_pem(), None).is_err());
+        assert!(SigningKey::from_pem(SigningAlgorithm::EdDSA, &ec_pem(), None).is_err());
+        assert!(SigningKey::from_pem(SigningAlgorithm::ES256, "not a key", None).is_err());
+    }
+
+    #[test]
+    fn generated_shared_secrets_should_differ() {
+        let key = SigningKey::generate(SigningAlgorithm::HS256).unwrap();
+        let other = SigningKey::generate(SigningAlgorithm::HS256).unwrap();
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert_ne!(key.kid(), other.kid());
+        assert_ne!(
+            key.material().expose_secret(),
+            other.material().expose_secret()
+        );
+        assert!(key.jwk().is_none());
+    }
+
+    #[test]
+    fn parse_key_state() {
+        for state in [
+            KeyState::Next,
+            KeyState::Active,
+            KeyState::Retiring,
+            KeyState::Retired,
+        ] {
+            assert_eq!(state.to_string().parse::<KeyState>().unwrap(), state);
+        }
+        assert!(!KeyState::Retired.is_trusted());
+        assert!(KeyState::Retiring.is_trusted());
+        assert!("expired".parse::<KeyState>().is_err());
+    }
+
+    #[test]
+    fn shared_secret_should_not_be_published() {
+        let key = SigningKey::from_secret(&Secret::new("secret".to_owned()), None);
+        assert_eq!(key.algorithm(), Algorithm::HS256);
+        assert_eq!(key.kid(), SHARED_SECRET_KEY_ID);
+        assert!(key.jwk().is_none());
+    }
+
+    #[test]
+    fn parse_signing_algorithm() {
+        assert_eq!(
+            "RS256".parse::<SigningAlgorithm>().unwrap(),
+            SigningAlgorithm::RS256
+        );
+        assert_eq!(
+            "es256".parse::<SigningAlgorithm>().unwrap(),
+            SigningAlgorithm::ES256
+        );
+        assert_eq!(
+            "EdDSA".parse::<SigningAlgorithm>().unwrap(),
+            SigningAlgorithm::EdDSA
+        );
+        assert_eq!(
+            "HS256".parse::<SigningAlgorithm>().unwrap(),
+            SigningAlgorithm::HS256
+        );
+        assert!("RS512".parse::<SigningAlgorithm>().is_err());
+    }
+}
@@ -0,0 +1,238 @@
+use aes_gcm::aead::OsRng;
+use color_eyre::eyre::{eyre, Context, Result};
+use image::{DynamicImage, ImageFormat, Luma};
+use qrcode::QrCode;
+use rand::RngCore;
+use secrecy::{ExposeSecret, Secret};
+use std::io::Cursor;
+use totp_rs::{Algorithm, Secret as RawSecret, TOTP};
+
+use super::{email::Email, two_fa_code::TwoFACode};
+use crate::utils::{
+    constants::{TOTP_ISSUER, TOTP_STEP_SECONDS},
+    crypto,
+};
+
+// 160 bits, what RFC 4226 recommends and what authenticator apps expect
+const TOTP_SECRET_LENGTH: usize = 20;
+pub(crate) const TOTP_DIGITS: usize = 6;
+
+// The shared secret of an authenticator app, kept base32 encoded like the apps show it.
+#[derive(Debug, Clone)]
+pub struct TotpSecret(Secret<String>);
+
+impl TotpSecret {
+    pub fn parse(secret: Secret<String>) -> Result<Self> {
+        let bytes = RawSecret::Encoded(secret.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32!"))?;
+        if bytes.len() < 16 {
+            return Err(eyre!("TOTP secret is too short!"));
+        }
+        Ok(Self(secret))
+    }
+
+    fn totp(&self, account_name: String) -> Result<TOTP> {
+        let bytes = RawSecret::Encoded(self.0.expose_secret().to_owned())
+            .to_bytes()
+            .map_err(|_| eyre!("TOTP secret is not valid base32!"))?;
+        Ok(TOTP::new_unchecked(
+            Algorithm::SHA1,
+            TOTP_DIGITS,
+            0,
+            TOTP_STEP_SECONDS,
+            bytes,
+            Some(TOTP_ISSUER.to_owned()),
+            account_name,
+        ))
+    }
+
+    pub fn generate(&self, time: u64) -> Result<Secret<String>> {
+        Ok(Secret::new(self.totp(String::new())?.generate(time)))
+    }
+
+    // Returns the time step the code belongs to, looking `skew` steps before and after `time`.
+    pub fn verify(&self, code: &TwoFACode, time: u64, skew: u8) -> Result<Option<u64>> {
+        let totp = self.totp(String::new())?;
+        let current = time / TOTP_STEP_SECONDS;
+        let skew = u64::from(skew);
+        let step = (current.saturating_sub(skew)..=current + skew)
+            .find(|step| totp.generate(step * TOTP_STEP_SECONDS) == *code.as_ref().expose_secret());
+        Ok(step)
+    }
+
+    pub fn otpauth_uri(&self, email: &Email) -> Result<String> {
+        let totp = self.totp(email.as_ref().expose_secret().to_owned())?;
+        Ok(totp.get_url())
+    }
+
+    pub fn qr_code_png(&self, email: &Email) -> Result<Vec<u8>> {
+        let code = QrCode::new(self.otpauth_uri(email)?).wrap_err("Fail to encode QR code")?;
+        let image = code.render::<Luma<u8>>().build();
+
+        let mut png = Vec::new();
+        DynamicImage::ImageLuma8(image)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .wrap_err("Fail to render QR code as PNG")?;
+        Ok(png)
+    }
+
+    pub fn encrypt(&self, key: &Secret<String>) -> Result<EncryptedTotpSecret> {
+        crypto::encrypt(key, self.0.expose_secret().as_bytes()).map(EncryptedTotpSecret)
+    }
+}
+
+impl Default for TotpSecret {
+    fn default() -> Self {
+        let mut bytes = vec![0u8; TOTP_SECRET_LENGTH];
+        OsRng.fill_bytes(&mut bytes);
+        let RawSecret::Encoded(encoded) = RawSecret::Raw(bytes).to_encoded() else {
+            unreachable!("to_encoded always returns an encoded secret")
+        };
+        Self(Secret::new(encoded))
+    }
+}
+
+impl AsRef<Secret<String>> for TotpSecret {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// A TOTP secret as it is stored: base64 of the nonce followed by the AES-GCM ciphertext.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptedTotpSecret(String);
+
+impl EncryptedTotpSecret {
+    pub fn parse(data: String) -> Result<Self> {
+        crypto::check_encrypted(&data).wrap_err("Invalid encrypted TOTP secret")?;
+        Ok(Self(data))
+    }
+
+    pub fn decrypt(&self, key: &Secret<String>) -> Result<TotpSecret> {
+        let plaintext = crypto::decrypt(key, &self.0).wrap_err("Fail to decrypt TOTP secret")?;
+        let secret = String::from_utf8(plaintext).wrap_err("Decrypted TOTP secret is not UTF-8")?;
+        TotpSecret::parse(Secret::new(secret))
+    }
+}
+
+impl AsRef<str> for EncryptedTotpSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> Secret<String> {
+        Secret::new("test-encryption-key".to_owned())
+    }
+
+    fn code(secret: &TotpSecret, time: u64) -> TwoFACode {
+        TwoFACode::parse_totp(secret.generate(time).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn default_should_parse() {
+        let secret = TotpSecret::default();
+        assert!(TotpSecret::parse(secret.as_ref().clone()).is_ok());
+    }
+
+    #[test]
+    fn invalid_secret_should_fail() {
+        let test_cases = ["", "not base32!", "JBSWY3DP"];
+        for test in test_cases {
+            assert!(TotpSecret::parse(Secret::new(test.to_owned())).is_err());
+        }
+    }
+
+    #[test]
+    fn should_match_rfc_6238_test_vector() {
+        // RFC 6238 appendix B, SHA1 secret "12345678901234567890"
+        let secret =
+            TotpSecret::parse(Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())).unwrap();
+        assert_eq!(secret.generate(59).unwrap().expose_secret(), "287082");
+        assert_eq!(
+            secret.generate(1111111109).unwrap().expose_secret(),
+            "081804"
+        );
+    }
+
+    #[test]
+    fn verify_should_accept_codes_within_skew() {
+        let secret = TotpSecret::default();
+        let time = 1_000_000_020;
+        let step = time / TOTP_STEP_SECONDS;
+
+        let result = secret.verify(&code(&secret, time), time, 1).unwrap();
+        assert_eq!(result, Some(step));
+        let result = secret
+            .verify(&code(&secret, time - TOTP_STEP_SECONDS), time, 1)
+            .unwrap();
+        assert_eq!(result, Some(step - 1));
+        let result = secret
+            .verify(&code(&secret, time + TOTP_STEP_SECONDS), time, 1)
+            .unwrap();
+        assert_eq!(result, Some(step + 1));
+    }
+
+    #[test]
+    fn verify_should_reject_codes_outside_skew() {
+        // the RFC 6238 secret, whose codes for these three steps are known to differ
+        let secret =
+            TotpSecret::parse(Secret::new("GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ".to_owned())).unwrap();
+        let time = 1111111109;
+        let old = code(&secret, time - 2 * TOTP_STEP_SECONDS);
+        assert_ne!(old, code(&secret, time));
+        assert_ne!(old, code(&secret, time - TOTP_STEP_SECONDS));
+
+        assert_eq!(secret.verify(&old, time, 1).unwrap(), None);
+        assert_eq!(
+            secret.verify(&old, time, 2).unwrap(),
+            Some(time / TOTP_STEP_SECONDS - 2)
+        );
+    }
+
+    #[test]
+    fn otpauth_uri_should_contain_secret_and_issuer() {
+        let secret = TotpSecret::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let uri = secret.otpauth_uri(&email).unwrap();
+        assert!(uri.starts_with("otpauth://totp/"));
+        assert!(uri.contains(&format!("secret={}", secret.as_ref().expose_secret())));
+        assert!(uri.contains("test%40test.com"));
+    }
+
+    #[test]
+    fn qr_code_should_be_png() {
+        let secret = TotpSecret::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let png = secret.qr_code_png(&email).unwrap();
+        assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+    }
+
+    #[test]
+    fn encrypted_secret_should_round_trip() {
+        let secret = TotpSecret::default();
+        let encrypted = secret.encrypt(&key()).unwrap();
+        assert!(!encrypted
+            .as_ref()
+            .contains(secret.as_ref().expose_secret().as_str()));
+
+        let parsed = EncryptedTotpSecret::parse(encrypted.as_ref().to_owned()).unwrap();
+        let decrypted = parsed.decrypt(&key()).unwrap();
+        assert_eq!(
+            decrypted.as_ref().expose_secret(),
+            secret.as_ref().expose_secret()
+        );
+    }
+
+    #[test]
+    fn wrong_key_should_fail_to_decrypt() {
+        let encrypted = TotpSecret::default().encrypt(&key()).unwrap();
+        let result = encrypted.decrypt(&Secret::new("another-key".to_owned()));
+        assert!(result.is_err());
+    }
+}
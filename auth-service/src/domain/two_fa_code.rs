@@ -1,36 +1,62 @@
 use color_eyre::eyre::{eyre, Result};
-use rand::{thread_rng, Rng};
+use rand::{rngs::OsRng, Rng};
 use secrecy::{ExposeSecret, Secret};
-use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::domain::totp_secret::TOTP_DIGITS;
+use crate::domain::two_factor_policy::{CodeAlphabet, TwoFactorPolicy};
+use crate::utils::{
+    constants::{TWO_FACTOR_POLICY, TWO_FA_CODE_HASH_KEY},
+    crypto::{keyed_hash, verify_keyed_hash},
+};
+
+const HASH_PREFIX: &str = "hmac-sha256:";
+
+// Only built through `parse` or `generate`, so every code follows a policy. Requests take the
+// code as a plain string for that reason.
+#[derive(Debug, Clone)]
 pub struct TwoFACode(Secret<String>);
 
 impl TwoFACode {
+    // codes are checked against TWO_FACTOR_POLICY, the one they were generated with
     pub fn parse(code: Secret<String>) -> Result<Self> {
-        // Code must be exactly 6 character long - this may include padding 0's
-        if !Self::validate(code.expose_secret()) {
-            return Err(eyre!("Invalid code length!"));
+        Self::parse_with(code, &TWO_FACTOR_POLICY)
+    }
+
+    // authenticator apps always show 6 digits, whatever the policy says of emailed codes
+    pub fn parse_totp(code: Secret<String>) -> Result<Self> {
+        let policy = TwoFactorPolicy {
+            code_length: TOTP_DIGITS,
+            alphabet: CodeAlphabet::Digits,
+            ..TwoFactorPolicy::default()
+        };
+        Self::parse_with(code, &policy)
+    }
+
+    pub fn parse_with(code: Secret<String>, policy: &TwoFactorPolicy) -> Result<Self> {
+        let code = policy.alphabet.normalize(code.expose_secret());
+        if !Self::validate(&code, policy) {
+            return Err(eyre!("Invalid 2FA code!"));
         }
-        Ok(Self(code))
+        Ok(Self(Secret::new(code)))
+    }
+
+    fn validate(s: &str, policy: &TwoFactorPolicy) -> bool {
+        s.chars().count() == policy.code_length && s.chars().all(|c| policy.alphabet.contains(c))
     }
 
-    fn validate(s: &str) -> bool {
-        s.len() == 6 && !s.chars().any(|v| !v.is_ascii_digit())
+    // every character is drawn from the OS CSPRNG
+    pub fn generate(policy: &TwoFactorPolicy) -> Self {
+        let symbols = policy.alphabet.symbols();
+        let code = (0..policy.code_length)
+            .map(|_| symbols[OsRng.gen_range(0..symbols.len())] as char)
+            .collect();
+        Self(Secret::new(code))
     }
 }
 
 impl Default for TwoFACode {
     fn default() -> Self {
-        let mut pad = "00000".to_owned();
-        let mut rng = thread_rng();
-        // generate the code as number then cast it into string
-        let code = rng.gen_range(0..=999999).to_string();
-        // append the number to the pad
-        pad.push_str(&code);
-        // truncate the pad off to exact 6 characters
-        let secret = Secret::new(pad.split_off(pad.len() - 6));
-        Self(secret)
+        Self::generate(&TWO_FACTOR_POLICY)
     }
 }
 
@@ -48,11 +74,46 @@ impl AsRef<Secret<String>> for TwoFACode {
     }
 }
 
+// What the stores keep of a 2FA code, its HMAC under TWO_FA_CODE_HASH_KEY.
+#[derive(Debug, Clone)]
+pub struct StoredTwoFACode(String);
+
+impl StoredTwoFACode {
+    pub fn hash(code: &TwoFACode) -> Self {
+        Self(keyed_hash(
+            &TWO_FA_CODE_HASH_KEY,
+            code.0.expose_secret().as_bytes(),
+        ))
+    }
+
+    // reads back what `as_stored` gave the store
+    pub fn parse(stored: String) -> Result<Self> {
+        stored
+            .strip_prefix(HASH_PREFIX)
+            .map(|hash| Self(hash.to_owned()))
+            .ok_or_else(|| eyre!("Stored 2FA code is not hashed"))
+    }
+
+    pub fn as_stored(&self) -> String {
+        format!("{HASH_PREFIX}{}", self.0)
+    }
+
+    // compares in constant time, so the time taken gives nothing away about the code
+    pub fn matches(&self, code: &TwoFACode) -> bool {
+        verify_keyed_hash(
+            &TWO_FA_CODE_HASH_KEY,
+            code.0.expose_secret().as_bytes(),
+            &self.0,
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::TwoFACode;
+    use super::{StoredTwoFACode, TwoFACode};
+    use crate::domain::two_factor_policy::{CodeAlphabet, TwoFactorPolicy};
     use rstest::*;
-    use secrecy::Secret;
+    use secrecy::{ExposeSecret, Secret};
 
     #[test]
     fn parse_should_pass() {
@@ -138,4 +199,64 @@ mod tests {
             assert!(response.is_err());
         }
     }
+
+    #[test]
+    fn stored_code_should_be_hashed() {
+        let code = TwoFACode::default();
+        let stored = StoredTwoFACode::hash(&code);
+        let value = stored.as_stored();
+        assert!(!value.contains(code.as_ref().expose_secret().as_str()));
+
+        let stored = StoredTwoFACode::parse(value).unwrap();
+        assert!(stored.matches(&code));
+        let other = TwoFACode::parse(Secret::new("000000".to_owned())).unwrap();
+        assert_eq!(stored.matches(&other), code == other);
+    }
+
+    #[test]
+    fn unhashed_stored_code_should_fail() {
+        assert!(StoredTwoFACode::parse("123456".to_owned()).is_err());
+    }
+
+    #[test]
+    fn generated_codes_should_follow_the_policy() {
+        let policy = TwoFactorPolicy {
+            code_length: 10,
+            alphabet: CodeAlphabet::CrockfordBase32,
+            ..TwoFactorPolicy::default()
+        };
+        let code = TwoFACode::generate(&policy);
+        let value = code.as_ref().expose_secret();
+        assert_eq!(value.len(), 10);
+        assert!(value.chars().all(|c| policy.alphabet.contains(c)));
+        assert!(TwoFACode::parse_with(code.as_ref().clone(), &policy).is_ok());
+
+        let default = TwoFACode::default();
+        assert_eq!(default.as_ref().expose_secret().len(), 6);
+        assert!(TwoFACode::parse_with(default.as_ref().clone(), &policy).is_err());
+    }
+
+    #[test]
+    fn totp_codes_should_parse_whatever_the_policy() {
+        let policy = TwoFactorPolicy {
+            code_length: 8,
+            ..TwoFactorPolicy::default()
+        };
+        let code = Secret::new("123456".to_owned());
+        assert!(TwoFACode::parse_with(code.clone(), &policy).is_err());
+        assert!(TwoFACode::parse_totp(code).is_ok());
+        assert!(TwoFACode::parse_totp(Secret::new("12345678".to_owned())).is_err());
+        assert!(TwoFACode::parse_totp(Secret::new("ABC123".to_owned())).is_err());
+    }
+
+    #[test]
+    fn base32_codes_should_parse_as_typed() {
+        let policy = TwoFactorPolicy {
+            alphabet: CodeAlphabet::CrockfordBase32,
+            ..TwoFactorPolicy::default()
+        };
+        let code = TwoFACode::parse_with(Secret::new("ab-c1lo".to_owned()), &policy).unwrap();
+        assert_eq!(code.as_ref().expose_secret(), "ABC110");
+        assert!(TwoFACode::parse_with(Secret::new("ABCUUU".to_owned()), &policy).is_err());
+    }
 }
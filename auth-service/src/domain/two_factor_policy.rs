@@ -0,0 +1,158 @@
+use color_eyre::eyre::{eyre, Report, Result};
+use std::str::FromStr;
+
+use crate::utils::constants::{
+    DEFAULT_TWO_FA_CODE_LENGTH, DEFAULT_TWO_FA_CODE_TTL_SECONDS, DEFAULT_TWO_FA_MAX_ATTEMPTS,
+    DEFAULT_TWO_FA_MAX_RESENDS,
+};
+
+// shorter codes are too easy to guess, longer ones too tedious to type
+const MIN_CODE_LENGTH: usize = 6;
+const MAX_CODE_LENGTH: usize = 16;
+
+const DIGITS: &[u8] = b"0123456789";
+// Crockford's base32 leaves out I, L, O and U, so codes can't be misread
+const CROCKFORD_BASE32: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CodeAlphabet {
+    #[default]
+    Digits,
+    CrockfordBase32,
+}
+
+impl CodeAlphabet {
+    pub fn symbols(&self) -> &'static [u8] {
+        match self {
+            Self::Digits => DIGITS,
+            Self::CrockfordBase32 => CROCKFORD_BASE32,
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        c.is_ascii() && self.symbols().contains(&(c as u8))
+    }
+
+    // Brings a code as the user typed it to the form it was generated in. Base32 codes are read
+    // case insensitive, with I and L taken as 1, O as 0, and hyphens left out.
+    pub fn normalize(&self, code: &str) -> String {
+        match self {
+            Self::Digits => code.to_owned(),
+            Self::CrockfordBase32 => code
+                .chars()
+                .filter(|c| *c != '-')
+                .map(|c| match c.to_ascii_uppercase() {
+                    'I' | 'L' => '1',
+                    'O' => '0',
+                    c => c,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for CodeAlphabet {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "digits" => Ok(Self::Digits),
+            "crockford-base32" | "base32" => Ok(Self::CrockfordBase32),
+            _ => Err(eyre!("Unknown 2FA code alphabet: {}", s)),
+        }
+    }
+}
+
+// How emailed 2FA codes look, how long they last, and how often a login attempt may guess one
+// or have a fresh one sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TwoFactorPolicy {
+    pub code_length: usize,
+    pub alphabet: CodeAlphabet,
+    pub ttl_seconds: i64,
+    pub max_attempts: u32,
+    pub max_resends: u32,
+}
+
+impl Default for TwoFactorPolicy {
+    fn default() -> Self {
+        Self {
+            code_length: DEFAULT_TWO_FA_CODE_LENGTH,
+            alphabet: CodeAlphabet::default(),
+            ttl_seconds: DEFAULT_TWO_FA_CODE_TTL_SECONDS,
+            max_attempts: DEFAULT_TWO_FA_MAX_ATTEMPTS,
+            max_resends: DEFAULT_TWO_FA_MAX_RESENDS,
+        }
+    }
+}
+
+impl TwoFactorPolicy {
+    pub fn validate(&self) -> Result<()> {
+        if !(MIN_CODE_LENGTH..=MAX_CODE_LENGTH).contains(&self.code_length) {
+            return Err(eyre!(
+                "2FA codes must be {} to {} characters long",
+                MIN_CODE_LENGTH,
+                MAX_CODE_LENGTH
+            ));
+        }
+        if self.ttl_seconds <= 0 {
+            return Err(eyre!("2FA codes must last a positive number of seconds"));
+        }
+        if self.max_attempts == 0 {
+            return Err(eyre!("2FA codes must allow at least one attempt"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodeAlphabet, TwoFactorPolicy};
+
+    #[test]
+    fn alphabet_should_parse() {
+        assert_eq!(
+            "digits".parse::<CodeAlphabet>().unwrap(),
+            CodeAlphabet::Digits
+        );
+        assert_eq!(
+            "Crockford-Base32".parse::<CodeAlphabet>().unwrap(),
+            CodeAlphabet::CrockfordBase32
+        );
+        assert!("hex".parse::<CodeAlphabet>().is_err());
+    }
+
+    #[test]
+    fn base32_codes_should_be_normalized() {
+        let alphabet = CodeAlphabet::CrockfordBase32;
+        assert_eq!(alphabet.normalize("abc-ilo"), "ABC110");
+        assert!(alphabet
+            .normalize("abc-ilo")
+            .chars()
+            .all(|c| alphabet.contains(c)));
+        assert!(!alphabet.contains('U'));
+        assert_eq!(CodeAlphabet::Digits.normalize("12-34"), "12-34");
+    }
+
+    #[test]
+    fn policy_should_be_validated() {
+        let policy = TwoFactorPolicy::default();
+        assert!(policy.validate().is_ok());
+        for invalid in [
+            TwoFactorPolicy {
+                code_length: 4,
+                ..policy
+            },
+            TwoFactorPolicy {
+                ttl_seconds: 0,
+                ..policy
+            },
+            TwoFactorPolicy {
+                max_attempts: 0,
+                ..policy
+            },
+        ] {
+            assert!(invalid.validate().is_err());
+        }
+    }
+}
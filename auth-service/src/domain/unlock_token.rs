@@ -0,0 +1,68 @@
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use secrecy::{ExposeSecret, Secret};
+
+const UNLOCK_TOKEN_LENGTH: usize = 32;
+
+#[derive(Debug, Clone)]
+pub struct UnlockToken(Secret<String>);
+
+impl UnlockToken {
+    pub fn parse(token: Secret<String>) -> Result<Self> {
+        if !Self::validate(token.expose_secret()) {
+            return Err(eyre!("Invalid unlock token!"));
+        }
+        Ok(Self(token))
+    }
+
+    fn validate(s: &str) -> bool {
+        s.len() == UNLOCK_TOKEN_LENGTH && s.chars().all(|c| c.is_ascii_alphanumeric())
+    }
+}
+
+impl Default for UnlockToken {
+    fn default() -> Self {
+        let token: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(UNLOCK_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(Secret::new(token))
+    }
+}
+
+impl PartialEq for UnlockToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl AsRef<Secret<String>> for UnlockToken {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnlockToken;
+    use secrecy::{ExposeSecret, Secret};
+
+    #[test]
+    fn default_should_parse() {
+        let token = UnlockToken::default();
+        let secret = token.as_ref().expose_secret().to_owned();
+        assert!(UnlockToken::parse(Secret::new(secret)).is_ok());
+    }
+
+    #[test]
+    fn invalid_input_should_fail() {
+        let too_long = "a".repeat(33);
+        let special = format!("{}-", "a".repeat(31));
+        let test_case = ["", "token", &too_long, &special];
+        for test in test_case {
+            let response = UnlockToken::parse(Secret::new(test.to_owned()));
+            assert!(response.is_err());
+        }
+    }
+}
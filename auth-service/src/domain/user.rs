@@ -1,32 +1,75 @@
 use super::{email::Email, password::Password};
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Report, Result};
 use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
-// #[derive(Debug, Clone, Default)]
-// pub enum UserRole {
-//     #[default]
-//     None = 0,
-//     Guest = 1,
-//     Member = 2,
-//     Admin = 3,
-// }
+// Roles are ordered, a route requiring a role lets every higher one in too.
+// Tokens issued before roles existed carry none.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum UserRole {
+    #[default]
+    None = 0,
+    Guest = 1,
+    Member = 2,
+    Admin = 3,
+}
+
+impl UserRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Guest => "guest",
+            Self::Member => "member",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for UserRole {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "guest" => Ok(Self::Guest),
+            "member" => Ok(Self::Member),
+            "admin" => Ok(Self::Admin),
+            _ => Err(eyre!("Unknown user role: {}", s)),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct User {
     email: Email,
     password: Password,
     requires_2fa: bool,
-    // user_role: UserRole,
+    email_verified: bool,
+    role: UserRole,
+    disabled: bool, // set by an admin, disabled accounts can't log in
 }
 
 impl User {
     // TODO: Talk about this?
-    pub(crate) fn new(email: Email, password: Password, requires_2fa: bool) -> Self {
+    pub(crate) fn new(
+        email: Email,
+        password: Password,
+        requires_2fa: bool,
+        email_verified: bool,
+        role: UserRole,
+        disabled: bool,
+    ) -> Self {
         Self {
             email,
             password,
             requires_2fa,
-            // user_role: UserRole::default(),
+            email_verified,
+            role,
+            disabled,
         }
     }
 
@@ -37,11 +80,14 @@ impl User {
     ) -> Result<User> {
         let email = Email::parse(email)?;
         let password = Password::parse(password)?;
+        // new accounts have to confirm they own the mailbox first.
         Ok(User {
             email,
             password,
             requires_2fa,
-            // user_role: UserRole::default(),
+            email_verified: false,
+            role: UserRole::Member,
+            disabled: false,
         })
     }
 
@@ -52,6 +98,39 @@ impl User {
     pub fn requires_2fa(&self) -> bool {
         self.requires_2fa
     }
+
+    pub fn email_verified(&self) -> bool {
+        self.email_verified
+    }
+
+    pub fn role(&self) -> UserRole {
+        self.role
+    }
+
+    pub fn disabled(&self) -> bool {
+        self.disabled
+    }
+}
+
+// What login does with accounts that haven't verified their email address yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum UnverifiedLoginPolicy {
+    #[default]
+    Deny,
+    // login works, but no refresh token is handed out, so the session ends with the JWT.
+    Restricted,
+}
+
+impl FromStr for UnverifiedLoginPolicy {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "deny" => Ok(Self::Deny),
+            "restricted" => Ok(Self::Restricted),
+            _ => Err(eyre!("Unknown unverified login policy: {}", s)),
+        }
+    }
 }
 
 impl AsRef<Email> for User {
@@ -65,3 +144,46 @@ impl AsRef<Password> for User {
         &self.password
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parsed_user_should_not_be_verified() {
+        let user = User::parse(
+            Secret::new("test@test.com".to_owned()),
+            Secret::new("Password123!".to_owned()),
+            false,
+        )
+        .unwrap();
+        assert!(!user.email_verified());
+        assert_eq!(user.role(), UserRole::Member);
+    }
+
+    #[test]
+    fn user_roles_should_be_ordered() {
+        assert!(UserRole::None < UserRole::Guest);
+        assert!(UserRole::Guest < UserRole::Member);
+        assert!(UserRole::Member < UserRole::Admin);
+        assert_eq!("Admin".parse::<UserRole>().unwrap(), UserRole::Admin);
+        assert_eq!(
+            UserRole::Guest.as_str().parse::<UserRole>().unwrap(),
+            UserRole::Guest
+        );
+        assert!("root".parse::<UserRole>().is_err());
+    }
+
+    #[test]
+    fn unverified_login_policy_should_parse() {
+        assert_eq!(
+            "deny".parse::<UnverifiedLoginPolicy>().unwrap(),
+            UnverifiedLoginPolicy::Deny
+        );
+        assert_eq!(
+            "Restricted".parse::<UnverifiedLoginPolicy>().unwrap(),
+            UnverifiedLoginPolicy::Restricted
+        );
+        assert!("allow".parse::<UnverifiedLoginPolicy>().is_err());
+    }
+}
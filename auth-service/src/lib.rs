@@ -1,16 +1,26 @@
 use app_state::AppState;
 use axum::{
+    extract::connect_info::{ConnectInfo, IntoMakeServiceWithConnectInfo},
     http::{HeaderValue, Method},
-    routing::{get, post, Router},
+    middleware::{self, AddExtension},
+    routing::{delete, get, post, Router},
     serve::Serve,
 };
 use redis::{Client, RedisResult};
-use routes::{hello, login, logout, signup, verify_2fa, verify_token};
+use routes::{
+    admin_router, cancel_email_change, change_email, change_password, confirm_email_change,
+    confirm_totp, delete_account, enroll_totp, forgot_password, hello, jwks, list_sessions, login,
+    logout, logout_all, magic_link_callback, refresh, regenerate_recovery_codes,
+    request_magic_link, resend_2fa, resend_verification_email, reset_password,
+    revoke_other_sessions, revoke_session, signup, unlock_account, verify_2fa, verify_email,
+    verify_recovery_code, verify_token,
+};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::io;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
+use utils::rate_limit::rate_limit;
 use utils::tracing::{make_span_with_request_id, on_request, on_response};
 
 pub mod app_state;
@@ -20,7 +30,10 @@ pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: SocketAddr,
 }
 
@@ -35,18 +48,49 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
         let router = Router::new()
             .nest_service("/", ServeDir::new("assets"))
             .route("/hello", get(hello))
+            .route("/.well-known/jwks.json", get(jwks))
             .route("/signup", post(signup))
             .route("/login", post(login))
+            .route("/login/magic-link", post(request_magic_link))
+            .route("/login/magic-link/callback", get(magic_link_callback))
             .route("/logout", post(logout))
+            .route("/logout-all", post(logout_all))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/verify-2fa/recovery", post(verify_recovery_code))
+            .route("/resend-2fa", post(resend_2fa))
             .route("/verify-token", post(verify_token))
+            .route("/refresh", post(refresh))
+            .route("/forgot-password", post(forgot_password))
+            .route("/reset-password", post(reset_password))
+            .route("/change-password", post(change_password))
+            .route("/change-email", post(change_email))
+            .route("/change-email/confirm", post(confirm_email_change))
+            .route("/change-email/cancel", post(cancel_email_change))
+            .route("/account", delete(delete_account))
+            .route("/sessions", get(list_sessions))
+            .route("/sessions/revoke-others", post(revoke_other_sessions))
+            .route("/sessions/:id", delete(revoke_session))
+            .route("/totp/enroll", post(enroll_totp))
+            .route("/totp/confirm", post(confirm_totp))
+            .route("/recovery-codes", post(regenerate_recovery_codes))
+            .route("/verify-email", get(verify_email))
+            .route("/unlock-account", get(unlock_account))
+            .nest("/admin", admin_router(app_state.clone()))
+            .route(
+                "/resend-verification-email",
+                post(resend_verification_email),
+            )
+            .layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                rate_limit,
+            ))
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -58,7 +102,11 @@ impl Application {
 
         let listener = TcpListener::bind(socket).await?;
         let address = listener.local_addr()?;
-        let server = axum::serve(listener, router);
+        // sessions record the address of the client
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Self { server, address })
     }
@@ -1,16 +1,31 @@
 use auth_service::{
     app_state::AppState,
-    domain::email::Email,
+    domain::{data_store::SigningKeyStore, email::Email},
     services::{
         data_stores::{
+            file_signing_key_store::FileSigningKeyStore,
+            postgres_signing_key_store::PostgresSigningKeyStore,
             postgres_user_store::PostgresUserStore,
+            redis_account_lockout_store::RedisAccountLockoutStore,
             redis_banned_token_store::RedisBannedTokenStore,
-            redis_two_fa_code_store::RedisTwoFaCodeStore,
+            redis_email_change_store::RedisEmailChangeStore,
+            redis_email_verification_token_store::RedisEmailVerificationTokenStore,
+            redis_magic_link_store::RedisMagicLinkStore,
+            redis_password_reset_token_store::RedisPasswordResetTokenStore,
+            redis_rate_limit_store::RedisRateLimitStore,
+            redis_refresh_token_store::RedisRefreshTokenStore,
+            redis_session_store::RedisSessionStore, redis_two_fa_code_store::RedisTwoFaCodeStore,
         },
+        keyring::Keyring,
         postmark_email_client::PostmarkEmailClient,
+        token_version_cache::TokenVersionCache,
     },
     utils::{
-        constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME},
+        constants::{
+            prod, DATABASE_URL, JWT_KEYRING_PATH, JWT_SIGNING_KEY, KEYRING_RELOAD_INTERVAL_SECONDS,
+            POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, TOKEN_VERSION_CACHE_TTL_SECONDS,
+            TWO_FACTOR_POLICY,
+        },
         tracing::init_tracing,
     },
     Application,
@@ -18,7 +33,10 @@ use auth_service::{
 use reqwest::Client;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
 use tokio::sync::RwLock;
 
 fn configure_poskmark_email_client() -> PostmarkEmailClient {
@@ -55,25 +73,90 @@ fn configure_redis() -> redis::Connection {
         .expect("Failed to get Redis connection! Is the port open and configured correctly?")
 }
 
+// The keyring lives in JWT_KEYRING_PATH when set, otherwise in Postgres.
+async fn configure_keyring(pg_pool: PgPool) -> Arc<RwLock<Keyring>> {
+    let store: Box<dyn SigningKeyStore> = match JWT_KEYRING_PATH.as_ref() {
+        Some(path) => Box::new(FileSigningKeyStore::new(path)),
+        None => Box::new(PostgresSigningKeyStore::new(pg_pool)),
+    };
+    let keyring = Keyring::load(store, JWT_SIGNING_KEY.clone())
+        .await
+        .expect("Fail to load signing keyring!");
+    let keyring = Arc::new(RwLock::new(keyring));
+
+    // other instances may rotate the keys
+    let reloaded = keyring.clone();
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(Duration::from_secs(KEYRING_RELOAD_INTERVAL_SECONDS));
+        loop {
+            interval.tick().await;
+            // token checks only wait on the swap, not on the store
+            let entries = reloaded.read().await.fetch().await;
+            match entries {
+                Ok(entries) => reloaded.write().await.replace(entries),
+                Err(e) => tracing::error!("Fail to reload signing keyring: {:?}", e),
+            }
+        }
+    });
+
+    keyring
+}
+
 #[tokio::main]
 async fn main() {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Fail to initialize tracing!");
+    // a bad 2FA policy should stop the server here rather than at the first login
+    LazyLock::force(&TWO_FACTOR_POLICY);
     let pg_pool = config_postgresql().await;
     let redis_client = Arc::new(RwLock::new(configure_redis()));
 
+    let keyring = configure_keyring(pg_pool.clone()).await;
+
     let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
     let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(
         redis_client.clone(),
     )));
     let two_fa_code_store = Arc::new(RwLock::new(RedisTwoFaCodeStore::new(redis_client.clone())));
     let email_client = Arc::new(configure_poskmark_email_client());
+    let refresh_token_store = Arc::new(RwLock::new(RedisRefreshTokenStore::new(
+        redis_client.clone(),
+    )));
+    let password_reset_token_store = Arc::new(RwLock::new(RedisPasswordResetTokenStore::new(
+        redis_client.clone(),
+    )));
+    let email_verification_token_store = Arc::new(RwLock::new(
+        RedisEmailVerificationTokenStore::new(redis_client.clone()),
+    ));
+    let email_change_store = Arc::new(RwLock::new(RedisEmailChangeStore::new(
+        redis_client.clone(),
+    )));
+    let magic_link_store = Arc::new(RwLock::new(RedisMagicLinkStore::new(redis_client.clone())));
+    let session_store = Arc::new(RwLock::new(RedisSessionStore::new(redis_client.clone())));
+    let token_version_cache = Arc::new(RwLock::new(TokenVersionCache::new(Duration::from_secs(
+        TOKEN_VERSION_CACHE_TTL_SECONDS,
+    ))));
+    let account_lockout_store = Arc::new(RwLock::new(RedisAccountLockoutStore::new(
+        redis_client.clone(),
+    )));
+    let rate_limit_store = Arc::new(RwLock::new(RedisRateLimitStore::new(redis_client.clone())));
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
+        refresh_token_store,
+        password_reset_token_store,
+        email_verification_token_store,
+        email_change_store,
+        magic_link_store,
+        keyring,
+        session_store,
+        token_version_cache,
+        account_lockout_store,
+        rate_limit_store,
     );
 
     let app = Application::build(app_state, prod::APP_ADDR)
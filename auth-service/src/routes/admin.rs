@@ -0,0 +1,155 @@
+use axum::extract::{FromRequestParts, Path, Request, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, Router};
+use axum::Json;
+use axum_extra::extract::CookieJar;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::app_state::AppState;
+use crate::domain::error::AuthAPIError;
+use crate::domain::signing_key::SigningAlgorithm;
+use crate::routes::admin_users::{
+    admin_delete_user, admin_disable_user, admin_enable_user, admin_get_user, admin_list_users,
+    admin_revoke_user_tokens, admin_update_user,
+};
+use crate::services::keyring::{Keyring, KeyringError};
+use crate::utils::authorized::{role, Authorized};
+use crate::utils::constants::{ADMIN_API_KEY, ADMIN_API_KEY_HEADER, JWT_COOKIE_NAME};
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RotateKeysRequest {
+    algorithm: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningKeyResponse {
+    pub kid: String,
+    pub algorithm: String,
+    pub state: String,
+    pub created_at: i64,
+}
+
+// Everything under /admin, behind require_admin.
+pub fn admin_router(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route("/keys", get(list_signing_keys))
+        .route("/keys/rotate", post(rotate_signing_keys))
+        .route("/keys/:kid/retire", post(retire_signing_key))
+        .route("/users", get(admin_list_users))
+        .route(
+            "/users/:email",
+            get(admin_get_user)
+                .patch(admin_update_user)
+                .delete(admin_delete_user),
+        )
+        .route("/users/:email/disable", post(admin_disable_user))
+        .route("/users/:email/enable", post(admin_enable_user))
+        .route(
+            "/users/:email/revoke-tokens",
+            post(admin_revoke_user_tokens),
+        )
+        .route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+// Admin routes take either the admin API key header or the JWT of an admin.
+pub async fn require_admin(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthAPIError> {
+    let (mut parts, body) = request.into_parts();
+    if parts.headers.contains_key(ADMIN_API_KEY_HEADER) {
+        check_api_key(&parts.headers)?;
+    } else if CookieJar::from_headers(&parts.headers)
+        .get(JWT_COOKIE_NAME)
+        .is_some()
+    {
+        Authorized::<role::Admin>::from_request_parts(&mut parts, &state).await?;
+    } else {
+        return Err(AuthAPIError::IncorrectCredentials);
+    }
+    Ok(next.run(Request::from_parts(parts, body)).await)
+}
+
+// The API key is refused unless ADMIN_API_KEY is configured.
+fn check_api_key(headers: &HeaderMap) -> Result<(), AuthAPIError> {
+    let expected = ADMIN_API_KEY
+        .as_ref()
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+    let provided = headers
+        .get(ADMIN_API_KEY_HEADER)
+        .ok_or(AuthAPIError::IncorrectCredentials)?;
+
+    if bool::from(
+        provided
+            .as_bytes()
+            .ct_eq(expected.expose_secret().as_bytes()),
+    ) {
+        Ok(())
+    } else {
+        Err(AuthAPIError::IncorrectCredentials)
+    }
+}
+
+fn list(keyring: &Keyring) -> Json<Vec<SigningKeyResponse>> {
+    let keys = keyring
+        .entries()
+        .iter()
+        .map(|entry| SigningKeyResponse {
+            kid: entry.key.kid().to_owned(),
+            algorithm: entry.key.signing_algorithm().to_string(),
+            state: entry.state.to_string(),
+            created_at: entry.created_at,
+        })
+        .collect();
+    Json(keys)
+}
+
+fn keyring_error(e: KeyringError) -> AuthAPIError {
+    match e {
+        KeyringError::KeyNotFound => AuthAPIError::SigningKeyNotFound,
+        KeyringError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+    }
+}
+
+#[tracing::instrument(name = "List signing keys route", skip_all)]
+pub async fn list_signing_keys(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    Ok((StatusCode::OK, list(&*state.keyring.read().await)))
+}
+
+// The next key becomes active and the active key keeps validating tokens until it is retired.
+#[tracing::instrument(name = "Rotate signing keys route", skip_all)]
+pub async fn rotate_signing_keys(
+    State(state): State<AppState>,
+    request: Option<Json<RotateKeysRequest>>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let Json(request) = request.unwrap_or_default();
+    let algorithm = request
+        .algorithm
+        .map(|algorithm| algorithm.parse::<SigningAlgorithm>())
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidData("Algorithm".to_owned()))?;
+
+    let mut keyring = state.keyring.write().await;
+    keyring.rotate(algorithm).await.map_err(keyring_error)?;
+    tracing::info!("Signing keys rotated");
+    Ok((StatusCode::OK, list(&keyring)))
+}
+
+#[tracing::instrument(name = "Retire signing key route", skip_all)]
+pub async fn retire_signing_key(
+    State(state): State<AppState>,
+    Path(kid): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let mut keyring = state.keyring.write().await;
+    keyring.retire(&kid).await.map_err(keyring_error)?;
+    tracing::info!("Signing key {} retired", kid);
+    Ok((StatusCode::OK, list(&keyring)))
+}
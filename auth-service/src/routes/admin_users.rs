@@ -0,0 +1,264 @@
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::{UserListQuery, UserStoreError, UserUpdate};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::user::{User, UserRole};
+use crate::routes::delete_account::remove_account;
+use crate::routes::logout::log_out_everywhere;
+use crate::utils::constants::{ADMIN_USERS_MAX_PAGE_SIZE, ADMIN_USERS_PAGE_SIZE};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListUsersQuery {
+    search: Option<String>,
+    page: Option<usize>, // starts at 1
+    per_page: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUserRequest {
+    #[serde(rename = "requires2FA")]
+    requires_2fa: Option<bool>,
+    role: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    pub email_verified: bool,
+    pub role: UserRole,
+    pub disabled: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserListResponse {
+    pub users: Vec<AdminUserResponse>,
+    pub page: usize,
+    pub per_page: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserDetailResponse {
+    #[serde(flatten)]
+    pub user: AdminUserResponse,
+    pub totp_enabled: bool,
+    pub sessions: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AdminActionResponse {
+    pub message: String,
+}
+
+impl From<&User> for AdminUserResponse {
+    fn from(user: &User) -> Self {
+        let email: &Email = user.as_ref();
+        Self {
+            email: email.as_ref().expose_secret().to_owned(),
+            requires_2fa: user.requires_2fa(),
+            email_verified: user.email_verified(),
+            role: user.role(),
+            disabled: user.disabled(),
+        }
+    }
+}
+
+fn parse_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))
+}
+
+fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+fn message(message: &str) -> impl IntoResponse {
+    let response = Json(AdminActionResponse {
+        message: message.to_owned(),
+    });
+    (StatusCode::OK, response)
+}
+
+#[tracing::instrument(name = "Admin list users route", skip_all)]
+pub async fn admin_list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = query.page.unwrap_or(1);
+    if page == 0 {
+        return Err(AuthAPIError::InvalidData("Page".to_owned()));
+    }
+    let per_page = query.per_page.unwrap_or(ADMIN_USERS_PAGE_SIZE);
+    if per_page == 0 || per_page > ADMIN_USERS_MAX_PAGE_SIZE {
+        return Err(AuthAPIError::InvalidData("Page size".to_owned()));
+    }
+
+    let query = UserListQuery {
+        search: query.search.filter(|search| !search.is_empty()),
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+    let list = state
+        .user_store
+        .read()
+        .await
+        .list_users(&query)
+        .await
+        .map_err(user_store_error)?;
+
+    let response = Json(AdminUserListResponse {
+        users: list.users.iter().map(AdminUserResponse::from).collect(),
+        page,
+        per_page,
+        total: list.total,
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Admin get user route", skip_all)]
+pub async fn admin_get_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let (user, totp_enabled) = {
+        let user_store = state.user_store.read().await;
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(user_store_error)?;
+        let totp_enabled = user_store
+            .get_totp(&email)
+            .await
+            .map_err(user_store_error)?
+            .is_some_and(|record| record.confirmed);
+        (user, totp_enabled)
+    };
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .len();
+
+    let response = Json(AdminUserDetailResponse {
+        user: AdminUserResponse::from(&user),
+        totp_enabled,
+        sessions,
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// A role change logs the user out everywhere, so a demoted admin doesn't keep their tokens.
+#[tracing::instrument(name = "Admin update user route", skip_all)]
+pub async fn admin_update_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+    Json(request): Json<UpdateUserRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let role = request
+        .role
+        .map(|role| role.parse::<UserRole>())
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidData("Role".to_owned()))?;
+
+    let before = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(user_store_error)?;
+    let update = UserUpdate {
+        requires_2fa: request.requires_2fa,
+        role,
+    };
+    let user = state
+        .user_store
+        .write()
+        .await
+        .update_user(&email, update)
+        .await
+        .map_err(user_store_error)?;
+
+    if user.role() != before.role() {
+        log_out_everywhere(&state, &email).await?;
+    }
+    tracing::info!("User was updated by an admin");
+    Ok((StatusCode::OK, Json(AdminUserResponse::from(&user))))
+}
+
+// Disabled accounts can't log in, and are logged out of every session right away.
+#[tracing::instrument(name = "Admin disable user route", skip_all)]
+pub async fn admin_disable_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&email, true)
+        .await
+        .map_err(user_store_error)?;
+    log_out_everywhere(&state, &email).await?;
+    tracing::info!("User was disabled by an admin");
+    Ok(message("User disabled"))
+}
+
+#[tracing::instrument(name = "Admin enable user route", skip_all)]
+pub async fn admin_enable_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_disabled(&email, false)
+        .await
+        .map_err(user_store_error)?;
+    tracing::info!("User was enabled by an admin");
+    Ok(message("User enabled"))
+}
+
+#[tracing::instrument(name = "Admin delete user route", skip_all)]
+pub async fn admin_delete_user(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    remove_account(&state, &email).await?;
+    tracing::info!("User was deleted by an admin");
+    Ok(message("User deleted"))
+}
+
+#[tracing::instrument(name = "Admin revoke user tokens route", skip_all)]
+pub async fn admin_revoke_user_tokens(
+    State(state): State<AppState>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    log_out_everywhere(&state, &email).await?;
+    tracing::info!("User tokens were revoked by an admin");
+    Ok(message("Every token of the user was revoked"))
+}
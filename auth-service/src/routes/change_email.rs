@@ -0,0 +1,264 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::{
+    EmailChangeRequest, EmailChangeStoreError, PasswordResetTokenStoreError, RefreshTokenStore,
+    SessionStore, TwoFACodeStore, UserStoreError,
+};
+use crate::domain::email::Email;
+use crate::domain::email_change_token::EmailChangeToken;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::routes::jwt::authenticated_email;
+use crate::routes::sessions::end_all_sessions;
+use crate::routes::unlock_account::confirm_password;
+use crate::utils::constants::{AUTH_SERVICE_URL, EMAIL_CHANGE_TOKEN_TTL_SECONDS};
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEmailRequest {
+    new_email: Secret<String>,
+    password: Secret<String>,
+}
+
+// The links in the emails open the auth page, which posts their token here once the user
+// clicks through. Link scanners and prefetchers only ever GET, so they can't act on a change.
+#[derive(Debug, Deserialize)]
+pub struct ChangeEmailTokenRequest {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Change email route", skip_all)]
+pub async fn change_email(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&state, &jar).await?;
+
+    let new_email = Email::parse(request.new_email)
+        .map_err(|_| AuthAPIError::InvalidData("New email".to_owned()))?;
+    let password = Password::parse(request.password)
+        .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
+    if new_email.eq(&email) {
+        return Err(AuthAPIError::InvalidData("New email".to_owned()));
+    }
+
+    confirm_password(&state, &email, &password).await?;
+    match state.user_store.read().await.get_user(&new_email).await {
+        Ok(_) => return Err(AuthAPIError::UserAlreadyExists),
+        Err(UserStoreError::UserNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let request = EmailChangeRequest {
+        email: email.clone(),
+        new_email: new_email.clone(),
+        confirm_token: EmailChangeToken::default(),
+        cancel_token: EmailChangeToken::default(),
+    };
+    state
+        .email_change_store
+        .write()
+        .await
+        .add_request(request.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    send_change_emails(&state, &request)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "Please confirm the change with the link sent to your new email address"
+            .to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm email change route", skip_all)]
+pub async fn confirm_email_change(
+    State(state): State<AppState>,
+    Json(request): Json<ChangeEmailTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailChangeToken::parse(request.token).map_err(|_| AuthAPIError::InvalidToken)?;
+    let request = state
+        .email_change_store
+        .write()
+        .await
+        .take_confirmed(&token)
+        .await
+        .map_err(take_error)?;
+
+    migrate_email(&state, &request.email, &request.new_email).await?;
+
+    // a reset link for the old address should not be able to touch the account anymore
+    let result = state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_token(&request.email)
+        .await;
+    match result {
+        Ok(()) | Err(PasswordResetTokenStoreError::TokenNotFound) => {}
+        Err(e) => tracing::error!(
+            "Fail to remove password reset token of the old address: {:?}",
+            e
+        ),
+    }
+
+    let response = Json(ChangeEmailResponse {
+        message: "Email address changed successfully!".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Cancel email change route", skip_all)]
+pub async fn cancel_email_change(
+    State(state): State<AppState>,
+    Json(request): Json<ChangeEmailTokenRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = EmailChangeToken::parse(request.token).map_err(|_| AuthAPIError::InvalidToken)?;
+    let request = state
+        .email_change_store
+        .write()
+        .await
+        .take_cancelled(&token)
+        .await
+        .map_err(take_error)?;
+
+    // the owner didn't ask for this, so whoever did is holding one of their sessions
+    end_all_sessions(&state, &request.email).await?;
+
+    let response = Json(ChangeEmailResponse {
+        message: "The email change was cancelled and every session was logged out. Please reset your password.".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+fn take_error(e: EmailChangeStoreError) -> AuthAPIError {
+    match e {
+        EmailChangeStoreError::RequestNotFound => AuthAPIError::InvalidToken,
+        EmailChangeStoreError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+    }
+}
+
+// Moves the account and everything keyed by its email to the new address.
+// All stores stay locked for the whole move, and the steps already done are undone if one fails.
+// The stores can't share a transaction, so the undo is best effort: each step is undone on its
+// own, and whatever can't be undone is logged for someone to put back by hand.
+#[tracing::instrument(name = "Migrate email", skip_all)]
+async fn migrate_email(
+    state: &AppState,
+    email: &Email,
+    new_email: &Email,
+) -> Result<(), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+    let mut two_fa_store = state.two_fa_code_store.write().await;
+    let mut refresh_store = state.refresh_token_store.write().await;
+    let mut session_store = state.session_store.write().await;
+
+    user_store
+        .update_email(email, new_email.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserAlreadyExists => AuthAPIError::UserAlreadyExists,
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    if let Err(e) = move_sessions(
+        &mut *two_fa_store,
+        &mut *refresh_store,
+        &mut *session_store,
+        email,
+        new_email,
+    )
+    .await
+    {
+        tracing::error!("Fail to migrate sessions, rolling back the email change");
+        // each store is moved back even if another one fails to, the moves do nothing where
+        // there's nothing under the new address
+        if let Err(e) = session_store.move_user(new_email, email).await {
+            tracing::error!("Fail to roll back sessions: {:?}", e);
+        }
+        if let Err(e) = refresh_store.move_user(new_email, email).await {
+            tracing::error!("Fail to roll back refresh tokens: {:?}", e);
+        }
+        if let Err(e) = two_fa_store.move_codes(new_email, email.clone()).await {
+            tracing::error!("Fail to roll back 2FA codes: {:?}", e);
+        }
+        if let Err(e) = user_store.update_email(new_email, email.clone()).await {
+            tracing::error!("Fail to roll back the email change: {:?}", e);
+        }
+        return Err(AuthAPIError::UnexpectedError(e));
+    }
+
+    Ok(())
+}
+
+async fn move_sessions(
+    two_fa_store: &mut dyn TwoFACodeStore,
+    refresh_store: &mut dyn RefreshTokenStore,
+    session_store: &mut dyn SessionStore,
+    email: &Email,
+    new_email: &Email,
+) -> Result<()> {
+    two_fa_store.move_codes(email, new_email.clone()).await?;
+    refresh_store.move_user(email, new_email).await?;
+    session_store.move_user(email, new_email).await?;
+    Ok(())
+}
+
+#[tracing::instrument(name = "Send email change emails", skip_all)]
+async fn send_change_emails(state: &AppState, request: &EmailChangeRequest) -> Result<()> {
+    let confirm_link = change_link("confirm", &request.confirm_token)?;
+    let body = format!(
+        "Please confirm this is the new email address of your account by opening this link: {}\nThe link expires in {} minutes.",
+        confirm_link,
+        EMAIL_CHANGE_TOKEN_TTL_SECONDS / 60
+    );
+    state
+        .email_client
+        .send_email(
+            &request.new_email,
+            "Let's Get Rusty Confirm Email Change",
+            &body,
+        )
+        .await?;
+
+    let cancel_link = change_link("cancel", &request.cancel_token)?;
+    let body = format!(
+        "Someone asked to move your account to {}. If this was you, there's nothing to do. If it wasn't, open this link to cancel the change and log out every session: {}",
+        request.new_email.as_ref().expose_secret(),
+        cancel_link
+    );
+    state
+        .email_client
+        .send_email(
+            &request.email,
+            "Let's Get Rusty Email Change Requested",
+            &body,
+        )
+        .await
+}
+
+fn change_link(action: &str, token: &EmailChangeToken) -> Result<Url> {
+    let mut url = Url::parse(&AUTH_SERVICE_URL)?;
+    url.query_pairs_mut()
+        .append_pair("changeEmail", action)
+        .append_pair("token", token.as_ref().expose_secret());
+    Ok(url)
+}
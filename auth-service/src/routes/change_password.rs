@@ -0,0 +1,95 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::routes::jwt::authenticated_claims;
+use crate::routes::logout::log_out_everywhere;
+use crate::routes::sessions::issue_session;
+use crate::routes::unlock_account::confirm_password;
+use crate::utils::client_info::ClientInfo;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordRequest {
+    current_password: Secret<String>,
+    new_password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangePasswordResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Change password route", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authenticated_claims(&state, &jar).await?;
+    let email =
+        Email::parse(Secret::new(claims.sub.clone())).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let current_password = Password::parse(request.current_password)
+        .map_err(|_| AuthAPIError::InvalidData("Current password".to_owned()))?;
+    let new_password = Password::parse(request.new_password)
+        .map_err(|_| AuthAPIError::InvalidData("New password".to_owned()))?;
+
+    let user = confirm_password(&state, &email, &current_password).await?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, new_password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // Log out every session, this one included, and carry on in a brand new session.
+    log_out_everywhere(&state, &email).await?;
+    let jar = issue_session(
+        &state,
+        &email,
+        user.email_verified(),
+        &claims.amr,
+        &client,
+        jar,
+    )
+    .await?;
+
+    // a pending reset link or 2FA code was issued for the old password
+    let _ = state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_token(&email)
+        .await;
+    let _ = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(&email)
+        .await;
+
+    let body = "The password of your account was just changed and every other session was logged out. If this wasn't you, reset your password right away.";
+    if let Err(e) = state
+        .email_client
+        .send_email(&email, "Let's Get Rusty Password Changed", body)
+        .await
+    {
+        tracing::warn!("Fail to send password change notification: {:?}", e);
+    }
+
+    let response = Json(ChangePasswordResponse {
+        message: "Password was changed successfully!".to_owned(),
+    });
+    Ok((jar, (StatusCode::OK, response)))
+}
@@ -0,0 +1,90 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::UserStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::routes::jwt::authenticated_claims;
+use crate::routes::sessions::end_all_sessions;
+use crate::routes::unlock_account::confirm_password;
+use crate::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteAccountResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Delete account route", skip_all)]
+pub async fn delete_account(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<DeleteAccountRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authenticated_claims(&state, &jar).await?;
+    let email =
+        Email::parse(Secret::new(claims.sub.clone())).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let password = Password::parse(request.password)
+        .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
+    confirm_password(&state, &email, &password).await?;
+
+    remove_account(&state, &email).await?;
+
+    let _ = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(&claims.jti, claims.exp as i64)
+        .await;
+    let jar = jar
+        .remove(Cookie::from(JWT_COOKIE_NAME))
+        .remove(Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+
+    let response = Json(DeleteAccountResponse {
+        message: "Account deleted successfully!".to_owned(),
+    });
+    Ok((jar, (StatusCode::OK, response)))
+}
+
+// Deletes the account, and everything keyed by its email along with it.
+pub(crate) async fn remove_account(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .user_store
+        .write()
+        .await
+        .delete_user(email.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // the account is gone, anything left behind is only cleanup
+    let _ = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(email)
+        .await;
+    let _ = state
+        .password_reset_token_store
+        .write()
+        .await
+        .remove_token(email)
+        .await;
+    if let Err(e) = end_all_sessions(state, email).await {
+        tracing::error!("Fail to end the sessions of deleted account: {:?}", e);
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::UserStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password_reset_token::PasswordResetToken;
+use crate::utils::constants::{AUTH_SERVICE_URL, PASSWORD_RESET_TOKEN_TTL_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct ForgotPasswordRequest {
+    email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForgotPasswordResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Forgot password route", skip_all)]
+pub async fn forgot_password(
+    State(state): State<AppState>,
+    Json(request): Json<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+
+    // Same answer whether the account exists or not, so this route can't be used to look up accounts.
+    let response = Json(ForgotPasswordResponse {
+        message: "If the account exists, a password reset link was sent to the email address"
+            .to_owned(),
+    });
+
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = PasswordResetToken::default();
+    state
+        .password_reset_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let link = reset_link(&email, &token).map_err(AuthAPIError::UnexpectedError)?;
+    let body = format!(
+        "Someone asked to reset the password of your account. If this was you, use this link to choose a new password: {}\nThe link can only be used once and expires in {} minutes. If you didn't ask for this, you can ignore this email.",
+        link,
+        PASSWORD_RESET_TOKEN_TTL_SECONDS / 60
+    );
+    state
+        .email_client
+        .send_email(&email, "Let's Get Rusty Password Reset", &body)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, response))
+}
+
+fn reset_link(email: &Email, token: &PasswordResetToken) -> color_eyre::Result<Url> {
+    let mut url = Url::parse(&AUTH_SERVICE_URL)?;
+    url.query_pairs_mut()
+        .append_pair("email", email.as_ref().expose_secret())
+        .append_pair("resetToken", token.as_ref().expose_secret());
+    Ok(url)
+}
@@ -0,0 +1,13 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::{response::IntoResponse, Json};
+
+use crate::app_state::AppState;
+
+// Public keys downstream services use to check our JWTs without calling /verify-token.
+// Every key that is not retired is listed, so a freshly rotated key is already known.
+#[tracing::instrument(name = "JWKS route", skip_all)]
+pub async fn jwks(State(state): State<AppState>) -> impl IntoResponse {
+    let keys = state.keyring.read().await.jwks();
+    ([(header::CACHE_CONTROL, "public, max-age=300")], Json(keys))
+}
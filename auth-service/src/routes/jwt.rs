@@ -1,6 +1,118 @@
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 
+use crate::app_state::AppState;
+use crate::domain::{
+    data_store::{SessionStoreError, UserStoreError},
+    email::Email,
+    error::AuthAPIError,
+    user::UserRole,
+};
+use crate::utils::{
+    auth::{validate_token, Claims},
+    constants::JWT_COOKIE_NAME,
+};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct JWToken {
     pub token: String,
+    // the service asking, which has to be listed in the token's aud
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audience: Option<String>,
+    // the least role the service asking requires, tokens below it get a 403
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<UserRole>,
+}
+
+// Checks the JWT cookie of the request and its session, and returns its claims.
+pub(crate) async fn authenticated_claims(
+    state: &AppState,
+    jar: &CookieJar,
+) -> Result<Claims, AuthAPIError> {
+    let token = match jar.get(JWT_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return Err(AuthAPIError::MissingToken),
+    };
+    let claims = validate_token(&*state.keyring.read().await, &token)
+        .await
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+    if state
+        .banned_token_store
+        .read()
+        .await
+        .check_token(&claims.jti)
+        .await
+    {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    let email =
+        Email::parse(Secret::new(claims.sub.clone())).map_err(|_| AuthAPIError::InvalidToken)?;
+    let session = state
+        .session_store
+        .write()
+        .await
+        .touch_session(&claims.sid)
+        .await
+        .map_err(|e| match e {
+            SessionStoreError::SessionNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    // sessions move along with an email change, tokens issued before it still name the old address
+    if session.email != email {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    if claims.ver != current_token_version(state, &email).await? {
+        return Err(AuthAPIError::InvalidToken);
+    }
+    Ok(claims)
+}
+
+// Checks the JWT cookie of the request and returns who it belongs to.
+pub(crate) async fn authenticated_email(
+    state: &AppState,
+    jar: &CookieJar,
+) -> Result<Email, AuthAPIError> {
+    let claims = authenticated_claims(state, jar).await?;
+    Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)
+}
+
+// The version tokens of the user have to carry, cached since every authenticated request needs it.
+pub(crate) async fn current_token_version(
+    state: &AppState,
+    email: &Email,
+) -> Result<i32, AuthAPIError> {
+    if let Some(version) = state.token_version_cache.read().await.get(email) {
+        return Ok(version);
+    }
+    let version = fetch_token_version(state, email).await?;
+    state
+        .token_version_cache
+        .write()
+        .await
+        .insert(email.clone(), version);
+    Ok(version)
+}
+
+// Reads the version from the user store, new tokens are always signed with this one.
+// Users that don't exist are on version 0, whatever tokens they had went with their sessions.
+pub(crate) async fn fetch_token_version(
+    state: &AppState,
+    email: &Email,
+) -> Result<i32, AuthAPIError> {
+    match state.user_store.read().await.get_token_version(email).await {
+        Ok(version) => Ok(version),
+        Err(UserStoreError::UserNotFound) => Ok(0),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+// The role new tokens of the user are signed with, users that don't exist have none.
+pub(crate) async fn fetch_role(state: &AppState, email: &Email) -> Result<UserRole, AuthAPIError> {
+    match state.user_store.read().await.get_user(email).await {
+        Ok(user) => Ok(user.role()),
+        Err(UserStoreError::UserNotFound) => Ok(UserRole::None),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
 }
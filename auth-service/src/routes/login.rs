@@ -1,16 +1,26 @@
 use axum::http::StatusCode;
 use axum::{extract::State, response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
+use color_eyre::eyre::Result;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 
+use crate::domain::account_lockout::LockoutRecord;
+use crate::domain::auth_method::AuthMethod;
+use crate::domain::data_store::{TwoFARecord, UserStoreError};
 use crate::domain::error::AuthAPIError;
 use crate::domain::login_attempt_id::LoginAttemptId;
 use crate::domain::two_fa_code::TwoFACode;
+use crate::domain::user::{UnverifiedLoginPolicy, User};
 use crate::{
     app_state::AppState,
     domain::{email::Email, password::Password},
-    utils::auth::generate_auth_cookie,
+    routes::{sessions::issue_session, unlock_account::register_failed_login},
+    utils::{
+        client_info::ClientInfo,
+        constants::{TWO_FACTOR_POLICY, UNVERIFIED_LOGIN_POLICY},
+    },
 };
 
 #[derive(Debug, Deserialize)]
@@ -42,6 +52,7 @@ impl LoginRequest {
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    client: ClientInfo,
     jar: CookieJar,
     Json(login): Json<LoginRequest>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
@@ -49,24 +60,104 @@ pub async fn login(
         Email::parse(login.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
     let password = Password::parse(login.password)
         .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
-    let store = state.user_store.read().await;
-    let user = store
-        .validate_user(&email, &password)
+
+    let lockout = state
+        .account_lockout_store
+        .read()
         .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        .get_record(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if let Some(seconds) = lockout.locked_for(Utc::now().timestamp()) {
+        return Err(AuthAPIError::AccountLocked(seconds));
+    }
 
-    let result = match user.requires_2fa() {
-        true => handle_2fa(&user.as_ref(), &state, jar).await,
-        false => handle_no_2fa(&user.as_ref(), jar).await,
+    let store = state.user_store.read().await;
+    let user = match store.validate_user(&email, &password).await {
+        Ok(user) => user,
+        // unknown emails are counted and locked just the same, or the lockout would tell which
+        // accounts exist
+        Err(e @ (UserStoreError::InvalidCredentials | UserStoreError::UserNotFound)) => {
+            drop(store);
+            let account_exists = e == UserStoreError::InvalidCredentials;
+            return Err(register_failed_login(&state, &email, account_exists).await);
+        }
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
     };
+    // an authenticator app is a second factor even if the account never turned on email codes
+    let totp_enabled = store
+        .get_totp(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+        .is_some_and(|record| record.confirmed);
+    drop(store);
+
+    if lockout != LockoutRecord::default() {
+        state
+            .account_lockout_store
+            .write()
+            .await
+            .remove_record(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    if user.disabled() {
+        return Err(AuthAPIError::AccountDisabled);
+    }
+    if !user.email_verified() && *UNVERIFIED_LOGIN_POLICY == UnverifiedLoginPolicy::Deny {
+        return Err(AuthAPIError::EmailNotVerified);
+    }
+
+    let result = start_session(
+        &user,
+        totp_enabled,
+        AuthMethod::Password,
+        &client,
+        &state,
+        jar,
+    )
+    .await;
 
     // a little hack to get this working. I'm sure there's a reason behind it?
     Ok((result.0, result.1.into_response()))
 }
 
+// Once the first factor checked out, either starts the second one or hands out the session cookies.
+pub(crate) async fn start_session(
+    user: &User,
+    totp_enabled: bool,
+    first_factor: AuthMethod,
+    client: &ClientInfo,
+    state: &AppState,
+    jar: CookieJar,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let email: &Email = user.as_ref();
+    match user.requires_2fa() || totp_enabled {
+        true => handle_2fa(email, totp_enabled, first_factor, client, state, jar).await,
+        false => {
+            handle_no_2fa(
+                email,
+                user.email_verified(),
+                first_factor,
+                client,
+                state,
+                jar,
+            )
+            .await
+        }
+    }
+}
+
 #[tracing::instrument(name = "Handle 2FA route", skip_all)]
 async fn handle_2fa(
     email: &Email,
+    totp_enabled: bool,
+    first_factor: AuthMethod,
+    client: &ClientInfo,
     state: &AppState,
     jar: CookieJar,
 ) -> (
@@ -74,35 +165,35 @@ async fn handle_2fa(
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
     let id = LoginAttemptId::default();
-    let code = TwoFACode::default();
+    let code = TwoFACode::generate(&TWO_FACTOR_POLICY);
+    let record = TwoFARecord {
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+        ..TwoFARecord::new(email.clone(), id.clone(), &code, vec![first_factor])
+    };
 
-    if let Err(e) = state
-        .two_fa_code_store
-        .write()
-        .await
-        .add_code(email.clone(), id.clone(), code.clone())
-        .await
-    {
-        // Need to discuss about this implementation -
-        /*
-           Currently theres nothing here that can stop us from updating existing value in our data store.
-           If we tried to add a new entry with existing email account, the databse will simply update the original value, returning as an error message...?
-           Also, currently, there's no implementation to clear the database of stored/temp values? E.g. Banned token have expiration date, but banned token store will keep that record forever.
-        */
+    // every login gets an attempt of its own, logins from other tabs stay pending alongside it
+    if let Err(e) = state.two_fa_code_store.write().await.add_code(record).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
-    // TODO: impl services that sends 2FA code to user's email
-    let body = format!(
-        "Please use this code to log into the website: {}",
-        code.as_ref().expose_secret()
-    );
-    if let Err(e) = state
-        .email_client
-        .send_email(email, "Let's Get Rusty 2FA Code", &body)
-        .await
-    {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    // the login attempt still gets a record, but the code comes from the authenticator app
+    if totp_enabled {
+        let response = TwoFactorAuthResponse {
+            message: "Authenticator app code required".to_owned(),
+            login_attempt_id: id.as_ref().to_owned(),
+        };
+        return (
+            jar,
+            Ok((
+                StatusCode::PARTIAL_CONTENT,
+                Json(LoginResponse::TwoFactorAuth(response)),
+            )),
+        );
+    }
+
+    if let Err(e) = send_2fa_code(state, email, &code).await {
+        return (jar, Err(AuthAPIError::UnexpectedError(e)));
     }
 
     let response = TwoFactorAuthResponse {
@@ -119,19 +210,41 @@ async fn handle_2fa(
     )
 }
 
+// TODO: impl services that sends 2FA code to user's email
+pub(crate) async fn send_2fa_code(state: &AppState, email: &Email, code: &TwoFACode) -> Result<()> {
+    let body = format!(
+        "Please use this code to log into the website: {}",
+        code.as_ref().expose_secret()
+    );
+    state
+        .email_client
+        .send_email(email, "Let's Get Rusty 2FA Code", &body)
+        .await
+}
+
 #[tracing::instrument(name = "Handle No 2FA route", skip_all)]
 async fn handle_no_2fa(
     email: &Email,
+    email_verified: bool,
+    first_factor: AuthMethod,
+    client: &ClientInfo,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
-        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
-    };
-
-    let jar = jar.add(auth_cookie);
-    (jar, Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))))
+    match issue_session(
+        state,
+        email,
+        email_verified,
+        &[first_factor],
+        client,
+        jar.clone(),
+    )
+    .await
+    {
+        Ok(jar) => (jar, Ok((StatusCode::OK, Json(LoginResponse::RegularAuth)))),
+        Err(e) => (jar, Err(e)),
+    }
 }
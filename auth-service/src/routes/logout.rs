@@ -1,11 +1,25 @@
 use axum::response::IntoResponse;
-use axum::{extract::State, http::StatusCode};
+use axum::{extract::State, http::StatusCode, Json};
 use axum_extra::extract::{cookie::Cookie, CookieJar};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
 
 use crate::app_state::AppState;
 use crate::{
-    domain::error::AuthAPIError,
-    utils::{auth::validate_token, constants::JWT_COOKIE_NAME},
+    domain::{
+        data_store::{RefreshTokenStoreError, UserStoreError},
+        email::Email,
+        error::AuthAPIError,
+        refresh_token::RefreshToken,
+    },
+    routes::{
+        jwt::authenticated_email,
+        sessions::{end_all_sessions, end_session},
+    },
+    utils::{
+        auth::validate_token,
+        constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+    },
 };
 
 #[tracing::instrument(name = "Logout route", skip_all)]
@@ -22,15 +36,96 @@ pub async fn logout(
         None => return (jar_clone, Err(AuthAPIError::MissingToken)),
     };
 
-    // remove JWT cookie and add to ban list
+    // remove JWT cookie, it is banned below once we know it is one of ours
     let jar_clone = jar_clone.remove(Cookie::from(JWT_COOKIE_NAME));
-    let mut ban_list = state.banned_token_store.write().await;
-    let _ = ban_list.add_token(cookie).await;
+
+    // the refresh token should not outlive the session it belongs to
+    let jar_clone = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(refresh) => {
+            revoke_refresh_token(&state, refresh.value()).await;
+            jar_clone.remove(Cookie::from(REFRESH_TOKEN_COOKIE_NAME))
+        }
+        None => jar_clone,
+    };
 
     // if the cookie contains invalid JWT return 401
     // else if succeed - return 200
-    match validate_token(cookie).await {
-        Ok(_) => (jar_clone, Ok(StatusCode::OK.into_response())),
-        Err(_) => (jar_clone, Err(AuthAPIError::InvalidToken)),
+    let claims = match validate_token(&*state.keyring.read().await, cookie).await {
+        Ok(claims) => claims,
+        Err(_) => return (jar_clone, Err(AuthAPIError::InvalidToken)),
+    };
+    let _ = state
+        .banned_token_store
+        .write()
+        .await
+        .add_token(&claims.jti, claims.exp as i64)
+        .await;
+
+    // the session may already be gone, logging out twice is fine
+    if let Ok(email) = Email::parse(Secret::new(claims.sub)) {
+        let _ = end_session(&state, &email, &claims.sid).await;
+    }
+    (jar_clone, Ok(StatusCode::OK.into_response()))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogoutAllResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Logout all route", skip_all)]
+pub async fn logout_all(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email = authenticated_email(&state, &jar).await?;
+    log_out_everywhere(&state, &email).await?;
+
+    let jar = jar
+        .remove(Cookie::from(JWT_COOKIE_NAME))
+        .remove(Cookie::from(REFRESH_TOKEN_COOKIE_NAME));
+    let response = Json(LogoutAllResponse {
+        message: "Logged out of every session".to_owned(),
+    });
+    Ok((jar, (StatusCode::OK, response)))
+}
+
+// Bumps the user's token version so every JWT issued so far is rejected, and ends their sessions.
+// Other instances keep accepting the old tokens until their cached version goes stale.
+pub(crate) async fn log_out_everywhere(
+    state: &AppState,
+    email: &Email,
+) -> Result<(), AuthAPIError> {
+    let version = state
+        .user_store
+        .write()
+        .await
+        .increment_token_version(email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    state
+        .token_version_cache
+        .write()
+        .await
+        .insert(email.clone(), version);
+    end_all_sessions(state, email).await
+}
+
+#[tracing::instrument(name = "Revoke refresh token", skip_all)]
+async fn revoke_refresh_token(state: &AppState, token: &str) {
+    let Ok(token) = RefreshToken::parse(Secret::new(token.to_owned())) else {
+        return;
+    };
+    let mut store = state.refresh_token_store.write().await;
+    let family_id = match store.consume_token(&token).await {
+        Ok(record) => record.family_id,
+        Err(RefreshTokenStoreError::TokenReused(family_id)) => family_id,
+        Err(_) => return,
+    };
+    if let Err(e) = store.revoke_family(&family_id).await {
+        tracing::error!("Fail to revoke refresh token family: {}", e);
     }
 }
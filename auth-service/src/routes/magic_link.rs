@@ -0,0 +1,134 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::auth_method::AuthMethod;
+use crate::domain::data_store::{MagicLinkStoreError, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::routes::login::start_session;
+use crate::utils::auth::{generate_magic_link_token, validate_magic_link_token};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{AUTH_SERVICE_URL, MAGIC_LINK_TTL_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkRequest {
+    email: Secret<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MagicLinkQuery {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Magic link route", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+
+    // Same answer whether the account exists or not, so this route can't be used to look up accounts.
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a login link was sent to the email address".to_owned(),
+    });
+
+    match state.user_store.read().await.get_user(&email).await {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let token = generate_magic_link_token(&email).map_err(AuthAPIError::UnexpectedError)?;
+    let link = magic_link(&token).map_err(AuthAPIError::UnexpectedError)?;
+    let body = format!(
+        "Use this link to log into the website: {}\nThe link can only be used once and expires in {} minutes. If you didn't ask for this, you can ignore this email.",
+        link,
+        MAGIC_LINK_TTL_SECONDS / 60
+    );
+    state
+        .email_client
+        .send_email(&email, "Let's Get Rusty Login Link", &body)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Magic link callback route", skip_all)]
+pub async fn magic_link_callback(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    jar: CookieJar,
+    Query(query): Query<MagicLinkQuery>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = validate_magic_link_token(query.token.expose_secret())
+        .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    state
+        .magic_link_store
+        .write()
+        .await
+        .consume_link(&claims.jti, claims.exp as i64)
+        .await
+        .map_err(|e| match e {
+            MagicLinkStoreError::LinkAlreadyUsed => {
+                tracing::warn!("A magic link was used twice");
+                AuthAPIError::InvalidToken
+            }
+            MagicLinkStoreError::UnexpectedError(e) => AuthAPIError::UnexpectedError(e),
+        })?;
+
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+    let (user, totp_enabled) = {
+        let mut user_store = state.user_store.write().await;
+        // opening the link proves the address belongs to whoever asked for it.
+        // the account may have been removed since the link was sent.
+        user_store.verify_email(&email).await.map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+        let user = user_store
+            .get_user(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        let totp_enabled = user_store
+            .get_totp(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?
+            .is_some_and(|record| record.confirmed);
+        (user, totp_enabled)
+    };
+    if user.disabled() {
+        return Err(AuthAPIError::AccountDisabled);
+    }
+
+    // the link only stands in for the password, accounts with a second factor still need it
+    let result = start_session(
+        &user,
+        totp_enabled,
+        AuthMethod::OneTimePassword,
+        &client,
+        &state,
+        jar,
+    )
+    .await;
+    Ok((result.0, result.1.into_response()))
+}
+
+fn magic_link(token: &str) -> color_eyre::Result<Url> {
+    let mut url = Url::parse(&AUTH_SERVICE_URL)?.join("/login/magic-link/callback")?;
+    url.query_pairs_mut().append_pair("token", token);
+    Ok(url)
+}
@@ -1,14 +1,48 @@
+pub mod admin;
+pub mod admin_users;
+pub mod change_email;
+pub mod change_password;
+pub mod delete_account;
+pub mod forgot_password;
 pub mod hello;
+pub mod jwks;
 pub mod jwt;
 pub mod login;
 pub mod logout;
+pub mod magic_link;
+pub mod recovery_codes;
+pub mod refresh;
+pub mod resend_2fa;
+pub mod resend_verification_email;
+pub mod reset_password;
+pub mod sessions;
 pub mod signup;
+pub mod totp;
+pub mod unlock_account;
 pub mod verify_2fa;
+pub mod verify_email;
 pub mod verify_token;
 
+pub use admin::*;
+pub use admin_users::*;
+pub use change_email::*;
+pub use change_password::*;
+pub use delete_account::*;
+pub use forgot_password::*;
 pub use hello::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use recovery_codes::*;
+pub use refresh::*;
+pub use resend_2fa::*;
+pub use resend_verification_email::*;
+pub use reset_password::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
+pub use unlock_account::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
 pub use verify_token::*;
@@ -0,0 +1,148 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::auth_method::AuthMethod;
+use crate::domain::data_store::UserStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::login_attempt_id::LoginAttemptId;
+use crate::domain::password::Password;
+use crate::domain::recovery_code::RecoveryCode;
+use crate::routes::jwt::authenticated_email;
+use crate::routes::sessions::issue_session;
+use crate::routes::unlock_account::confirm_password;
+use crate::routes::verify_2fa::{
+    end_login_attempt, pending_login_attempt, register_failed_2fa_attempt, spend_2fa_attempt,
+};
+use crate::utils::{client_info::ClientInfo, constants::RECOVERY_CODE_COUNT};
+
+#[derive(Debug, Deserialize)]
+pub struct RegenerateRecoveryCodesRequest {
+    password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCodesResponse {
+    pub message: String,
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyRecoveryCodeRequest {
+    email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    id: String,
+    recovery_code: Secret<String>,
+}
+
+#[tracing::instrument(name = "Regenerate recovery codes route", skip_all)]
+pub async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&state, &jar).await?;
+    let password = Password::parse(request.password)
+        .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
+
+    let codes: Vec<RecoveryCode> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| RecoveryCode::default())
+        .collect();
+
+    confirm_password(&state, &email, &password).await?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_recovery_codes(&email, codes.clone())
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // this is the only time the codes are ever shown, only their hashes are kept
+    let response = Json(RecoveryCodesResponse {
+        message: "Keep these codes somewhere safe, each one can be used once to log in without your second factor. Any older codes stopped working.".to_owned(),
+        recovery_codes: codes
+            .iter()
+            .map(|code| code.as_ref().expose_secret().to_owned())
+            .collect(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Verify recovery code route", skip_all)]
+pub async fn verify_recovery_code(
+    State(state): State<AppState>,
+    client: ClientInfo,
+    jar: CookieJar,
+    Json(request): Json<VerifyRecoveryCodeRequest>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+    let id = LoginAttemptId::parse(request.id)
+        .map_err(|_| AuthAPIError::InvalidData("Login ID".to_owned()))?;
+    let code = RecoveryCode::parse(request.recovery_code)
+        .map_err(|_| AuthAPIError::InvalidData("Recovery code".to_owned()))?;
+
+    // the login attempt proves the password was given, the recovery code stands in for the second factor
+    let info =
+        pending_login_attempt(&mut *state.two_fa_code_store.write().await, &email, &id).await?;
+    let attempts = spend_2fa_attempt(&state, &id).await?;
+
+    // a wrong recovery code counts as a wrong 2FA code of the login attempt
+    let result = state
+        .user_store
+        .read()
+        .await
+        .check_recovery_code(&email, &code)
+        .await;
+    match result {
+        Ok(()) => {}
+        Err(UserStoreError::InvalidCredentials) => {
+            return Err(register_failed_2fa_attempt(&state, &id, attempts).await)
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // the login attempt goes first, so of two requests racing with it only one burns the code,
+    // and a code is never burned for an account that can't log in anymore
+    let user = end_login_attempt(&state, &info).await?;
+    let result = state
+        .user_store
+        .write()
+        .await
+        .use_recovery_code(&email, &code)
+        .await;
+    let remaining = match result {
+        Ok(remaining) => remaining,
+        // used up by another login in the meantime
+        Err(UserStoreError::InvalidCredentials) => return Err(AuthAPIError::IncorrectCredentials),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    let amr = AuthMethod::with_second_factor(&info.amr);
+    let jar = issue_session(&state, &email, user.email_verified(), &amr, &client, jar).await?;
+
+    let body = format!(
+        "A recovery code was just used to log into your account, you have {} left. If this wasn't you, reset your password and generate new recovery codes right away.",
+        remaining
+    );
+    if let Err(e) = state
+        .email_client
+        .send_email(&email, "Let's Get Rusty Recovery Code Used", &body)
+        .await
+    {
+        tracing::warn!("Fail to send recovery code notification: {:?}", e);
+    }
+
+    Ok((jar, StatusCode::OK.into_response()))
+}
@@ -0,0 +1,84 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+
+use crate::app_state::AppState;
+use crate::domain::data_store::{RefreshTokenStoreError, SessionStoreError};
+use crate::domain::error::AuthAPIError;
+use crate::domain::refresh_token::RefreshToken;
+use crate::routes::jwt::{fetch_role, fetch_token_version};
+use crate::utils::auth::{generate_auth_cookie, generate_refresh_cookie};
+use crate::utils::constants::REFRESH_TOKEN_COOKIE_NAME;
+
+#[tracing::instrument(name = "Refresh token route", skip_all)]
+pub async fn refresh(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let token = match jar.get(REFRESH_TOKEN_COOKIE_NAME) {
+        Some(cookie) => cookie.value().to_owned(),
+        None => return Err(AuthAPIError::MissingToken),
+    };
+    let token = RefreshToken::parse(Secret::new(token)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let mut store = state.refresh_token_store.write().await;
+    let record = match store.consume_token(&token).await {
+        Ok(record) => record,
+        Err(RefreshTokenStoreError::TokenReused(family_id)) => {
+            // someone is holding on to a token that was already rotated - assume it was stolen.
+            tracing::warn!("Refresh token reuse detected, revoking the token family");
+            store
+                .revoke_family(&family_id)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            return Err(AuthAPIError::InvalidToken);
+        }
+        Err(RefreshTokenStoreError::UnexpectedError(e)) => {
+            return Err(AuthAPIError::UnexpectedError(e))
+        }
+        Err(_) => return Err(AuthAPIError::InvalidToken),
+    };
+
+    // the family id is the session, which may have been revoked since the token was issued
+    match state
+        .session_store
+        .write()
+        .await
+        .touch_session(&record.family_id)
+        .await
+    {
+        Ok(_) => {}
+        Err(SessionStoreError::SessionNotFound) => {
+            store
+                .revoke_family(&record.family_id)
+                .await
+                .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+            return Err(AuthAPIError::InvalidToken);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // the user store is locked before the refresh store everywhere else
+    drop(store);
+    let version = fetch_token_version(&state, &record.email).await?;
+    let role = fetch_role(&state, &record.email).await?;
+    let auth_cookie = generate_auth_cookie(
+        &*state.keyring.read().await,
+        &record.email,
+        &record.family_id,
+        version,
+        role,
+        &record.amr,
+    )
+    .map_err(AuthAPIError::UnexpectedError)?;
+    let mut store = state.refresh_token_store.write().await;
+    let refresh_cookie =
+        generate_refresh_cookie(&mut *store, &record.email, &record.amr, &record.family_id)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+    let jar = jar.add(auth_cookie).add(refresh_cookie);
+    Ok((jar, StatusCode::OK.into_response()))
+}
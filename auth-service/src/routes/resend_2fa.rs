@@ -0,0 +1,73 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use chrono::Utc;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::data_store::TwoFACodeStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::login_attempt_id::LoginAttemptId;
+use crate::domain::two_fa_code::TwoFACode;
+use crate::routes::login::{send_2fa_code, TwoFactorAuthResponse};
+use crate::routes::totp::enabled_totp_secret;
+use crate::routes::verify_2fa::pending_login_attempt;
+use crate::utils::constants::TWO_FACTOR_POLICY;
+
+#[derive(Debug, Deserialize)]
+pub struct Resend2FARequest {
+    email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    id: String,
+}
+
+// Emails a fresh code for a pending login attempt, the old code stops working.
+#[tracing::instrument(name = "Resend 2FA code route", skip_all)]
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+    let id = LoginAttemptId::parse(request.id)
+        .map_err(|_| AuthAPIError::InvalidData("Login ID".to_owned()))?;
+
+    // looked up first, the user store is never locked while holding the 2FA store
+    if enabled_totp_secret(&state, &email).await?.is_some() {
+        return Err(AuthAPIError::InvalidData(
+            "Login attempt uses an authenticator app".to_owned(),
+        ));
+    }
+
+    let code = TwoFACode::generate(&TWO_FACTOR_POLICY);
+    {
+        let mut store = state.two_fa_code_store.write().await;
+        let record = pending_login_attempt(&mut *store, &email, &id).await?;
+        if record.resends >= TWO_FACTOR_POLICY.max_resends {
+            return Err(AuthAPIError::TooManyTwoFactorResends);
+        }
+        if record.resend_cooldown(Utc::now().timestamp()).is_some() {
+            return Err(AuthAPIError::TooManyRequests);
+        }
+
+        store
+            .resend_code(&id, code.clone())
+            .await
+            .map_err(|e| match e {
+                TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+    }
+
+    send_2fa_code(&state, &email, &code)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(TwoFactorAuthResponse {
+        message: "A new 2FA code was sent".to_owned(),
+        login_attempt_id: id.as_ref().to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
@@ -0,0 +1,61 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::{EmailVerificationTokenStoreError, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::routes::verify_email::send_verification_email;
+
+#[derive(Debug, Deserialize)]
+pub struct ResendVerificationEmailRequest {
+    email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResendVerificationEmailResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Resend verification email route", skip_all)]
+pub async fn resend_verification_email(
+    State(state): State<AppState>,
+    Json(request): Json<ResendVerificationEmailRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+
+    // The cooldown is checked before looking up the account, so a 429 doesn't give away whether it exists.
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .start_cooldown(&email)
+        .await
+        .map_err(|e| match e {
+            EmailVerificationTokenStoreError::CooldownActive => AuthAPIError::TooManyRequests,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let response = Json(ResendVerificationEmailResponse {
+        message: "If the account exists and is not verified yet, a new verification link was sent"
+            .to_owned(),
+    });
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Ok((StatusCode::OK, response)),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    if !user.email_verified() {
+        send_verification_email(&state, &email)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+    }
+
+    Ok((StatusCode::OK, response))
+}
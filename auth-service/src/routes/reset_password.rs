@@ -0,0 +1,77 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::domain::password_reset_token::PasswordResetToken;
+use crate::routes::logout::log_out_everywhere;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetPasswordRequest {
+    email: Secret<String>,
+    reset_token: Secret<String>,
+    new_password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetPasswordResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Reset password route", skip_all)]
+pub async fn reset_password(
+    State(state): State<AppState>,
+    Json(request): Json<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email =
+        Email::parse(request.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
+    let token = PasswordResetToken::parse(request.reset_token)
+        .map_err(|_| AuthAPIError::InvalidData("Reset token".to_owned()))?;
+    let password = Password::parse(request.new_password)
+        .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
+
+    {
+        let mut reset_store = state.password_reset_token_store.write().await;
+        let expected = reset_store
+            .get_token(&email)
+            .await
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+        if expected.ne(&token) {
+            return Err(AuthAPIError::InvalidToken);
+        }
+
+        // consume the token first, a link can only ever be used once
+        reset_store
+            .remove_token(&email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    state
+        .user_store
+        .write()
+        .await
+        .update_password(&email, password)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    // whoever had the old password should not stay logged in
+    log_out_everywhere(&state, &email).await?;
+    let _ = state
+        .two_fa_code_store
+        .write()
+        .await
+        .remove_codes(&email)
+        .await;
+
+    let response = Json(ResetPasswordResponse {
+        message: "Password was reset successfully!".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
@@ -0,0 +1,214 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use chrono::Utc;
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::app_state::AppState;
+use crate::domain::auth_method::AuthMethod;
+use crate::domain::data_store::{Session, SessionStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::routes::jwt::{authenticated_claims, fetch_role, fetch_token_version};
+use crate::utils::{
+    auth::{generate_auth_cookie, generate_refresh_cookie},
+    client_info::ClientInfo,
+    constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: i64,
+    pub last_seen_at: i64,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub current: bool, // the session the request was made with
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevokeSessionsResponse {
+    pub message: String,
+    pub revoked: usize,
+}
+
+#[tracing::instrument(name = "List sessions route", skip_all)]
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticated_claims(&state, &jar).await?;
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: session.id == claims.sid,
+            id: session.id,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            ip: session.ip,
+            user_agent: session.user_agent,
+        })
+        .collect();
+    Ok(Json(sessions))
+}
+
+#[tracing::instrument(name = "Revoke session route", skip_all)]
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
+    let claims = authenticated_claims(&state, &jar).await?;
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    end_session(&state, &email, &id)
+        .await
+        .map_err(|e| match e {
+            SessionStoreError::SessionNotFound => AuthAPIError::SessionNotFound,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    // revoking the session of the request is a logout
+    let jar = if id == claims.sid {
+        jar.remove(Cookie::from(JWT_COOKIE_NAME))
+            .remove(Cookie::from(REFRESH_TOKEN_COOKIE_NAME))
+    } else {
+        jar
+    };
+
+    let response = Json(RevokeSessionsResponse {
+        message: "Session was revoked".to_owned(),
+        revoked: 1,
+    });
+    Ok((jar, (StatusCode::OK, response)))
+}
+
+#[tracing::instrument(name = "Revoke other sessions route", skip_all)]
+pub async fn revoke_other_sessions(
+    State(state): State<AppState>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims = authenticated_claims(&state, &jar).await?;
+    let email = Email::parse(Secret::new(claims.sub)).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let sessions = state
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let mut revoked = 0;
+    for session in sessions.iter().filter(|session| session.id != claims.sid) {
+        match end_session(&state, &email, &session.id).await {
+            Ok(()) => revoked += 1,
+            // already ended by another request
+            Err(SessionStoreError::SessionNotFound) => {}
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+    }
+
+    let response = Json(RevokeSessionsResponse {
+        message: "Every other session was revoked".to_owned(),
+        revoked,
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Registers a new session for the client and hands out its cookies.
+// Unverified accounts only get to keep the session until the JWT expires, they get no refresh token.
+pub(crate) async fn issue_session(
+    state: &AppState,
+    email: &Email,
+    email_verified: bool,
+    amr: &[AuthMethod],
+    client: &ClientInfo,
+    jar: CookieJar,
+) -> Result<CookieJar, AuthAPIError> {
+    let now = Utc::now().timestamp();
+    let session = Session {
+        id: Uuid::new_v4().to_string(),
+        email: email.clone(),
+        created_at: now,
+        last_seen_at: now,
+        ip: client.ip.clone(),
+        user_agent: client.user_agent.clone(),
+    };
+    let id = session.id.clone();
+    state
+        .session_store
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let version = fetch_token_version(state, email).await?;
+    let role = fetch_role(state, email).await?;
+    let auth_cookie =
+        generate_auth_cookie(&*state.keyring.read().await, email, &id, version, role, amr)
+            .map_err(AuthAPIError::UnexpectedError)?;
+    let jar = jar.add(auth_cookie);
+    if !email_verified {
+        return Ok(jar);
+    }
+
+    let mut refresh_store = state.refresh_token_store.write().await;
+    let refresh_cookie = generate_refresh_cookie(&mut *refresh_store, email, amr, &id)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    Ok(jar.add(refresh_cookie))
+}
+
+// Removes one session of the user along with its refresh tokens.
+pub(crate) async fn end_session(
+    state: &AppState,
+    email: &Email,
+    id: &str,
+) -> Result<(), SessionStoreError> {
+    state
+        .session_store
+        .write()
+        .await
+        .remove_session(email, id)
+        .await?;
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_family(id)
+        .await
+        .map_err(|e| SessionStoreError::UnexpectedError(e.into()))
+}
+
+// Logs the user out everywhere, their tokens stop working right away.
+pub(crate) async fn end_all_sessions(state: &AppState, email: &Email) -> Result<(), AuthAPIError> {
+    state
+        .session_store
+        .write()
+        .await
+        .remove_user_sessions(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    state
+        .refresh_token_store
+        .write()
+        .await
+        .revoke_user(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+}
@@ -1,6 +1,8 @@
 use crate::app_state::AppState;
+use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::user::User;
+use crate::routes::verify_email::send_verification_email;
 use axum::http::StatusCode;
 use axum::{extract::State, response::IntoResponse, Json};
 use secrecy::Secret;
@@ -31,18 +33,33 @@ pub async fn signup(
         Err(e) => return Err(AuthAPIError::InvalidData(e.to_string())),
     };
 
+    let email: &Email = user.as_ref();
+    let email = email.clone();
     let mut user_store = state.user_store.write().await;
 
-    if user_store.get_user(&user.as_ref()).await.is_ok() {
+    if user_store.get_user(&email).await.is_ok() {
         return Err(AuthAPIError::UserAlreadyExists);
     }
 
     if let Err(e) = user_store.add_user(user).await {
         return Err(AuthAPIError::UnexpectedError(e.into()));
     };
+    drop(user_store);
+
+    // The account exists at this point, if the email doesn't go out the user can ask for another one.
+    let _ = state
+        .email_verification_token_store
+        .write()
+        .await
+        .start_cooldown(&email)
+        .await;
+    if let Err(e) = send_verification_email(&state, &email).await {
+        tracing::warn!("Fail to send verification email: {:?}", e);
+    }
 
     let response = Json(SignupResponse {
-        message: "User created successfully!".to_string(),
+        message: "User created successfully! Please check your email to verify your account."
+            .to_string(),
     });
     Ok((StatusCode::CREATED, response).into_response())
 }
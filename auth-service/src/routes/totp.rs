@@ -0,0 +1,199 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::UserStoreError;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::domain::totp_secret::TotpSecret;
+use crate::domain::two_fa_code::TwoFACode;
+use crate::routes::jwt::authenticated_email;
+use crate::routes::unlock_account::confirm_password;
+use crate::utils::constants::{TOTP_ENCRYPTION_KEY, TOTP_SKEW_STEPS};
+
+#[derive(Debug, Deserialize)]
+pub struct TotpEnrollRequest {
+    password: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+    // base64 encoded PNG
+    pub qr_code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TotpConfirmRequest {
+    code: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpConfirmResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Enroll TOTP route", skip_all)]
+pub async fn enroll_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<TotpEnrollRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&state, &jar).await?;
+    let password = Password::parse(request.password)
+        .map_err(|_| AuthAPIError::InvalidData("Password".to_owned()))?;
+
+    // a stolen session alone could otherwise put its own authenticator app on the account, and
+    // keep the owner out of every later login
+    confirm_password(&state, &email, &password).await?;
+
+    let secret = TotpSecret::default();
+    let encrypted = secret
+        .encrypt(&TOTP_ENCRYPTION_KEY)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    {
+        let mut user_store = state.user_store.write().await;
+        let record = user_store
+            .get_totp(&email)
+            .await
+            .map_err(user_store_error)?;
+        // replacing a working authenticator app needs its own flow, it can't happen by accident
+        if record.is_some_and(|record| record.confirmed) {
+            return Err(AuthAPIError::TwoFactorAlreadyEnabled);
+        }
+        user_store
+            .set_totp_secret(&email, encrypted)
+            .await
+            .map_err(user_store_error)?;
+    }
+
+    let otpauth_uri = secret
+        .otpauth_uri(&email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let qr_code = secret
+        .qr_code_png(&email)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let response = Json(TotpEnrollResponse {
+        secret: secret.as_ref().expose_secret().to_owned(),
+        otpauth_uri,
+        qr_code: STANDARD.encode(qr_code),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Confirm TOTP route", skip_all)]
+pub async fn confirm_totp(
+    State(state): State<AppState>,
+    jar: CookieJar,
+    Json(request): Json<TotpConfirmRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = authenticated_email(&state, &jar).await?;
+    let code = TwoFACode::parse_totp(request.code)
+        .map_err(|_| AuthAPIError::InvalidData("2FA Code".to_owned()))?;
+
+    let record = state
+        .user_store
+        .read()
+        .await
+        .get_totp(&email)
+        .await
+        .map_err(user_store_error)?;
+    let record = match record {
+        Some(record) if !record.confirmed => record,
+        Some(_) => return Err(AuthAPIError::TwoFactorAlreadyEnabled),
+        None => {
+            return Err(AuthAPIError::InvalidData(
+                "No authenticator app enrollment was started".to_owned(),
+            ))
+        }
+    };
+    let secret = record
+        .secret
+        .decrypt(&TOTP_ENCRYPTION_KEY)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    check_totp_code(&state, &email, &secret, &code).await?;
+    state
+        .user_store
+        .write()
+        .await
+        .confirm_totp(&email)
+        .await
+        .map_err(user_store_error)?;
+
+    let response = Json(TotpConfirmResponse {
+        message: "Authenticator app enabled successfully!".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Returns the authenticator app secret of the account, only once its enrollment was confirmed.
+pub(crate) async fn enabled_totp_secret(
+    state: &AppState,
+    email: &Email,
+) -> Result<Option<TotpSecret>, AuthAPIError> {
+    let record = state
+        .user_store
+        .read()
+        .await
+        .get_totp(email)
+        .await
+        .map_err(user_store_error)?;
+    match record {
+        Some(record) if record.confirmed => record
+            .secret
+            .decrypt(&TOTP_ENCRYPTION_KEY)
+            .map(Some)
+            .map_err(AuthAPIError::UnexpectedError),
+        _ => Ok(None),
+    }
+}
+
+// Checks a code from the authenticator app and burns its time step so it can't be replayed.
+pub(crate) async fn check_totp_code(
+    state: &AppState,
+    email: &Email,
+    secret: &TotpSecret,
+    code: &TwoFACode,
+) -> Result<(), AuthAPIError> {
+    let now: u64 = Utc::now()
+        .timestamp()
+        .try_into()
+        .map_err(|e: std::num::TryFromIntError| AuthAPIError::UnexpectedError(e.into()))?;
+    let step = secret
+        .verify(code, now, *TOTP_SKEW_STEPS)
+        .map_err(AuthAPIError::UnexpectedError)?
+        .ok_or(AuthAPIError::MismatchIdentification)?;
+
+    state
+        .user_store
+        .write()
+        .await
+        .use_totp_step(email, step)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::TotpCodeReused => {
+                tracing::warn!("An authenticator app code was used twice");
+                AuthAPIError::MismatchIdentification
+            }
+            e => user_store_error(e),
+        })
+}
+
+fn user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
@@ -0,0 +1,165 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use chrono::Utc;
+use color_eyre::eyre::Result;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::account_lockout::LockoutRecord;
+use crate::domain::data_store::{AccountLockoutStoreError, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::password::Password;
+use crate::domain::unlock_token::UnlockToken;
+use crate::domain::user::User;
+use crate::utils::constants::{AUTH_SERVICE_URL, LOGIN_LOCKOUT_POLICY, UNLOCK_TOKEN_TTL_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct UnlockAccountQuery {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UnlockAccountResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Unlock account route", skip_all)]
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    Query(query): Query<UnlockAccountQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token = UnlockToken::parse(query.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let mut store = state.account_lockout_store.write().await;
+    let email = store
+        .consume_unlock_token(&token)
+        .await
+        .map_err(|e| match e {
+            AccountLockoutStoreError::TokenNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+    store
+        .remove_record(&email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    tracing::info!("Account was unlocked by its owner");
+    let response = Json(UnlockAccountResponse {
+        message: "Account unlocked, you can log in again".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Checks the password of a signed in account before a sensitive change. Wrong passwords count
+// against the same lockout as failed logins, or a stolen session could guess it for as long as
+// it likes.
+pub(crate) async fn confirm_password(
+    state: &AppState,
+    email: &Email,
+    password: &Password,
+) -> Result<User, AuthAPIError> {
+    let lockout = state
+        .account_lockout_store
+        .read()
+        .await
+        .get_record(email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if let Some(seconds) = lockout.locked_for(Utc::now().timestamp()) {
+        return Err(AuthAPIError::AccountLocked(seconds));
+    }
+
+    let result = state
+        .user_store
+        .read()
+        .await
+        .validate_user(email, password)
+        .await;
+    let user = match result {
+        Ok(user) => user,
+        Err(UserStoreError::InvalidCredentials) => {
+            return Err(register_failed_login(state, email, true).await)
+        }
+        Err(_) => return Err(AuthAPIError::IncorrectCredentials),
+    };
+    if lockout != LockoutRecord::default() {
+        state
+            .account_lockout_store
+            .write()
+            .await
+            .remove_record(email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+    Ok(user)
+}
+
+// Counts a wrong password against the account, and locks it once there were too many.
+// The owner gets an email with a link to unlock it right away, if there is an account at all.
+pub(crate) async fn register_failed_login(
+    state: &AppState,
+    email: &Email,
+    account_exists: bool,
+) -> AuthAPIError {
+    let locked_for = {
+        let mut store = state.account_lockout_store.write().await;
+        let failed_attempts = match store.count_failure(email).await {
+            Ok(failed_attempts) => failed_attempts,
+            Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+        };
+        let locked_for = LOGIN_LOCKOUT_POLICY.register_failure(failed_attempts);
+        if let Some(seconds) = locked_for {
+            if let Err(e) = store.lock(email, Utc::now().timestamp() + seconds).await {
+                return AuthAPIError::UnexpectedError(e.into());
+            }
+        }
+        locked_for
+    };
+
+    let Some(seconds) = locked_for else {
+        return AuthAPIError::IncorrectCredentials;
+    };
+    tracing::warn!("Account was locked for {seconds} seconds after too many failed logins");
+    // the lock holds even if the email doesn't go out
+    if !account_exists {
+        return AuthAPIError::AccountLocked(seconds);
+    }
+    if let Err(e) = send_unlock_email(state, email, seconds).await {
+        tracing::error!("Fail to send unlock email: {:?}", e);
+    }
+    AuthAPIError::AccountLocked(seconds)
+}
+
+#[tracing::instrument(name = "Send unlock email", skip_all)]
+async fn send_unlock_email(state: &AppState, email: &Email, seconds: i64) -> Result<()> {
+    let token = UnlockToken::default();
+    state
+        .account_lockout_store
+        .write()
+        .await
+        .add_unlock_token(email.clone(), token.clone())
+        .await?;
+
+    let link = unlock_link(&token)?;
+    let body = format!(
+        "There were too many failed attempts to log into your account, so it is locked for {} seconds.\nIf this was you, you can unlock it right away by opening this link: {}\nThe link expires in {} hours. If this wasn't you, consider changing your password.",
+        seconds,
+        link,
+        UNLOCK_TOKEN_TTL_SECONDS / 3600
+    );
+    state
+        .email_client
+        .send_email(email, "Let's Get Rusty Account Locked", &body)
+        .await
+}
+
+fn unlock_link(token: &UnlockToken) -> Result<Url> {
+    let mut url = Url::parse(&AUTH_SERVICE_URL)?.join("/unlock-account")?;
+    url.query_pairs_mut()
+        .append_pair("token", token.as_ref().expose_secret());
+    Ok(url)
+}
@@ -2,15 +2,22 @@ use axum::extract::State;
 use axum::http::StatusCode;
 use axum::{response::IntoResponse, Json};
 use axum_extra::extract::CookieJar;
+use chrono::Utc;
 use secrecy::Secret;
 use serde::Deserialize;
 
 use crate::app_state::AppState;
+use crate::domain::auth_method::AuthMethod;
+use crate::domain::data_store::{TwoFACodeStore, TwoFACodeStoreError, TwoFARecord};
 use crate::domain::email::Email;
 use crate::domain::error::AuthAPIError;
 use crate::domain::login_attempt_id::LoginAttemptId;
 use crate::domain::two_fa_code::TwoFACode;
-use crate::utils::auth::generate_auth_cookie;
+use crate::domain::user::User;
+use crate::routes::sessions::issue_session;
+use crate::routes::totp::{check_totp_code, enabled_totp_secret};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::TWO_FACTOR_POLICY;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -25,6 +32,7 @@ pub struct VerifyToken {
 #[tracing::instrument(name = "Verify 2FA code route", skip_all)]
 pub async fn verify_2fa(
     State(state): State<AppState>,
+    client: ClientInfo,
     jar: CookieJar,
     Json(input): Json<VerifyToken>,
 ) -> Result<(CookieJar, impl IntoResponse), AuthAPIError> {
@@ -32,27 +40,156 @@ pub async fn verify_2fa(
         Email::parse(input.email).map_err(|_| AuthAPIError::InvalidData("Email".to_owned()))?;
     let id = LoginAttemptId::parse(input.id.clone())
         .map_err(|_| AuthAPIError::InvalidData("Login ID".to_owned()))?;
-    let code = TwoFACode::parse(input.code.clone())
-        .map_err(|_| AuthAPIError::InvalidData("2FA Code".to_owned()))?;
 
-    let mut two_fa_store = state.two_fa_code_store.write().await;
-    let info = two_fa_store
-        .get_code(&email)
+    let record = check_2fa_code(&state, &email, &id, input.code).await?;
+
+    let jar = complete_2fa_login(&state, &record, &client, jar).await?;
+    Ok((jar, StatusCode::OK.into_response()))
+}
+
+// Ends the login attempt once the second factor was checked and hands out the session cookies.
+pub(crate) async fn complete_2fa_login(
+    state: &AppState,
+    record: &TwoFARecord,
+    client: &ClientInfo,
+    jar: CookieJar,
+) -> Result<CookieJar, AuthAPIError> {
+    let email = &record.email;
+    let amr = AuthMethod::with_second_factor(&record.amr);
+
+    let user = end_login_attempt(state, record).await?;
+    issue_session(state, email, user.email_verified(), &amr, client, jar).await
+}
+
+// Throws the login attempt away and returns its account, as long as the account may still log in.
+// Only the first of two requests racing with the same login attempt gets past this.
+pub(crate) async fn end_login_attempt(
+    state: &AppState,
+    record: &TwoFARecord,
+) -> Result<User, AuthAPIError> {
+    state
+        .two_fa_code_store
+        .write()
         .await
-        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+        .remove_code(&record.id)
+        .await
+        .map_err(|e| match e {
+            TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&record.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    // the account may have been disabled while the code was on its way
+    if user.disabled() {
+        return Err(AuthAPIError::AccountDisabled);
+    }
+    Ok(user)
+}
+
+// Checks a 2FA code against the given login attempt of the account and returns the attempt.
+// Accounts with an authenticator app use its codes instead of the emailed one, so the code is
+// only parsed once it is known which of the two it should be.
+pub(crate) async fn check_2fa_code(
+    state: &AppState,
+    email: &Email,
+    id: &LoginAttemptId,
+    code: Secret<String>,
+) -> Result<TwoFARecord, AuthAPIError> {
+    // looked up first, the user store is never locked while holding the 2FA store
+    let totp_secret = enabled_totp_secret(state, email).await?;
+    let code = match totp_secret {
+        Some(_) => TwoFACode::parse_totp(code),
+        None => TwoFACode::parse(code),
+    }
+    .map_err(|_| AuthAPIError::InvalidData("2FA Code".to_owned()))?;
+
+    let info =
+        pending_login_attempt(&mut *state.two_fa_code_store.write().await, email, id).await?;
+    let attempts = spend_2fa_attempt(state, id).await?;
+
+    let matched = match totp_secret {
+        Some(secret) => match check_totp_code(state, email, &secret, &code).await {
+            Ok(()) => true,
+            Err(AuthAPIError::MismatchIdentification) => false,
+            Err(e) => return Err(e),
+        },
+        None => info.code.matches(&code),
+    };
+    if !matched {
+        return Err(register_failed_2fa_attempt(state, id, attempts).await);
+    }
+    Ok(info)
+}
 
-    if info.id.ne(&id) || info.code.ne(&code) {
+// Looks up the login attempt of the user. Expired ones are thrown away here rather than left to
+// the store, the in-memory one never expires anything on its own.
+pub(crate) async fn pending_login_attempt(
+    store: &mut dyn TwoFACodeStore,
+    email: &Email,
+    id: &LoginAttemptId,
+) -> Result<TwoFARecord, AuthAPIError> {
+    let record = store
+        .get_code(id)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+    if record.email.ne(email) {
         return Err(AuthAPIError::MismatchIdentification);
     }
+    if record.is_expired(Utc::now().timestamp()) {
+        if let Err(e) = store.remove_code(id).await {
+            tracing::error!("Fail to remove expired 2FA code: {:?}", e);
+        }
+        return Err(AuthAPIError::TwoFactorCodeExpired);
+    }
+    Ok(record)
+}
+
+// Counts a guess against the login attempt before its code is checked, so requests racing for
+// the last guess don't all get it. Guesses past the policy's limit aren't checked at all.
+pub(crate) async fn spend_2fa_attempt(
+    state: &AppState,
+    id: &LoginAttemptId,
+) -> Result<u32, AuthAPIError> {
+    let mut store = state.two_fa_code_store.write().await;
+    let attempts = store.count_attempt(id).await.map_err(|e| match e {
+        // the code expired or was thrown away in the meantime
+        TwoFACodeStoreError::LoginAttemptIdNotFound => AuthAPIError::IncorrectCredentials,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    })?;
+    if attempts <= TWO_FACTOR_POLICY.max_attempts {
+        return Ok(attempts);
+    }
 
-    // can't imagine this would break? maybe database error?
-    if let Err(e) = two_fa_store.remove_code(&email).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+    // the request that spent the last guess is throwing the code away already
+    match store.remove_code(id).await {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
     }
+    Err(AuthAPIError::TooManyTwoFactorAttempts)
+}
 
-    let auth_cookie =
-        generate_auth_cookie(&email).map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
-    let jar = jar.add(auth_cookie);
+// Called once the code of a guess counted by `spend_2fa_attempt` turned out wrong. The last
+// guess the policy allows throws the 2FA code away, so the user has to log in again for a new one.
+pub(crate) async fn register_failed_2fa_attempt(
+    state: &AppState,
+    id: &LoginAttemptId,
+    attempts: u32,
+) -> AuthAPIError {
+    tracing::warn!(attempts, "A wrong 2FA code was given for a login attempt");
+    if attempts < TWO_FACTOR_POLICY.max_attempts {
+        return AuthAPIError::MismatchIdentification;
+    }
 
-    Ok((jar, StatusCode::OK.into_response()))
+    match state.two_fa_code_store.write().await.remove_code(id).await {
+        Ok(()) | Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {}
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()),
+    }
+    tracing::warn!("2FA code was thrown away after too many wrong guesses");
+    AuthAPIError::TooManyTwoFactorAttempts
 }
@@ -0,0 +1,93 @@
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::{response::IntoResponse, Json};
+use color_eyre::eyre::Result;
+use reqwest::Url;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::app_state::AppState;
+use crate::domain::data_store::{EmailVerificationTokenStoreError, UserStoreError};
+use crate::domain::email::Email;
+use crate::domain::email_verification_token::EmailVerificationToken;
+use crate::domain::error::AuthAPIError;
+use crate::utils::constants::{AUTH_SERVICE_URL, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct VerifyEmailQuery {
+    token: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+#[tracing::instrument(name = "Verify email route", skip_all)]
+pub async fn verify_email(
+    State(state): State<AppState>,
+    Query(query): Query<VerifyEmailQuery>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let token =
+        EmailVerificationToken::parse(query.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let email = state
+        .email_verification_token_store
+        .write()
+        .await
+        .consume_token(&token)
+        .await
+        .map_err(|e| match e {
+            EmailVerificationTokenStoreError::UnexpectedError(e) => {
+                AuthAPIError::UnexpectedError(e)
+            }
+            _ => AuthAPIError::InvalidToken,
+        })?;
+
+    // the account may have been removed since the link was sent
+    state
+        .user_store
+        .write()
+        .await
+        .verify_email(&email)
+        .await
+        .map_err(|e| match e {
+            UserStoreError::UserNotFound => AuthAPIError::InvalidToken,
+            e => AuthAPIError::UnexpectedError(e.into()),
+        })?;
+
+    let response = Json(VerifyEmailResponse {
+        message: "Email address verified successfully!".to_owned(),
+    });
+    Ok((StatusCode::OK, response))
+}
+
+// Stores a fresh verification token for the user and mails them the link to it.
+#[tracing::instrument(name = "Send verification email", skip_all)]
+pub(crate) async fn send_verification_email(state: &AppState, email: &Email) -> Result<()> {
+    let token = EmailVerificationToken::default();
+    state
+        .email_verification_token_store
+        .write()
+        .await
+        .add_token(email.clone(), token.clone())
+        .await?;
+
+    let link = verification_link(&token)?;
+    let body = format!(
+        "Welcome! Please confirm your email address by opening this link: {}\nThe link expires in {} hours.",
+        link,
+        EMAIL_VERIFICATION_TOKEN_TTL_SECONDS / 3600
+    );
+    state
+        .email_client
+        .send_email(email, "Let's Get Rusty Email Verification", &body)
+        .await
+}
+
+fn verification_link(token: &EmailVerificationToken) -> Result<Url> {
+    let mut url = Url::parse(&AUTH_SERVICE_URL)?.join("/verify-email")?;
+    url.query_pairs_mut()
+        .append_pair("token", token.as_ref().expose_secret());
+    Ok(url)
+}
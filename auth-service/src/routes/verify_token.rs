@@ -1,23 +1,67 @@
 use crate::app_state::AppState;
-use crate::routes::jwt::JWToken;
-use crate::utils::auth::validate_token;
+use crate::domain::{data_store::SessionStoreError, email::Email, error::AuthAPIError};
+use crate::routes::jwt::{current_token_version, JWToken};
+use crate::utils::auth::{validate_token, validate_token_for_audience};
+use crate::utils::authorized::has_role;
 use axum::extract::State;
 use axum::http::StatusCode;
 use axum::{response::IntoResponse, Json};
+use secrecy::Secret;
 
 #[tracing::instrument(name = "Verify Token", skip_all)]
 pub async fn verify_token(
     State(app): State<AppState>,
     Json(jwt): Json<JWToken>,
 ) -> impl IntoResponse {
+    let claims = {
+        let keyring = app.keyring.read().await;
+        match &jwt.audience {
+            Some(audience) => validate_token_for_audience(&keyring, &jwt.token, audience).await,
+            None => validate_token(&keyring, &jwt.token).await,
+        }
+    };
+    let Ok(claims) = claims else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
     let store = app.banned_token_store.clone();
     let ban_list = store.read().await;
-    if ban_list.check_token(&jwt.token).await {
+    if ban_list.check_token(&claims.jti).await {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    drop(ban_list);
+
+    let Ok(email) = Email::parse(Secret::new(claims.sub)) else {
         return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    // the token outlives its session when the user revokes it, and its sub when the session
+    // moved to a new email address
+    match app
+        .session_store
+        .write()
+        .await
+        .touch_session(&claims.sid)
+        .await
+    {
+        Ok(session) if session.email == email => {}
+        Ok(_) | Err(SessionStoreError::SessionNotFound) => {
+            return StatusCode::UNAUTHORIZED.into_response()
+        }
+        Err(e) => return AuthAPIError::UnexpectedError(e.into()).into_response(),
+    }
+
+    // tokens issued before the user logged out everywhere
+    match current_token_version(&app, &email).await {
+        Ok(version) if version == claims.ver => {}
+        Ok(_) => return StatusCode::UNAUTHORIZED.into_response(),
+        Err(e) => return e.into_response(),
     }
 
-    match validate_token(&jwt.token).await {
-        Ok(_) => StatusCode::OK.into_response(),
-        Err(_) => StatusCode::UNAUTHORIZED.into_response(),
+    match jwt.role {
+        Some(required) if !has_role(claims.role, required) => {
+            AuthAPIError::Forbidden.into_response()
+        }
+        _ => StatusCode::OK.into_response(),
     }
 }
@@ -0,0 +1,141 @@
+use color_eyre::eyre::{Context, Report};
+use std::path::PathBuf;
+
+use crate::{
+    domain::data_store::{keyring_states, KeyringEntry, SigningKeyStore, SigningKeyStoreError},
+    services::keyring::StoredSigningKey,
+};
+
+// Keeps the keyring in a JSON file, for deployments that would rather not keep it in Postgres.
+// Changes are checked against the file right before it is replaced, but nothing locks it in
+// between, so only one instance should rotate keys in a file keyring at a time.
+pub struct FileSigningKeyStore {
+    path: PathBuf,
+}
+
+impl FileSigningKeyStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for FileSigningKeyStore {
+    #[tracing::instrument(name = "Get signing keys from file", skip_all)]
+    async fn get_keys(&self) -> Result<Vec<KeyringEntry>, SigningKeyStoreError> {
+        let data = match tokio::fs::read_to_string(&self.path).await {
+            Ok(data) => data,
+            // nothing saved yet
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(SigningKeyStoreError::UnexpectedError(
+                    Report::new(e).wrap_err("Fail to read keyring file"),
+                ))
+            }
+        };
+
+        let keys: Vec<StoredSigningKey> = serde_json::from_str(&data)
+            .wrap_err("Fail to deserialize keyring file")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+        keys.into_iter()
+            .map(StoredSigningKey::open)
+            .collect::<Result<_, _>>()
+            .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Save signing keys to file", skip_all)]
+    async fn save_keys(
+        &mut self,
+        current: &[KeyringEntry],
+        keys: &[KeyringEntry],
+    ) -> Result<(), SigningKeyStoreError> {
+        if keyring_states(&self.get_keys().await?) != keyring_states(current) {
+            return Err(SigningKeyStoreError::Conflict);
+        }
+
+        let keys = keys
+            .iter()
+            .map(StoredSigningKey::seal)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+        let data = serde_json::to_string_pretty(&keys)
+            .wrap_err("Fail to serialize keyring")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        // write next to the file and move it over, so a crash can't leave half a keyring behind
+        let temp = self.path.with_extension("tmp");
+        tokio::fs::write(&temp, data)
+            .await
+            .wrap_err("Fail to write keyring file")
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+        tokio::fs::rename(&temp, &self.path)
+            .await
+            .wrap_err("Fail to replace keyring file")
+            .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::signing_key::{KeyState, SigningAlgorithm, SigningKey};
+    use uuid::Uuid;
+
+    fn store() -> FileSigningKeyStore {
+        let path = std::env::temp_dir().join(format!("keyring-{}.json", Uuid::new_v4()));
+        FileSigningKeyStore::new(path)
+    }
+
+    #[tokio::test]
+    async fn missing_file_should_be_empty_keyring() {
+        let store = store();
+        assert!(store.get_keys().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn saved_keys_should_be_read_back() {
+        let mut store = store();
+        let keys = [
+            KeyringEntry {
+                key: SigningKey::generate(SigningAlgorithm::EdDSA).unwrap(),
+                state: KeyState::Active,
+                created_at: 1,
+            },
+            KeyringEntry {
+                key: SigningKey::generate(SigningAlgorithm::HS256).unwrap(),
+                state: KeyState::Next,
+                created_at: 2,
+            },
+        ];
+        store.save_keys(&[], &keys).await.unwrap();
+
+        let stored = store.get_keys().await.unwrap();
+        assert_eq!(stored.len(), 2);
+        for (stored, key) in stored.iter().zip(keys.iter()) {
+            assert_eq!(stored.key.kid(), key.key.kid());
+            assert_eq!(stored.state, key.state);
+        }
+
+        // the private keys never hit the disk in the clear
+        let data = std::fs::read_to_string(&store.path).unwrap();
+        assert!(!data.contains("PRIVATE KEY"));
+        std::fs::remove_file(&store.path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn save_keys_over_a_changed_file_should_fail() {
+        let mut store = store();
+        let keys = [KeyringEntry {
+            key: SigningKey::generate(SigningAlgorithm::HS256).unwrap(),
+            state: KeyState::Active,
+            created_at: 1,
+        }];
+        store.save_keys(&[], &keys).await.unwrap();
+
+        assert_eq!(
+            store.save_keys(&[], &keys).await,
+            Err(SigningKeyStoreError::Conflict)
+        );
+        std::fs::remove_file(&store.path).unwrap();
+    }
+}
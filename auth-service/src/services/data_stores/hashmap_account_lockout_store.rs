@@ -0,0 +1,148 @@
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        account_lockout::LockoutRecord,
+        data_store::{AccountLockoutStore, AccountLockoutStoreError},
+        email::Email,
+        unlock_token::UnlockToken,
+    },
+    utils::constants::{LOCKOUT_RECORD_TTL_SECONDS, UNLOCK_TOKEN_TTL_SECONDS},
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapAccountLockoutStore {
+    // email -> (record, unix timestamp it expires at)
+    records: HashMap<Email, (LockoutRecord, i64)>,
+    // token -> (email, unix timestamp it expires at)
+    tokens: HashMap<String, (Email, i64)>,
+}
+
+impl HashmapAccountLockoutStore {
+    // the record to write to, an expired one starts over, and every write keeps it for another TTL
+    fn fresh_record(&mut self, email: &Email) -> &mut LockoutRecord {
+        let now = Utc::now().timestamp();
+        let (record, expires_at) = self
+            .records
+            .entry(email.clone())
+            .or_insert_with(|| (LockoutRecord::default(), now));
+        if *expires_at <= now {
+            *record = LockoutRecord::default();
+        }
+        *expires_at = now + LOCKOUT_RECORD_TTL_SECONDS;
+        record
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountLockoutStore for HashmapAccountLockoutStore {
+    async fn get_record(&self, email: &Email) -> Result<LockoutRecord, AccountLockoutStoreError> {
+        match self.records.get(email) {
+            Some((record, expires_at)) if *expires_at > Utc::now().timestamp() => {
+                Ok(record.clone())
+            }
+            _ => Ok(LockoutRecord::default()),
+        }
+    }
+
+    async fn count_failure(&mut self, email: &Email) -> Result<u32, AccountLockoutStoreError> {
+        let record = self.fresh_record(email);
+        record.failed_attempts += 1;
+        Ok(record.failed_attempts)
+    }
+
+    async fn lock(&mut self, email: &Email, until: i64) -> Result<(), AccountLockoutStoreError> {
+        self.fresh_record(email).locked_until = Some(until);
+        Ok(())
+    }
+
+    async fn remove_record(&mut self, email: &Email) -> Result<(), AccountLockoutStoreError> {
+        self.records.remove(email);
+        Ok(())
+    }
+
+    async fn add_unlock_token(
+        &mut self,
+        email: Email,
+        token: UnlockToken,
+    ) -> Result<(), AccountLockoutStoreError> {
+        let expires_at = Utc::now().timestamp() + UNLOCK_TOKEN_TTL_SECONDS;
+        self.tokens.insert(
+            token.as_ref().expose_secret().to_owned(),
+            (email, expires_at),
+        );
+        Ok(())
+    }
+
+    async fn consume_unlock_token(
+        &mut self,
+        token: &UnlockToken,
+    ) -> Result<Email, AccountLockoutStoreError> {
+        match self.tokens.remove(token.as_ref().expose_secret()) {
+            Some((email, expires_at)) if expires_at > Utc::now().timestamp() => Ok(email),
+            _ => Err(AccountLockoutStoreError::TokenNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn get_email() -> Email {
+        Email::parse(Secret::new("test@test.com".to_owned()))
+            .expect("Unable to parse dummy email account")
+    }
+
+    #[tokio::test]
+    async fn record_should_be_stored_and_removed() {
+        let mut store = HashmapAccountLockoutStore::default();
+        let email = get_email();
+        assert_eq!(store.get_record(&email).await, Ok(LockoutRecord::default()));
+
+        assert_eq!(store.count_failure(&email).await, Ok(1));
+        assert_eq!(store.count_failure(&email).await, Ok(2));
+        store.lock(&email, 1000).await.unwrap();
+        let record = LockoutRecord {
+            failed_attempts: 2,
+            locked_until: Some(1000),
+        };
+        assert_eq!(store.get_record(&email).await, Ok(record));
+
+        store.remove_record(&email).await.unwrap();
+        assert_eq!(store.get_record(&email).await, Ok(LockoutRecord::default()));
+    }
+
+    #[tokio::test]
+    async fn expired_record_should_start_over() {
+        let mut store = HashmapAccountLockoutStore::default();
+        let email = get_email();
+        store.count_failure(&email).await.unwrap();
+        store.lock(&email, 1000).await.unwrap();
+        store.records.get_mut(&email).unwrap().1 = Utc::now().timestamp() - 1;
+
+        assert_eq!(store.get_record(&email).await, Ok(LockoutRecord::default()));
+        assert_eq!(store.count_failure(&email).await, Ok(1));
+        assert_eq!(store.get_record(&email).await.unwrap().locked_until, None);
+    }
+
+    #[tokio::test]
+    async fn unlock_token_should_only_be_consumed_once() {
+        let mut store = HashmapAccountLockoutStore::default();
+        let email = get_email();
+        let token = UnlockToken::default();
+        store
+            .add_unlock_token(email.clone(), token.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(store.consume_unlock_token(&token).await, Ok(email));
+        assert_eq!(
+            store.consume_unlock_token(&token).await,
+            Err(AccountLockoutStoreError::TokenNotFound)
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::Utc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+use crate::domain::data_store::{BannedTokenStore, BannedTokenStoreError};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapBannedTokenStore {
+    // jti -> expiry of the banned token (unix timestamp), the janitor purges the expired ones
+    pub blacklist: HashMap<String, i64>,
+}
+
+impl HashmapBannedTokenStore {
+    // Drops every entry whose token has expired by now and returns how many went. A token is
+    // still taken during the second of its exp, so its ban lasts through that second too.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = Utc::now().timestamp();
+        let before = self.blacklist.len();
+        self.blacklist.retain(|_, expires_at| *expires_at >= now);
+        before - self.blacklist.len()
+    }
+
+    // Builds a store shared behind a lock with its janitor already running.
+    pub fn with_janitor(interval: Duration) -> Arc<RwLock<Self>> {
+        let store = Arc::new(RwLock::new(Self::default()));
+        spawn_janitor(Arc::downgrade(&store), interval);
+        store
+    }
+}
+
+// Purges expired entries from the store every `interval`, for as long as the store is around.
+pub fn spawn_janitor(
+    store: Weak<RwLock<HashmapBannedTokenStore>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            let Some(store) = store.upgrade() else {
+                break;
+            };
+            let purged = store.write().await.purge_expired();
+            if purged > 0 {
+                tracing::debug!("Purged {} expired banned tokens", purged);
+            }
+        }
+    })
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for HashmapBannedTokenStore {
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError> {
+        if self.check_token(jti).await {
+            return Err(BannedTokenStoreError::TokenExist);
+        }
+        self.blacklist.insert(jti.to_owned(), expires_at);
+        Ok(())
+    }
+
+    async fn check_token(&self, jti: &str) -> bool {
+        let now = Utc::now().timestamp();
+        self.blacklist
+            .get(jti)
+            .is_some_and(|expires_at| *expires_at >= now)
+    }
+
+    async fn count(&self) -> Result<usize, BannedTokenStoreError> {
+        let now = Utc::now().timestamp();
+        Ok(self
+            .blacklist
+            .values()
+            .filter(|expires_at| **expires_at >= now)
+            .count())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn in_an_hour() -> i64 {
+        Utc::now().timestamp() + 3600
+    }
+
+    #[tokio::test]
+    async fn test_add_token_pass() {
+        let mut store = HashmapBannedTokenStore::default();
+        let token = "token";
+
+        // send token to the list
+        let result = store.add_token(token, in_an_hour()).await;
+        // our result should return Ok(())
+        assert!(result.is_ok());
+        // our token should exist in the database collection
+        assert!(store.check_token(token).await);
+    }
+
+    #[tokio::test]
+    async fn adding_duplicated_token_should_fail_test() {
+        let mut store = HashmapBannedTokenStore::default();
+        let token = "token";
+
+        assert!(store.add_token(token, in_an_hour()).await.is_ok());
+        assert!(store.add_token(token, in_an_hour()).await.is_err()); // should report an error stating the token already exist.
+    }
+
+    #[tokio::test]
+    async fn valid_token_should_pass_checks() {
+        let mut store = HashmapBannedTokenStore::default();
+        let token = "token";
+
+        assert!(store.add_token(token, in_an_hour()).await.is_ok());
+        assert!(store.check_token(token).await);
+    }
+
+    #[tokio::test]
+    async fn check_empty_store_should_fail() {
+        let store = HashmapBannedTokenStore::default();
+        let token = "token";
+        assert!(!store.check_token(token).await);
+    }
+
+    #[tokio::test]
+    async fn token_not_in_list_should_return_false() {
+        let mut store = HashmapBannedTokenStore::default();
+        let token = "token";
+        let search = "token1";
+        assert!(store.add_token(token, in_an_hour()).await.is_ok());
+        assert!(!store.check_token(search).await);
+    }
+
+    #[tokio::test]
+    async fn expired_token_should_no_longer_be_banned() {
+        let mut store = HashmapBannedTokenStore::default();
+        let expired = Utc::now().timestamp() - 1;
+        assert!(store.add_token("token", expired).await.is_ok());
+        assert!(!store.check_token("token").await);
+        assert_eq!(store.count().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn token_should_stay_banned_until_its_exp() {
+        let mut store = HashmapBannedTokenStore::default();
+        let expires_at = Utc::now().timestamp() + 1;
+        assert!(store.add_token("token", expires_at).await.is_ok());
+        assert!(store.check_token("token").await);
+    }
+
+    #[tokio::test]
+    async fn purge_should_only_drop_expired_tokens() {
+        let mut store = HashmapBannedTokenStore::default();
+        assert!(store.add_token("live", in_an_hour()).await.is_ok());
+        assert!(store
+            .add_token("expired", Utc::now().timestamp() - 1)
+            .await
+            .is_ok());
+
+        assert_eq!(store.purge_expired(), 1);
+        assert_eq!(store.blacklist.len(), 1);
+        assert_eq!(store.count().await.unwrap(), 1);
+        assert!(store.check_token("live").await);
+    }
+
+    #[tokio::test]
+    async fn janitor_should_purge_expired_tokens() {
+        let store = HashmapBannedTokenStore::with_janitor(Duration::from_millis(10));
+        let expired = Utc::now().timestamp() - 1;
+        assert!(store
+            .write()
+            .await
+            .add_token("expired", expired)
+            .await
+            .is_ok());
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(store.read().await.blacklist.is_empty());
+    }
+
+    #[tokio::test]
+    async fn janitor_should_stop_once_the_store_is_dropped() {
+        let store = Arc::new(RwLock::new(HashmapBannedTokenStore::default()));
+        let janitor = spawn_janitor(Arc::downgrade(&store), Duration::from_millis(10));
+        drop(store);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(janitor.is_finished());
+    }
+}
@@ -0,0 +1,135 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        data_store::{EmailChangeRequest, EmailChangeStore, EmailChangeStoreError},
+        email::Email,
+        email_change_token::EmailChangeToken,
+    },
+    utils::constants::EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapEmailChangeStore {
+    // keyed by the current email, along with the unix timestamp the request expires at
+    requests: HashMap<Email, (EmailChangeRequest, i64)>,
+}
+
+impl HashmapEmailChangeStore {
+    fn take(
+        &mut self,
+        matches: impl Fn(&EmailChangeRequest) -> bool,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        let email = self
+            .requests
+            .iter()
+            .find(|(_, (request, _))| matches(request))
+            .map(|(email, _)| email.clone())
+            .ok_or(EmailChangeStoreError::RequestNotFound)?;
+
+        match self.requests.remove(&email) {
+            Some((request, expires_at)) if expires_at > Utc::now().timestamp() => Ok(request),
+            _ => Err(EmailChangeStoreError::RequestNotFound),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailChangeStore for HashmapEmailChangeStore {
+    async fn add_request(
+        &mut self,
+        request: EmailChangeRequest,
+    ) -> Result<(), EmailChangeStoreError> {
+        let expires_at = Utc::now().timestamp() + EMAIL_CHANGE_TOKEN_TTL_SECONDS;
+        self.requests
+            .insert(request.email.clone(), (request, expires_at));
+        Ok(())
+    }
+
+    async fn take_confirmed(
+        &mut self,
+        confirm_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        self.take(|request| request.confirm_token.eq(confirm_token))
+    }
+
+    async fn take_cancelled(
+        &mut self,
+        cancel_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        self.take(|request| request.cancel_token.eq(cancel_token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn get_default_value() -> EmailChangeRequest {
+        EmailChangeRequest {
+            email: Email::parse(Secret::new("test@test.com".to_owned())).unwrap(),
+            new_email: Email::parse(Secret::new("new@test.com".to_owned())).unwrap(),
+            confirm_token: EmailChangeToken::default(),
+            cancel_token: EmailChangeToken::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn take_confirmed_should_return_request_once() {
+        let mut store = HashmapEmailChangeStore::default();
+        let request = get_default_value();
+        assert!(store.add_request(request.clone()).await.is_ok());
+
+        let result = store.take_confirmed(&request.confirm_token).await.unwrap();
+        assert_eq!(result.new_email, request.new_email);
+
+        let result = store.take_confirmed(&request.confirm_token).await;
+        assert_eq!(result.unwrap_err(), EmailChangeStoreError::RequestNotFound);
+        let result = store.take_cancelled(&request.cancel_token).await;
+        assert_eq!(result.unwrap_err(), EmailChangeStoreError::RequestNotFound);
+    }
+
+    #[tokio::test]
+    async fn tokens_should_not_be_interchangeable() {
+        let mut store = HashmapEmailChangeStore::default();
+        let request = get_default_value();
+        assert!(store.add_request(request.clone()).await.is_ok());
+
+        let result = store.take_confirmed(&request.cancel_token).await;
+        assert_eq!(result.unwrap_err(), EmailChangeStoreError::RequestNotFound);
+
+        let result = store.take_cancelled(&request.cancel_token).await.unwrap();
+        assert_eq!(result.email, request.email);
+    }
+
+    #[tokio::test]
+    async fn new_request_should_replace_old_request() {
+        let mut store = HashmapEmailChangeStore::default();
+        let request = get_default_value();
+        let new_request = get_default_value();
+        assert!(store.add_request(request.clone()).await.is_ok());
+        assert!(store.add_request(new_request.clone()).await.is_ok());
+
+        let result = store.take_confirmed(&request.confirm_token).await;
+        assert_eq!(result.unwrap_err(), EmailChangeStoreError::RequestNotFound);
+        assert!(store
+            .take_confirmed(&new_request.confirm_token)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn expired_request_should_not_be_found() {
+        let mut store = HashmapEmailChangeStore::default();
+        let request = get_default_value();
+        store.requests.insert(
+            request.email.clone(),
+            (request.clone(), Utc::now().timestamp() - 1),
+        );
+
+        let result = store.take_confirmed(&request.confirm_token).await;
+        assert_eq!(result.unwrap_err(), EmailChangeStoreError::RequestNotFound);
+    }
+}
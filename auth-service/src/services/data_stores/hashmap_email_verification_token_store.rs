@@ -0,0 +1,133 @@
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        data_store::{EmailVerificationTokenStore, EmailVerificationTokenStoreError},
+        email::Email,
+        email_verification_token::EmailVerificationToken,
+    },
+    utils::constants::{
+        EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+    },
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapEmailVerificationTokenStore {
+    // token -> (email, unix timestamp it expires at)
+    tokens: HashMap<String, (Email, i64)>,
+    // email -> unix timestamp the cooldown ends at
+    cooldowns: HashMap<Email, i64>,
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for HashmapEmailVerificationTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + EMAIL_VERIFICATION_TOKEN_TTL_SECONDS;
+        self.tokens.insert(
+            token.as_ref().expose_secret().to_owned(),
+            (email, expires_at),
+        );
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        match self.tokens.remove(token.as_ref().expose_secret()) {
+            Some((email, expires_at)) if expires_at > Utc::now().timestamp() => Ok(email),
+            _ => Err(EmailVerificationTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn start_cooldown(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let now = Utc::now().timestamp();
+        if self.cooldowns.get(email).is_some_and(|until| *until > now) {
+            return Err(EmailVerificationTokenStoreError::CooldownActive);
+        }
+        self.cooldowns.insert(
+            email.clone(),
+            now + EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn get_default_value() -> (Email, EmailVerificationToken) {
+        let email = Email::parse(Secret::new("test@test.com".to_owned()))
+            .expect("Unable to parse dummy email account");
+        (email, EmailVerificationToken::default())
+    }
+
+    #[tokio::test]
+    async fn consume_token_should_return_email() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let (email, token) = get_default_value();
+
+        assert!(store.add_token(email.clone(), token.clone()).await.is_ok());
+        assert_eq!(store.consume_token(&token).await.unwrap(), email);
+    }
+
+    #[tokio::test]
+    async fn consumed_token_should_not_be_found() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let (email, token) = get_default_value();
+
+        assert!(store.add_token(email, token.clone()).await.is_ok());
+        assert!(store.consume_token(&token).await.is_ok());
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(
+            result.unwrap_err(),
+            EmailVerificationTokenStoreError::TokenNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_token_should_not_be_found() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let (email, token) = get_default_value();
+        store.tokens.insert(
+            token.as_ref().expose_secret().to_owned(),
+            (email, Utc::now().timestamp() - 1),
+        );
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(
+            result.unwrap_err(),
+            EmailVerificationTokenStoreError::TokenNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn cooldown_should_block_until_it_ends() {
+        let mut store = HashmapEmailVerificationTokenStore::default();
+        let (email, _) = get_default_value();
+
+        assert!(store.start_cooldown(&email).await.is_ok());
+        let result = store.start_cooldown(&email).await;
+        assert_eq!(
+            result.unwrap_err(),
+            EmailVerificationTokenStoreError::CooldownActive
+        );
+
+        store
+            .cooldowns
+            .insert(email.clone(), Utc::now().timestamp() - 1);
+        assert!(store.start_cooldown(&email).await.is_ok());
+    }
+}
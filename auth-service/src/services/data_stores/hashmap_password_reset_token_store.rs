@@ -0,0 +1,118 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::{
+    domain::{
+        data_store::{PasswordResetTokenStore, PasswordResetTokenStoreError},
+        email::Email,
+        password_reset_token::PasswordResetToken,
+    },
+    utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapPasswordResetTokenStore {
+    // token along with the unix timestamp it expires at, Redis handles this for us with a TTL.
+    tokens: HashMap<Email, (PasswordResetToken, i64)>,
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for HashmapPasswordResetTokenStore {
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        // requesting a new link replaces the previous one
+        let expires_at = Utc::now().timestamp() + PASSWORD_RESET_TOKEN_TTL_SECONDS;
+        self.tokens.insert(email, (token, expires_at));
+        Ok(())
+    }
+
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError> {
+        match self.tokens.get(email) {
+            Some((token, expires_at)) if *expires_at > Utc::now().timestamp() => Ok(token.clone()),
+            _ => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        match self.tokens.remove(email) {
+            Some(_) => Ok(()),
+            None => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn get_default_value() -> (Email, PasswordResetToken) {
+        let email = Email::parse(Secret::new("test@test.com".to_owned()))
+            .expect("Unable to parse dummy email account");
+        (email, PasswordResetToken::default())
+    }
+
+    #[tokio::test]
+    async fn get_token_should_succeed() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let (email, token) = get_default_value();
+
+        assert!(store.add_token(email.clone(), token.clone()).await.is_ok());
+        assert_eq!(store.get_token(&email).await.unwrap(), token);
+    }
+
+    #[tokio::test]
+    async fn new_token_should_replace_old_token() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let (email, token) = get_default_value();
+        let new_token = PasswordResetToken::default();
+
+        assert!(store.add_token(email.clone(), token).await.is_ok());
+        assert!(store
+            .add_token(email.clone(), new_token.clone())
+            .await
+            .is_ok());
+        assert_eq!(store.get_token(&email).await.unwrap(), new_token);
+    }
+
+    #[tokio::test]
+    async fn removed_token_should_not_be_found() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let (email, token) = get_default_value();
+
+        assert!(store.add_token(email.clone(), token).await.is_ok());
+        assert!(store.remove_token(&email).await.is_ok());
+
+        let result = store.get_token(&email).await;
+        assert_eq!(
+            result.unwrap_err(),
+            PasswordResetTokenStoreError::TokenNotFound
+        );
+        let result = store.remove_token(&email).await;
+        assert_eq!(
+            result.unwrap_err(),
+            PasswordResetTokenStoreError::TokenNotFound
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_token_should_not_be_found() {
+        let mut store = HashmapPasswordResetTokenStore::default();
+        let (email, token) = get_default_value();
+        store
+            .tokens
+            .insert(email.clone(), (token, Utc::now().timestamp() - 1));
+
+        let result = store.get_token(&email).await;
+        assert_eq!(
+            result.unwrap_err(),
+            PasswordResetTokenStoreError::TokenNotFound
+        );
+    }
+}
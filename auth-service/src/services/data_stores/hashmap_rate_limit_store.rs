@@ -0,0 +1,82 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_store::{RateLimitStore, RateLimitStoreError},
+    rate_limit::{RateLimit, RateLimitDecision},
+};
+
+// past this many keys, the ones whose bucket filled up again are dropped
+const MAX_KEYS_BEFORE_PRUNE: usize = 10_000;
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapRateLimitStore {
+    // key -> theoretical arrival time, unix milliseconds
+    tats: HashMap<String, i64>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for HashmapRateLimitStore {
+    async fn check(
+        &mut self,
+        buckets: &[(&str, &RateLimit)],
+        now: i64,
+    ) -> Result<Vec<RateLimitDecision>, RateLimitStoreError> {
+        let decisions: Vec<RateLimitDecision> = buckets
+            .iter()
+            .map(|(key, limit)| limit.check(self.tats.get(*key).copied(), now))
+            .collect();
+        if decisions.iter().all(|decision| decision.allowed) {
+            if self.tats.len() >= MAX_KEYS_BEFORE_PRUNE {
+                self.tats.retain(|_, tat| *tat > now);
+            }
+            for ((key, _), decision) in buckets.iter().zip(&decisions) {
+                self.tats.insert((*key).to_owned(), decision.tat);
+            }
+        }
+        Ok(decisions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: RateLimit = RateLimit {
+        burst: 2,
+        period_seconds: 60,
+    };
+
+    async fn allowed(store: &mut HashmapRateLimitStore, keys: &[&str], now: i64) -> Vec<bool> {
+        let buckets: Vec<(&str, &RateLimit)> = keys.iter().map(|key| (*key, &LIMIT)).collect();
+        let decisions = store.check(&buckets, now).await.unwrap();
+        decisions.iter().map(|decision| decision.allowed).collect()
+    }
+
+    #[tokio::test]
+    async fn keys_should_have_their_own_bucket() {
+        let mut store = HashmapRateLimitStore::default();
+        let now = 1_000_000;
+        for _ in 0..2 {
+            assert_eq!(allowed(&mut store, &["a"], now).await, [true]);
+        }
+        assert_eq!(allowed(&mut store, &["a"], now).await, [false]);
+        assert_eq!(allowed(&mut store, &["b"], now).await, [true]);
+    }
+
+    #[tokio::test]
+    async fn rejected_request_should_not_spend_other_buckets() {
+        let mut store = HashmapRateLimitStore::default();
+        let now = 1_000_000;
+        for _ in 0..2 {
+            assert_eq!(allowed(&mut store, &["a"], now).await, [true]);
+        }
+        assert_eq!(allowed(&mut store, &["b", "a"], now).await, [true, false]);
+        assert_eq!(allowed(&mut store, &["b", "a"], now).await, [true, false]);
+
+        // b still has its whole burst
+        for _ in 0..2 {
+            assert_eq!(allowed(&mut store, &["b"], now).await, [true]);
+        }
+        assert_eq!(allowed(&mut store, &["b"], now).await, [false]);
+    }
+}
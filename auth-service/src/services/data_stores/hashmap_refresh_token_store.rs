@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{
+        data_store::{RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+        email::Email,
+        refresh_token::RefreshToken,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Clone, Debug)]
+struct StoredRefreshToken {
+    record: RefreshTokenRecord,
+    used: bool,
+    expires_at: i64,
+}
+
+// Tokens and revoked families last as long as they would in Redis, expired ones are dropped as
+// new tokens come in.
+#[derive(Default, Clone, Debug)]
+pub struct HashmapRefreshTokenStore {
+    tokens: HashMap<String, StoredRefreshToken>,
+    // family id -> when its last token expires
+    revoked_families: HashMap<String, i64>,
+}
+
+impl HashmapRefreshTokenStore {
+    fn purge_expired(&mut self, now: i64) {
+        self.tokens.retain(|_, token| token.expires_at > now);
+        self.revoked_families
+            .retain(|_, expires_at| *expires_at > now);
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for HashmapRefreshTokenStore {
+    async fn add_token(
+        &mut self,
+        token: &RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let now = Utc::now().timestamp();
+        self.purge_expired(now);
+
+        let key = token.as_ref().expose_secret().to_owned();
+        if self.tokens.contains_key(&key) {
+            return Err(RefreshTokenStoreError::UnexpectedError(eyre!(
+                "Refresh token already exist!"
+            )));
+        }
+        let token = StoredRefreshToken {
+            record,
+            used: false,
+            expires_at: now + REFRESH_TOKEN_TTL_SECONDS,
+        };
+        self.tokens.insert(key, token);
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let now = Utc::now().timestamp();
+        let key = token.as_ref().expose_secret();
+        let token = self
+            .tokens
+            .get_mut(key)
+            .filter(|token| token.expires_at > now)
+            .ok_or(RefreshTokenStoreError::TokenNotFound)?;
+        let family_id = &token.record.family_id;
+
+        if self
+            .revoked_families
+            .get(family_id)
+            .is_some_and(|expires_at| *expires_at > now)
+        {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if token.used {
+            return Err(RefreshTokenStoreError::TokenReused(family_id.clone()));
+        }
+        token.used = true;
+
+        Ok(token.record.clone())
+    }
+
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS;
+        self.revoked_families
+            .insert(family_id.to_owned(), expires_at);
+        Ok(())
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let expires_at = Utc::now().timestamp() + REFRESH_TOKEN_TTL_SECONDS;
+        let families = self
+            .tokens
+            .values()
+            .filter(|token| token.record.email.eq(email))
+            .map(|token| (token.record.family_id.clone(), expires_at));
+        self.revoked_families.extend(families);
+        Ok(())
+    }
+
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), RefreshTokenStoreError> {
+        self.tokens
+            .values_mut()
+            .filter(|token| token.record.email.eq(email))
+            .for_each(|token| token.record.email = new_email.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::auth_method::AuthMethod;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn get_default_value() -> (RefreshToken, RefreshTokenRecord) {
+        let email = Email::parse(Secret::new("test@test.com".to_owned()))
+            .expect("Unable to parse dummy email account");
+        let record = RefreshTokenRecord {
+            email,
+            family_id: Uuid::new_v4().to_string(),
+            amr: vec![AuthMethod::Password],
+        };
+        (RefreshToken::default(), record)
+    }
+
+    #[tokio::test]
+    async fn consume_token_should_succeed() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+
+        let result = store.consume_token(&token).await.unwrap();
+        assert_eq!(result.family_id, record.family_id);
+        assert_eq!(result.email, record.email);
+        assert_eq!(result.amr, record.amr);
+    }
+
+    #[tokio::test]
+    async fn consume_unknown_token_should_fail() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let result = store.consume_token(&RefreshToken::default()).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::TokenNotFound);
+    }
+
+    #[tokio::test]
+    async fn consume_token_twice_should_report_reuse() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+        assert!(store.consume_token(&token).await.is_ok());
+
+        match store.consume_token(&token).await {
+            Err(RefreshTokenStoreError::TokenReused(family_id)) => {
+                assert_eq!(family_id, record.family_id)
+            }
+            _ => panic!("Reusing a refresh token should be reported!"),
+        }
+    }
+
+    #[tokio::test]
+    async fn revoked_family_should_reject_every_token() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+        let sibling = RefreshToken::default();
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+        assert!(store.add_token(&sibling, record.clone()).await.is_ok());
+        assert!(store.revoke_family(&record.family_id).await.is_ok());
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::FamilyRevoked);
+        let result = store.consume_token(&sibling).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::FamilyRevoked);
+    }
+
+    #[tokio::test]
+    async fn revoke_user_should_only_revoke_their_families() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+        let other_token = RefreshToken::default();
+        let other_record = RefreshTokenRecord {
+            email: Email::parse(Secret::new("other@test.com".to_owned())).unwrap(),
+            family_id: Uuid::new_v4().to_string(),
+            amr: vec![AuthMethod::Password],
+        };
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+        assert!(store.add_token(&other_token, other_record).await.is_ok());
+        assert!(store.revoke_user(&record.email).await.is_ok());
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::FamilyRevoked);
+        assert!(store.consume_token(&other_token).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn expired_tokens_should_be_purged_on_insert() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+        assert!(store.revoke_family(&record.family_id).await.is_ok());
+        let past = Utc::now().timestamp() - 1;
+        store.tokens.values_mut().for_each(|t| t.expires_at = past);
+        store.revoked_families.values_mut().for_each(|t| *t = past);
+
+        let result = store.consume_token(&token).await;
+        assert_eq!(result.unwrap_err(), RefreshTokenStoreError::TokenNotFound);
+
+        let (other, record) = get_default_value();
+        assert!(store.add_token(&other, record).await.is_ok());
+        assert_eq!(store.tokens.len(), 1);
+        assert!(store.revoked_families.is_empty());
+    }
+
+    #[tokio::test]
+    async fn move_user_should_keep_sessions_alive() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let (token, record) = get_default_value();
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+
+        assert!(store.add_token(&token, record.clone()).await.is_ok());
+        assert!(store.move_user(&record.email, &new_email).await.is_ok());
+
+        let result = store.consume_token(&token).await.unwrap();
+        assert_eq!(result.email, new_email);
+        assert_eq!(result.family_id, record.family_id);
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+
+use crate::{
+    domain::{
+        data_store::{Session, SessionStore, SessionStoreError},
+        email::Email,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+#[derive(Default, Clone, Debug)]
+pub struct HashmapSessionStore {
+    sessions: HashMap<String, Session>,
+}
+
+impl HashmapSessionStore {
+    // idle sessions are only skipped, the store is meant for tests
+    fn is_active(session: &Session, now: i64) -> bool {
+        session.last_seen_at + REFRESH_TOKEN_TTL_SECONDS > now
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for HashmapSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        if self.sessions.contains_key(&session.id) {
+            return Err(SessionStoreError::UnexpectedError(eyre!(
+                "Session already exist!"
+            )));
+        }
+        self.sessions.insert(session.id.clone(), session);
+        Ok(())
+    }
+
+    async fn touch_session(&mut self, id: &str) -> Result<Session, SessionStoreError> {
+        let now = Utc::now().timestamp();
+        match self.sessions.get_mut(id) {
+            Some(session) if Self::is_active(session, now) => {
+                session.last_seen_at = now;
+                Ok(session.clone())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let now = Utc::now().timestamp();
+        let mut sessions: Vec<Session> = self
+            .sessions
+            .values()
+            .filter(|session| session.email.eq(email) && Self::is_active(session, now))
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+        match self.sessions.get(id) {
+            Some(session) if session.email.eq(email) => {
+                self.sessions.remove(id);
+                Ok(())
+            }
+            _ => Err(SessionStoreError::SessionNotFound),
+        }
+    }
+
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<usize, SessionStoreError> {
+        let before = self.sessions.len();
+        self.sessions.retain(|_, session| session.email.ne(email));
+        Ok(before - self.sessions.len())
+    }
+
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), SessionStoreError> {
+        self.sessions
+            .values_mut()
+            .filter(|session| session.email.eq(email))
+            .for_each(|session| session.email = new_email.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn email(address: &str) -> Email {
+        Email::parse(Secret::new(address.to_owned())).expect("Unable to parse dummy email account")
+    }
+
+    fn session(email: &Email) -> Session {
+        let now = Utc::now().timestamp();
+        Session {
+            id: Uuid::new_v4().to_string(),
+            email: email.clone(),
+            created_at: now,
+            last_seen_at: now,
+            ip: Some("127.0.0.1".to_owned()),
+            user_agent: Some("test".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn touch_session_should_update_last_seen() {
+        let mut store = HashmapSessionStore::default();
+        let mut session = session(&email("test@test.com"));
+        session.last_seen_at -= 60;
+        assert!(store.add_session(session.clone()).await.is_ok());
+
+        let touched = store.touch_session(&session.id).await.unwrap();
+        assert_eq!(touched.id, session.id);
+        assert!(touched.last_seen_at > session.last_seen_at);
+    }
+
+    #[tokio::test]
+    async fn touch_unknown_session_should_fail() {
+        let mut store = HashmapSessionStore::default();
+        let result = store.touch_session("unknown").await;
+        assert_eq!(result.unwrap_err(), SessionStoreError::SessionNotFound);
+    }
+
+    #[tokio::test]
+    async fn idle_session_should_be_gone() {
+        let mut store = HashmapSessionStore::default();
+        let email = email("test@test.com");
+        let mut session = session(&email);
+        session.last_seen_at -= REFRESH_TOKEN_TTL_SECONDS;
+        assert!(store.add_session(session.clone()).await.is_ok());
+
+        assert!(store.get_sessions(&email).await.unwrap().is_empty());
+        let result = store.touch_session(&session.id).await;
+        assert_eq!(result.unwrap_err(), SessionStoreError::SessionNotFound);
+    }
+
+    #[tokio::test]
+    async fn get_sessions_should_only_return_own_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = email("test@test.com");
+        let first = session(&email);
+        let second = session(&email);
+        assert!(store.add_session(first.clone()).await.is_ok());
+        assert!(store.add_session(second.clone()).await.is_ok());
+        assert!(store
+            .add_session(session(&self::email("other@test.com")))
+            .await
+            .is_ok());
+
+        let sessions = store.get_sessions(&email).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert!(sessions.contains(&first));
+        assert!(sessions.contains(&second));
+    }
+
+    #[tokio::test]
+    async fn remove_session_of_other_user_should_fail() {
+        let mut store = HashmapSessionStore::default();
+        let session = session(&email("test@test.com"));
+        assert!(store.add_session(session.clone()).await.is_ok());
+
+        let result = store
+            .remove_session(&email("other@test.com"), &session.id)
+            .await;
+        assert_eq!(result.unwrap_err(), SessionStoreError::SessionNotFound);
+        assert!(store.touch_session(&session.id).await.is_ok());
+
+        assert!(store
+            .remove_session(&session.email, &session.id)
+            .await
+            .is_ok());
+        let result = store.touch_session(&session.id).await;
+        assert_eq!(result.unwrap_err(), SessionStoreError::SessionNotFound);
+    }
+
+    #[tokio::test]
+    async fn remove_user_sessions_should_only_remove_own_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let email = email("test@test.com");
+        let first = session(&email);
+        let other = session(&self::email("other@test.com"));
+        assert!(store.add_session(first.clone()).await.is_ok());
+        assert!(store.add_session(other.clone()).await.is_ok());
+
+        assert_eq!(store.remove_user_sessions(&email).await.unwrap(), 1);
+        assert!(store.get_sessions(&email).await.unwrap().is_empty());
+        assert!(store.touch_session(&other.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn move_user_should_keep_sessions() {
+        let mut store = HashmapSessionStore::default();
+        let old_email = email("old@test.com");
+        let new_email = email("new@test.com");
+        let session = session(&old_email);
+        assert!(store.add_session(session.clone()).await.is_ok());
+
+        assert!(store.move_user(&old_email, &new_email).await.is_ok());
+        assert!(store.get_sessions(&old_email).await.unwrap().is_empty());
+        let sessions = store.get_sessions(&new_email).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, session.id);
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use crate::domain::data_store::{
+    keyring_states, KeyringEntry, SigningKeyStore, SigningKeyStoreError,
+};
+
+#[derive(Default, Debug, Clone)]
+pub struct HashmapSigningKeyStore {
+    // keyed by kid
+    keys: HashMap<String, KeyringEntry>,
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for HashmapSigningKeyStore {
+    async fn get_keys(&self) -> Result<Vec<KeyringEntry>, SigningKeyStoreError> {
+        let mut keys: Vec<KeyringEntry> = self.keys.values().cloned().collect();
+        keys.sort_by_key(|entry| entry.created_at);
+        Ok(keys)
+    }
+
+    async fn save_keys(
+        &mut self,
+        current: &[KeyringEntry],
+        keys: &[KeyringEntry],
+    ) -> Result<(), SigningKeyStoreError> {
+        let stored: Vec<KeyringEntry> = self.keys.values().cloned().collect();
+        if keyring_states(&stored) != keyring_states(current) {
+            return Err(SigningKeyStoreError::Conflict);
+        }
+        self.keys = keys
+            .iter()
+            .map(|entry| (entry.key.kid().to_owned(), entry.clone()))
+            .collect();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::signing_key::{KeyState, SigningAlgorithm, SigningKey};
+
+    fn entry(state: KeyState, created_at: i64) -> KeyringEntry {
+        KeyringEntry {
+            key: SigningKey::generate(SigningAlgorithm::HS256).unwrap(),
+            state,
+            created_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_save_and_get_keys() {
+        let mut store = HashmapSigningKeyStore::default();
+        assert!(store.get_keys().await.unwrap().is_empty());
+
+        let keys = [entry(KeyState::Next, 2), entry(KeyState::Active, 1)];
+        store.save_keys(&[], &keys).await.unwrap();
+
+        let stored = store.get_keys().await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].key.kid(), keys[1].key.kid());
+        assert_eq!(stored[0].state, KeyState::Active);
+    }
+
+    #[tokio::test]
+    async fn save_keys_should_replace_keyring() {
+        let mut store = HashmapSigningKeyStore::default();
+        let first = [entry(KeyState::Active, 1)];
+        store.save_keys(&[], &first).await.unwrap();
+
+        let keys = [entry(KeyState::Active, 2)];
+        store.save_keys(&first, &keys).await.unwrap();
+
+        let stored = store.get_keys().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].key.kid(), keys[0].key.kid());
+    }
+
+    #[tokio::test]
+    async fn save_keys_over_a_changed_keyring_should_fail() {
+        let mut store = HashmapSigningKeyStore::default();
+        let first = [entry(KeyState::Active, 1)];
+        store.save_keys(&[], &first).await.unwrap();
+
+        // made to the keyring as it was before the first save
+        let keys = [entry(KeyState::Active, 2)];
+        assert_eq!(
+            store.save_keys(&[], &keys).await,
+            Err(SigningKeyStoreError::Conflict)
+        );
+
+        let mut retired = first.clone();
+        retired[0].state = KeyState::Retired;
+        assert_eq!(
+            store.save_keys(&retired, &keys).await,
+            Err(SigningKeyStoreError::Conflict)
+        );
+        assert_eq!(
+            store.get_keys().await.unwrap()[0].key.kid(),
+            first[0].key.kid()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use chrono::Utc;
 use color_eyre::eyre::{eyre, Result};
 use std::collections::HashMap;
 
@@ -5,70 +6,144 @@ use crate::domain::{
     data_store::{TwoFACodeStore, TwoFACodeStoreError, TwoFARecord},
     email::Email,
     login_attempt_id::LoginAttemptId,
-    two_fa_code::TwoFACode,
+    two_fa_code::{StoredTwoFACode, TwoFACode},
 };
+use crate::utils::constants::{TWO_FACTOR_POLICY, TWO_FA_MAX_PENDING_ATTEMPTS};
 
 #[derive(Default, Clone, Debug)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>, // not a good idea to use tuples instead of concrete type for data store?
+    codes: HashMap<LoginAttemptId, TwoFARecord>,
+    // email -> pending login attempts, oldest first
+    attempts: HashMap<Email, Vec<LoginAttemptId>>,
 }
 
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
-    async fn add_code(
-        &mut self,
-        email: Email,
-        id: LoginAttemptId,
-        code: TwoFACode,
-    ) -> Result<(), TwoFACodeStoreError> {
-        if let Some(_) = self.codes.insert(email, (id, code)) {
-            // if we received some, it means the key already exist instead, it updates the hashmap table, returning the old value back...
-            // TODO: Discuss whether we need to handle this specific type of update or not?
+    async fn add_code(&mut self, record: TwoFARecord) -> Result<(), TwoFACodeStoreError> {
+        if self.codes.contains_key(&record.id) {
             return Err(TwoFACodeStoreError::UnexpectedError(eyre!(
                 "Key already exist!"
             )));
         }
+
+        // nothing else expires codes here, the user's stale ones are dropped as new ones come in
+        let now = Utc::now().timestamp();
+        let attempts = self.attempts.entry(record.email.clone()).or_default();
+        attempts.retain(|id| match self.codes.get(id) {
+            Some(record) if !record.is_expired(now) => true,
+            _ => {
+                self.codes.remove(id);
+                false
+            }
+        });
+        attempts.push(record.id.clone());
+        if attempts.len() > TWO_FA_MAX_PENDING_ATTEMPTS {
+            let dropped = attempts.len() - TWO_FA_MAX_PENDING_ATTEMPTS;
+            for old_id in attempts.drain(..dropped) {
+                self.codes.remove(&old_id);
+            }
+        }
+
+        self.codes.insert(record.id.clone(), record);
         Ok(())
     }
 
-    async fn get_code(&self, email: &Email) -> Result<TwoFARecord, TwoFACodeStoreError> {
-        match self.codes.get(&email) {
-            Some(code) => {
-                let id = code.0.clone();
-                let code = code.1.clone();
-                let record = TwoFARecord { id, code };
-                Ok(record)
+    async fn get_code(&self, id: &LoginAttemptId) -> Result<TwoFARecord, TwoFACodeStoreError> {
+        match self.codes.get(id) {
+            Some(record) => Ok(record.clone()),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn resend_code(
+        &mut self,
+        id: &LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<TwoFARecord, TwoFACodeStoreError> {
+        match self.codes.get_mut(id) {
+            Some(record) => {
+                let now = Utc::now().timestamp();
+                record.code = StoredTwoFACode::hash(&code);
+                record.resends += 1;
+                record.last_sent_at = now;
+                record.expires_at = now + TWO_FACTOR_POLICY.ttl_seconds;
+                Ok(record.clone())
             }
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(&email) {
-            Some(_) => Ok(()),
+    async fn count_attempt(&mut self, id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError> {
+        match self.codes.get_mut(id) {
+            Some(record) => {
+                record.failed_attempts += 1;
+                Ok(record.failed_attempts)
+            }
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn remove_code(&mut self, id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        let record = self
+            .codes
+            .remove(id)
+            .ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        if let Some(attempts) = self.attempts.get_mut(&record.email) {
+            attempts.retain(|attempt| attempt != id);
+            if attempts.is_empty() {
+                self.attempts.remove(&record.email);
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        for id in self.attempts.remove(email).unwrap_or_default() {
+            self.codes.remove(&id);
+        }
+        Ok(())
+    }
+
+    async fn move_codes(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let Some(ids) = self.attempts.remove(email) else {
+            return Ok(());
+        };
+        for id in &ids {
+            if let Some(record) = self.codes.get_mut(id) {
+                record.email = new_email.clone();
+            }
+        }
+        self.attempts.entry(new_email).or_default().extend(ids);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::domain::{
-        data_store::TwoFACodeStore, email::Email, login_attempt_id::LoginAttemptId,
+        auth_method::AuthMethod,
+        data_store::{TwoFACodeStore, TwoFACodeStoreError, TwoFARecord},
+        email::Email,
+        login_attempt_id::LoginAttemptId,
         two_fa_code::TwoFACode,
     };
 
     use super::HashmapTwoFACodeStore;
+    use crate::utils::constants::{TWO_FA_MAX_PENDING_ATTEMPTS, TWO_FA_RESEND_COOLDOWN_SECONDS};
     use secrecy::Secret;
 
-    fn get_default_value() -> (Email, LoginAttemptId, TwoFACode) {
+    fn get_default_value() -> (Email, LoginAttemptId, TwoFACode, Vec<AuthMethod>) {
         // TODO: replace this with faker email address
         let random_email = "test@test.com".to_owned();
         let secret = Secret::new(random_email);
         let email = Email::parse(secret).expect("Unable to parse dummy email account");
         let id = LoginAttemptId::default();
         let code = TwoFACode::default();
-        (email, id, code)
+        (email, id, code, vec![AuthMethod::Password])
     }
 
     #[tokio::test]
@@ -76,7 +151,9 @@ mod tests {
         let mut db = HashmapTwoFACodeStore::default();
 
         let data = get_default_value();
-        let result = db.add_code(data.0, data.1, data.2).await;
+        let result = db
+            .add_code(TwoFARecord::new(data.0, data.1, &data.2, data.3))
+            .await;
         assert!(result.is_ok());
     }
 
@@ -85,11 +162,19 @@ mod tests {
         let mut db = HashmapTwoFACodeStore::default();
         let data = get_default_value();
 
-        let result = db.add_code(data.0.clone(), data.1, data.2).await;
+        let result = db
+            .add_code(TwoFARecord::new(
+                data.0.clone(),
+                data.1.clone(),
+                &data.2,
+                data.3,
+            ))
+            .await;
         assert!(result.is_ok());
 
-        let data = db.get_code(&data.0).await;
-        assert!(data.is_ok());
+        let record = db.get_code(&data.1).await.unwrap();
+        assert_eq!(record.email, data.0);
+        assert_eq!(record.amr, vec![AuthMethod::Password]);
     }
 
     #[tokio::test]
@@ -97,11 +182,175 @@ mod tests {
         let mut db = HashmapTwoFACodeStore::default();
         let data = get_default_value();
 
-        let result = db.add_code(data.0.clone(), data.1, data.2).await;
+        let result = db
+            .add_code(TwoFARecord::new(
+                data.0.clone(),
+                data.1.clone(),
+                &data.2,
+                data.3,
+            ))
+            .await;
         assert!(result.is_ok());
 
-        let result = db.remove_code(&data.0).await;
+        let result = db.remove_code(&data.1).await;
         assert!(result.is_ok());
+        assert_eq!(
+            db.remove_code(&data.1).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn move_codes_should_succeed() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+
+        let result = db
+            .add_code(TwoFARecord::new(
+                data.0.clone(),
+                data.1.clone(),
+                &data.2,
+                data.3,
+            ))
+            .await;
+        assert!(result.is_ok());
+
+        let result = db.move_codes(&data.0, new_email.clone()).await;
+        assert!(result.is_ok());
+
+        let record = db.get_code(&data.1).await.unwrap();
+        assert_eq!(record.email, new_email);
+        db.remove_codes(&new_email).await.unwrap();
+        assert!(db.get_code(&data.1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn logins_should_be_kept_apart() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+        let other_id = LoginAttemptId::default();
+        let other_code = TwoFACode::default();
+
+        db.add_code(TwoFARecord::new(
+            data.0.clone(),
+            data.1.clone(),
+            &data.2,
+            data.3.clone(),
+        ))
+        .await
+        .unwrap();
+        db.add_code(TwoFARecord::new(
+            data.0.clone(),
+            other_id.clone(),
+            &other_code,
+            data.3,
+        ))
+        .await
+        .unwrap();
+
+        assert!(db.get_code(&data.1).await.unwrap().code.matches(&data.2));
+        assert!(db
+            .get_code(&other_id)
+            .await
+            .unwrap()
+            .code
+            .matches(&other_code));
+
+        db.remove_code(&data.1).await.unwrap();
+        assert!(db.get_code(&other_id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn oldest_logins_should_be_dropped_past_the_cap() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+
+        let ids: Vec<LoginAttemptId> = (0..=TWO_FA_MAX_PENDING_ATTEMPTS)
+            .map(|_| LoginAttemptId::default())
+            .collect();
+        for id in &ids {
+            db.add_code(TwoFARecord::new(
+                data.0.clone(),
+                id.clone(),
+                &TwoFACode::default(),
+                data.3.clone(),
+            ))
+            .await
+            .unwrap();
+        }
+
+        assert!(db.get_code(&ids[0]).await.is_err());
+        for id in &ids[1..] {
+            assert!(db.get_code(id).await.is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn attempts_should_be_counted() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+
+        let result = db
+            .add_code(TwoFARecord::new(data.0, data.1.clone(), &data.2, data.3))
+            .await;
+        assert!(result.is_ok());
+
+        assert_eq!(db.count_attempt(&data.1).await, Ok(1));
+        assert_eq!(db.count_attempt(&data.1).await, Ok(2));
+        assert_eq!(db.get_code(&data.1).await.unwrap().failed_attempts, 2);
+
+        db.remove_code(&data.1).await.unwrap();
+        assert_eq!(
+            db.count_attempt(&data.1).await,
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn resend_code_should_swap_the_code() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+
+        let result = db
+            .add_code(TwoFARecord::new(
+                data.0.clone(),
+                data.1.clone(),
+                &data.2,
+                data.3,
+            ))
+            .await;
+        assert!(result.is_ok());
+        let sent = db.get_code(&data.1).await.unwrap();
+        assert!(sent.resend_cooldown(sent.last_sent_at).is_some());
+
+        let new_code = TwoFACode::default();
+        let record = db.resend_code(&data.1, new_code.clone()).await.unwrap();
+        assert_eq!(record.id, data.1);
+        assert!(record.code.matches(&new_code));
+        assert_eq!(record.resends, 1);
+        assert_eq!(
+            record.resend_cooldown(record.last_sent_at + TWO_FA_RESEND_COOLDOWN_SECONDS),
+            None
+        );
+        assert!(record.expires_at >= sent.expires_at);
+    }
+
+    #[tokio::test]
+    async fn expired_codes_should_be_dropped_on_add() {
+        let mut db = HashmapTwoFACodeStore::default();
+        let data = get_default_value();
+
+        let mut expired = TwoFARecord::new(data.0.clone(), data.1.clone(), &data.2, data.3);
+        expired.expires_at = expired.created_at - 1;
+        db.add_code(expired).await.unwrap();
+        assert!(db.get_code(&data.1).await.is_ok());
+
+        let id = LoginAttemptId::default();
+        let record = TwoFARecord::new(data.0, id.clone(), &TwoFACode::default(), vec![]);
+        db.add_code(record).await.unwrap();
+        assert!(db.get_code(&data.1).await.is_err());
+        assert!(db.get_code(&id).await.is_ok());
     }
 
     // TODO: impl expected failure case
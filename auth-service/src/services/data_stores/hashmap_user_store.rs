@@ -1,11 +1,20 @@
 use std::collections::HashMap;
 
-use crate::domain::data_store::{UserStore, UserStoreError};
-use crate::domain::{email::Email, password::Password, user::User};
+use crate::domain::data_store::{
+    TotpRecord, UserList, UserListQuery, UserStore, UserStoreError, UserUpdate,
+};
+use crate::domain::{
+    email::Email, password::Password, recovery_code::RecoveryCode,
+    totp_secret::EncryptedTotpSecret, user::User,
+};
+use secrecy::ExposeSecret;
 
 #[derive(Default, Clone, Debug)]
 pub struct HashmapUserStore {
     users: HashMap<Email, User>,
+    totp: HashMap<Email, TotpRecord>,
+    recovery_codes: HashMap<Email, Vec<RecoveryCode>>,
+    token_versions: HashMap<Email, i32>,
 }
 
 #[async_trait::async_trait]
@@ -40,7 +49,255 @@ impl UserStore for HashmapUserStore {
     }
 
     async fn delete_user(&mut self, email: Email) -> Result<(), UserStoreError> {
-        self.users.retain(|k, _| k.eq(&email));
+        match self.users.remove(&email) {
+            Some(_) => {
+                self.totp.remove(&email);
+                self.recovery_codes.remove(&email);
+                self.token_versions.remove(&email);
+                Ok(())
+            }
+            None => Err(UserStoreError::UserNotFound),
+        }
+    }
+
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        *user = User::new(
+            email.clone(),
+            password,
+            user.requires_2fa(),
+            user.email_verified(),
+            user.role(),
+            user.disabled(),
+        );
+        Ok(())
+    }
+
+    async fn verify_email(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        let password: &Password = user.as_ref();
+        *user = User::new(
+            email.clone(),
+            password.clone(),
+            user.requires_2fa(),
+            true,
+            user.role(),
+            user.disabled(),
+        );
+        Ok(())
+    }
+
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), UserStoreError> {
+        if self.users.contains_key(&new_email) {
+            return Err(UserStoreError::UserAlreadyExists);
+        }
+        let user = self
+            .users
+            .remove(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        let password: &Password = user.as_ref();
+        let user = User::new(
+            new_email.clone(),
+            password.clone(),
+            user.requires_2fa(),
+            true,
+            user.role(),
+            user.disabled(),
+        );
+        self.users.insert(new_email.clone(), user);
+        if let Some(record) = self.totp.remove(email) {
+            self.totp.insert(new_email.clone(), record);
+        }
+        if let Some(codes) = self.recovery_codes.remove(email) {
+            self.recovery_codes.insert(new_email.clone(), codes);
+        }
+        if let Some(version) = self.token_versions.remove(email) {
+            self.token_versions.insert(new_email, version);
+        }
+        Ok(())
+    }
+
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let record = TotpRecord {
+            secret,
+            confirmed: false,
+            last_step: None,
+        };
+        self.totp.insert(email.clone(), record);
+        Ok(())
+    }
+
+    async fn get_totp(&self, email: &Email) -> Result<Option<TotpRecord>, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.totp.get(email).cloned())
+    }
+
+    async fn confirm_totp(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let record = self
+            .totp
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        record.confirmed = true;
+        Ok(())
+    }
+
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        let record = self
+            .totp
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        if record.last_step.is_some_and(|last| last >= step) {
+            return Err(UserStoreError::TotpCodeReused);
+        }
+        record.last_step = Some(step);
+        Ok(())
+    }
+
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        self.recovery_codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn check_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError> {
+        match self.recovery_codes.get(email) {
+            Some(codes) if codes.contains(code) => Ok(()),
+            _ => Err(UserStoreError::InvalidCredentials),
+        }
+    }
+
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, UserStoreError> {
+        let codes = self
+            .recovery_codes
+            .get_mut(email)
+            .ok_or(UserStoreError::InvalidCredentials)?;
+        let index = codes
+            .iter()
+            .position(|c| c.eq(code))
+            .ok_or(UserStoreError::InvalidCredentials)?;
+        codes.remove(index);
+        Ok(codes.len())
+    }
+
+    async fn get_token_version(&self, email: &Email) -> Result<i32, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        Ok(self.token_versions.get(email).copied().unwrap_or_default())
+    }
+
+    async fn increment_token_version(&mut self, email: &Email) -> Result<i32, UserStoreError> {
+        if !self.users.contains_key(email) {
+            return Err(UserStoreError::UserNotFound);
+        }
+        let version = self.token_versions.entry(email.clone()).or_default();
+        *version += 1;
+        Ok(*version)
+    }
+
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserList, UserStoreError> {
+        let search = query.search.as_deref().map(str::to_lowercase);
+        let mut users: Vec<&User> = self
+            .users
+            .iter()
+            .filter(|(email, _)| match &search {
+                Some(search) => email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(search),
+                None => true,
+            })
+            .map(|(_, user)| user)
+            .collect();
+        users.sort_by(|a, b| {
+            let a: &Email = a.as_ref();
+            let b: &Email = b.as_ref();
+            a.as_ref().expose_secret().cmp(b.as_ref().expose_secret())
+        });
+
+        let total = users.len();
+        let users = users
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit)
+            .cloned()
+            .collect();
+        Ok(UserList { users, total })
+    }
+
+    async fn update_user(
+        &mut self,
+        email: &Email,
+        update: UserUpdate,
+    ) -> Result<User, UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        let password: &Password = user.as_ref();
+        *user = User::new(
+            email.clone(),
+            password.clone(),
+            update.requires_2fa.unwrap_or(user.requires_2fa()),
+            user.email_verified(),
+            update.role.unwrap_or(user.role()),
+            user.disabled(),
+        );
+        Ok(user.clone())
+    }
+
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(UserStoreError::UserNotFound)?;
+        let password: &Password = user.as_ref();
+        *user = User::new(
+            email.clone(),
+            password.clone(),
+            user.requires_2fa(),
+            user.email_verified(),
+            user.role(),
+            disabled,
+        );
         Ok(())
     }
 }
@@ -48,7 +305,7 @@ impl UserStore for HashmapUserStore {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::user::User;
+    use crate::domain::user::{User, UserRole};
     use secrecy::Secret;
 
     #[tokio::test]
@@ -100,4 +357,312 @@ mod tests {
         let result = db.validate_user(user.as_ref(), user.as_ref()).await;
         assert_eq!(result.is_ok(), true);
     }
+
+    #[tokio::test]
+    async fn test_update_password() {
+        let email = Secret::new("test@test.com".to_owned());
+        let password = Secret::new("password123!".to_owned());
+        let user = User::parse(email, password, true).unwrap();
+        let mut db = HashmapUserStore::default();
+        assert!(db.add_user(user.clone()).await.is_ok());
+
+        let new_password = Password::parse(Secret::new("newPassword123!".to_owned())).unwrap();
+        let result = db
+            .update_password(user.as_ref(), new_password.clone())
+            .await;
+        assert!(result.is_ok());
+
+        let result = db.validate_user(user.as_ref(), user.as_ref()).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::InvalidCredentials);
+        let result = db.validate_user(user.as_ref(), &new_password).await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_update_password_unknown_user() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password123!".to_owned())).unwrap();
+        let mut db = HashmapUserStore::default();
+        let result = db.update_password(&email, password).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_verify_email() {
+        let email = Secret::new("test@test.com".to_owned());
+        let password = Secret::new("password123!".to_owned());
+        let user = User::parse(email, password, true).unwrap();
+        let mut db = HashmapUserStore::default();
+        assert!(db.add_user(user.clone()).await.is_ok());
+
+        assert!(db.verify_email(user.as_ref()).await.is_ok());
+
+        let result = db.get_user(user.as_ref()).await.unwrap();
+        assert!(result.email_verified());
+        assert!(result.requires_2fa());
+    }
+
+    #[tokio::test]
+    async fn test_verify_email_unknown_user() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let mut db = HashmapUserStore::default();
+        let result = db.verify_email(&email).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_update_email() {
+        let email = Secret::new("test@test.com".to_owned());
+        let password = Secret::new("password123!".to_owned());
+        let user = User::parse(email, password, true).unwrap();
+        let mut db = HashmapUserStore::default();
+        assert!(db.add_user(user.clone()).await.is_ok());
+
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+        assert!(db
+            .update_email(user.as_ref(), new_email.clone())
+            .await
+            .is_ok());
+
+        let result = db.get_user(user.as_ref()).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+        let result = db.validate_user(&new_email, user.as_ref()).await.unwrap();
+        assert!(result.email_verified());
+        assert!(result.requires_2fa());
+    }
+
+    #[tokio::test]
+    async fn test_update_email_to_existing_user() {
+        let user = User::parse(
+            Secret::new("test@test.com".to_owned()),
+            Secret::new("password123!".to_owned()),
+            true,
+        )
+        .unwrap();
+        let other = User::parse(
+            Secret::new("other@test.com".to_owned()),
+            Secret::new("password123!".to_owned()),
+            true,
+        )
+        .unwrap();
+        let mut db = HashmapUserStore::default();
+        assert!(db.add_user(user.clone()).await.is_ok());
+        assert!(db.add_user(other.clone()).await.is_ok());
+
+        let other_email: &Email = other.as_ref();
+        let result = db.update_email(user.as_ref(), other_email.clone()).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserAlreadyExists);
+        assert!(db.get_user(user.as_ref()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let first = User::parse(
+            Secret::new("test@test.com".to_owned()),
+            Secret::new("password123!".to_owned()),
+            true,
+        )
+        .unwrap();
+        let second = User::parse(
+            Secret::new("other@test.com".to_owned()),
+            Secret::new("password123!".to_owned()),
+            true,
+        )
+        .unwrap();
+        let mut db = HashmapUserStore::default();
+        assert!(db.add_user(first.clone()).await.is_ok());
+        assert!(db.add_user(second.clone()).await.is_ok());
+
+        let email: &Email = first.as_ref();
+        assert!(db.delete_user(email.clone()).await.is_ok());
+
+        // only the deleted user should be gone
+        let result = db.get_user(first.as_ref()).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+        assert!(db.get_user(second.as_ref()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_unknown_user() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let mut db = HashmapUserStore::default();
+        let result = db.delete_user(email).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    async fn store_with_totp() -> (HashmapUserStore, Email) {
+        let user = User::parse(
+            Secret::new("test@test.com".to_owned()),
+            Secret::new("password123!".to_owned()),
+            true,
+        )
+        .unwrap();
+        let email: &Email = user.as_ref();
+        let email = email.clone();
+        let mut db = HashmapUserStore::default();
+        db.add_user(user).await.unwrap();
+
+        let secret = EncryptedTotpSecret::parse("a".repeat(32)).unwrap();
+        db.set_totp_secret(&email, secret).await.unwrap();
+        (db, email)
+    }
+
+    #[tokio::test]
+    async fn test_token_version() {
+        let (mut db, email) = store_with_totp().await;
+        assert_eq!(db.get_token_version(&email).await.unwrap(), 0);
+        assert_eq!(db.increment_token_version(&email).await.unwrap(), 1);
+        assert_eq!(db.get_token_version(&email).await.unwrap(), 1);
+
+        let unknown = Email::parse(Secret::new("unknown@test.com".to_owned())).unwrap();
+        let result = db.increment_token_version(&unknown).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        let (mut db, email) = store_with_totp().await;
+        assert_eq!(db.get_user(&email).await.unwrap().role(), UserRole::Member);
+
+        let update = UserUpdate {
+            role: Some(UserRole::Admin),
+            ..Default::default()
+        };
+        let user = db.update_user(&email, update).await.unwrap();
+        assert_eq!(user.role(), UserRole::Admin);
+        assert!(user.requires_2fa());
+
+        let update = UserUpdate {
+            requires_2fa: Some(false),
+            ..Default::default()
+        };
+        let user = db.update_user(&email, update).await.unwrap();
+        assert_eq!(user.role(), UserRole::Admin);
+        assert!(!user.requires_2fa());
+    }
+
+    #[tokio::test]
+    async fn test_set_disabled() {
+        let (mut db, email) = store_with_totp().await;
+        assert!(!db.get_user(&email).await.unwrap().disabled());
+
+        assert!(db.set_disabled(&email, true).await.is_ok());
+        assert!(db.get_user(&email).await.unwrap().disabled());
+        assert!(db.set_disabled(&email, false).await.is_ok());
+        assert!(!db.get_user(&email).await.unwrap().disabled());
+
+        let unknown = Email::parse(Secret::new("unknown@test.com".to_owned())).unwrap();
+        let result = db.set_disabled(&unknown, true).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let mut db = HashmapUserStore::default();
+        for address in ["carol@test.com", "alice@test.com", "bob@other.com"] {
+            let user = User::parse(
+                Secret::new(address.to_owned()),
+                Secret::new("password123!".to_owned()),
+                false,
+            )
+            .unwrap();
+            db.add_user(user).await.unwrap();
+        }
+        let emails = |list: &UserList| -> Vec<String> {
+            list.users
+                .iter()
+                .map(|user| {
+                    let email: &Email = user.as_ref();
+                    email.as_ref().expose_secret().to_owned()
+                })
+                .collect()
+        };
+
+        let query = UserListQuery {
+            search: None,
+            offset: 1,
+            limit: 1,
+        };
+        let list = db.list_users(&query).await.unwrap();
+        assert_eq!(list.total, 3);
+        assert_eq!(emails(&list), vec!["bob@other.com"]);
+
+        let query = UserListQuery {
+            search: Some("TEST.com".to_owned()),
+            offset: 0,
+            limit: 10,
+        };
+        let list = db.list_users(&query).await.unwrap();
+        assert_eq!(list.total, 2);
+        assert_eq!(emails(&list), vec!["alice@test.com", "carol@test.com"]);
+    }
+
+    #[tokio::test]
+    async fn test_totp_enrollment() {
+        let (mut db, email) = store_with_totp().await;
+        let record = db.get_totp(&email).await.unwrap().unwrap();
+        assert!(!record.confirmed);
+
+        assert!(db.confirm_totp(&email).await.is_ok());
+        let record = db.get_totp(&email).await.unwrap().unwrap();
+        assert!(record.confirmed);
+    }
+
+    #[tokio::test]
+    async fn test_totp_step_reuse() {
+        let (mut db, email) = store_with_totp().await;
+        assert!(db.use_totp_step(&email, 10).await.is_ok());
+
+        let result = db.use_totp_step(&email, 10).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::TotpCodeReused);
+        let result = db.use_totp_step(&email, 9).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::TotpCodeReused);
+        assert!(db.use_totp_step(&email, 11).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_totp_follows_user() {
+        let (mut db, email) = store_with_totp().await;
+        let new_email = Email::parse(Secret::new("new@test.com".to_owned())).unwrap();
+        db.update_email(&email, new_email.clone()).await.unwrap();
+        assert!(db.get_totp(&new_email).await.unwrap().is_some());
+
+        db.delete_user(new_email.clone()).await.unwrap();
+        let result = db.get_totp(&new_email).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::UserNotFound);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_are_single_use() {
+        let (mut db, email) = store_with_totp().await;
+        let codes: Vec<RecoveryCode> = (0..3).map(|_| RecoveryCode::default()).collect();
+        db.set_recovery_codes(&email, codes.clone()).await.unwrap();
+
+        assert_eq!(db.check_recovery_code(&email, &codes[1]).await, Ok(()));
+        assert_eq!(db.use_recovery_code(&email, &codes[1]).await.unwrap(), 2);
+        assert_eq!(
+            db.check_recovery_code(&email, &codes[1]).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        let result = db.use_recovery_code(&email, &codes[1]).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::InvalidCredentials);
+        assert_eq!(db.use_recovery_code(&email, &codes[0]).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_new_recovery_codes_replace_old_ones() {
+        let (mut db, email) = store_with_totp().await;
+        let old = RecoveryCode::default();
+        db.set_recovery_codes(&email, vec![old.clone()])
+            .await
+            .unwrap();
+        let new = RecoveryCode::default();
+        db.set_recovery_codes(&email, vec![new.clone()])
+            .await
+            .unwrap();
+
+        let result = db.use_recovery_code(&email, &old).await;
+        assert_eq!(result.unwrap_err(), UserStoreError::InvalidCredentials);
+        assert_eq!(db.use_recovery_code(&email, &new).await.unwrap(), 0);
+    }
 }
@@ -0,0 +1,82 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::domain::data_store::{MagicLinkStore, MagicLinkStoreError};
+
+// Used links along with when they expire, links that are dead anyway are dropped as new ones come in.
+#[derive(Default, Debug, Clone)]
+pub struct HashsetMagicLinkStore {
+    pub consumed: HashMap<String, i64>,
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for HashsetMagicLinkStore {
+    async fn consume_link(
+        &mut self,
+        link_id: &str,
+        expires_at: i64,
+    ) -> Result<(), MagicLinkStoreError> {
+        // the link is still taken during the second of its exp
+        let now = Utc::now().timestamp();
+        self.consumed.retain(|_, expires_at| *expires_at >= now);
+
+        if self.consumed.contains_key(link_id) {
+            return Err(MagicLinkStoreError::LinkAlreadyUsed);
+        }
+        self.consumed.insert(link_id.to_owned(), expires_at);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expires_at() -> i64 {
+        Utc::now().timestamp() + 600
+    }
+
+    #[tokio::test]
+    async fn test_consume_link() {
+        let mut store = HashsetMagicLinkStore::default();
+        assert!(store.consume_link("link", expires_at()).await.is_ok());
+        assert!(store.consumed.contains_key("link"));
+    }
+
+    #[tokio::test]
+    async fn consuming_link_twice_should_fail() {
+        let mut store = HashsetMagicLinkStore::default();
+        assert!(store.consume_link("link", expires_at()).await.is_ok());
+        assert_eq!(
+            store.consume_link("link", expires_at()).await,
+            Err(MagicLinkStoreError::LinkAlreadyUsed)
+        );
+    }
+
+    #[tokio::test]
+    async fn replaying_link_about_to_expire_should_fail() {
+        let mut store = HashsetMagicLinkStore::default();
+        let expires_at = Utc::now().timestamp() + 1;
+        assert!(store.consume_link("link", expires_at).await.is_ok());
+        assert_eq!(
+            store.consume_link("link", expires_at).await,
+            Err(MagicLinkStoreError::LinkAlreadyUsed)
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_links_should_be_pruned() {
+        let mut store = HashsetMagicLinkStore::default();
+        let past = Utc::now().timestamp() - 10;
+        store.consumed.insert("old".to_owned(), past);
+        assert!(store.consume_link("link", expires_at()).await.is_ok());
+        assert!(!store.consumed.contains_key("old"));
+    }
+
+    #[tokio::test]
+    async fn other_links_should_stay_usable() {
+        let mut store = HashsetMagicLinkStore::default();
+        assert!(store.consume_link("link", expires_at()).await.is_ok());
+        assert!(store.consume_link("link1", expires_at()).await.is_ok());
+    }
+}
@@ -1,6 +1,26 @@
+pub mod file_signing_key_store;
+pub mod hashmap_account_lockout_store;
+pub mod hashmap_banned_token_store;
+pub mod hashmap_email_change_store;
+pub mod hashmap_email_verification_token_store;
+pub mod hashmap_password_reset_token_store;
+pub mod hashmap_rate_limit_store;
+pub mod hashmap_refresh_token_store;
+pub mod hashmap_session_store;
+pub mod hashmap_signing_key_store;
 pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
-pub mod hashset_banned_token_store;
+pub mod hashset_magic_link_store;
+pub mod postgres_session_store;
+pub mod postgres_signing_key_store;
 pub mod postgres_user_store;
+pub mod redis_account_lockout_store;
 pub mod redis_banned_token_store;
+pub mod redis_email_change_store;
+pub mod redis_email_verification_token_store;
+pub mod redis_magic_link_store;
+pub mod redis_password_reset_token_store;
+pub mod redis_rate_limit_store;
+pub mod redis_refresh_token_store;
+pub mod redis_session_store;
 pub mod redis_two_fa_code_store;
@@ -0,0 +1,165 @@
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_store::{Session, SessionStore, SessionStoreError},
+        email::Email,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // sessions last seen before this are idle and treated as gone
+    fn idle_cutoff() -> i64 {
+        Utc::now().timestamp() - REFRESH_TOKEN_TTL_SECONDS
+    }
+}
+
+struct SessionRow {
+    id: String,
+    email: String,
+    created_at: i64,
+    last_seen_at: i64,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl TryFrom<SessionRow> for Session {
+    type Error = SessionStoreError;
+
+    fn try_from(row: SessionRow) -> Result<Self, Self::Error> {
+        let email =
+            Email::parse(Secret::new(row.email)).map_err(SessionStoreError::UnexpectedError)?;
+        Ok(Session {
+            id: row.id,
+            email,
+            created_at: row.created_at,
+            last_seen_at: row.last_seen_at,
+            ip: row.ip,
+            user_agent: row.user_agent,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    #[tracing::instrument(name = "Add session to PostgreSQL", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        // idle sessions are never touched again, clean them up as new ones come in
+        sqlx::query!(
+            "DELETE FROM sessions WHERE last_seen_at <= $1;",
+            Self::idle_cutoff()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Fail to delete idle sessions from PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        sqlx::query!(
+            "INSERT INTO sessions (id, email, created_at, last_seen_at, ip, user_agent) VALUES ($1, $2, $3, $4, $5, $6);",
+            session.id,
+            session.email.as_ref().expose_secret(),
+            session.created_at,
+            session.last_seen_at,
+            session.ip,
+            session.user_agent
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Fail to insert session into PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Touch session in PostgreSQL", skip_all)]
+    async fn touch_session(&mut self, id: &str) -> Result<Session, SessionStoreError> {
+        let row = sqlx::query_as!(
+            SessionRow,
+            "UPDATE sessions SET last_seen_at = $2 WHERE id = $1 AND last_seen_at > $3 RETURNING id, email, created_at, last_seen_at, ip, user_agent;",
+            id,
+            Utc::now().timestamp(),
+            Self::idle_cutoff()
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .wrap_err("Fail to update session in PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?
+        .ok_or(SessionStoreError::SessionNotFound)?;
+        row.try_into()
+    }
+
+    #[tracing::instrument(name = "Get sessions from PostgreSQL", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let rows = sqlx::query_as!(
+            SessionRow,
+            "SELECT id, email, created_at, last_seen_at, ip, user_agent FROM sessions WHERE email = $1 AND last_seen_at > $2 ORDER BY created_at;",
+            email.as_ref().expose_secret(),
+            Self::idle_cutoff()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Fail to fetch sessions from PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+        rows.into_iter().map(Session::try_from).collect()
+    }
+
+    #[tracing::instrument(name = "Remove session from PostgreSQL", skip_all)]
+    async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE id = $1 AND email = $2;",
+            id,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Fail to delete session from PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(SessionStoreError::SessionNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Remove user sessions from PostgreSQL", skip_all)]
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<usize, SessionStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM sessions WHERE email = $1;",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Fail to delete user sessions from PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+        Ok(result.rows_affected() as usize)
+    }
+
+    #[tracing::instrument(name = "Move user sessions in PostgreSQL", skip_all)]
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), SessionStoreError> {
+        sqlx::query!(
+            "UPDATE sessions SET email = $2 WHERE email = $1;",
+            email.as_ref().expose_secret(),
+            new_email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .wrap_err("Fail to move user sessions in PostgreSQL")
+        .map_err(SessionStoreError::UnexpectedError)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,97 @@
+use color_eyre::eyre::Context;
+use sqlx::PgPool;
+
+use crate::{
+    domain::data_store::{keyring_states, KeyringEntry, SigningKeyStore, SigningKeyStoreError},
+    services::keyring::StoredSigningKey,
+};
+
+pub struct PostgresSigningKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresSigningKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SigningKeyStore for PostgresSigningKeyStore {
+    #[tracing::instrument(name = "Get signing keys from PostgreSQL", skip_all)]
+    async fn get_keys(&self) -> Result<Vec<KeyringEntry>, SigningKeyStoreError> {
+        let rows = sqlx::query_as!(
+            StoredSigningKey,
+            "SELECT kid, algorithm, private_key, state, created_at FROM signing_keys ORDER BY created_at;"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .wrap_err("Fail to fetch signing keys from PostgreSQL")
+        .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        rows.into_iter()
+            .map(StoredSigningKey::open)
+            .collect::<Result<_, _>>()
+            .map_err(SigningKeyStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Save signing keys to PostgreSQL", skip_all)]
+    async fn save_keys(
+        &mut self,
+        current: &[KeyringEntry],
+        keys: &[KeyringEntry],
+    ) -> Result<(), SigningKeyStoreError> {
+        let keys = keys
+            .iter()
+            .map(StoredSigningKey::seal)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(SigningKeyStoreError::UnexpectedError)?;
+
+        // other instances reloading in between must see either the old keyring or the new one
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+        // other instances saving wait here until this one is done, readers don't. The lock
+        // also holds on an empty table, which row locks could not.
+        sqlx::query!("LOCK TABLE signing_keys IN SHARE ROW EXCLUSIVE MODE;")
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+        let mut stored: Vec<(String, String)> =
+            sqlx::query!("SELECT kid, state FROM signing_keys;")
+                .fetch_all(&mut *transaction)
+                .await
+                .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?
+                .into_iter()
+                .map(|row| (row.kid, row.state))
+                .collect();
+        stored.sort();
+        if stored != keyring_states(current) {
+            return Err(SigningKeyStoreError::Conflict);
+        }
+
+        sqlx::query!("DELETE FROM signing_keys;")
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+        for key in keys {
+            sqlx::query!(
+                "INSERT INTO signing_keys (kid, algorithm, private_key, state, created_at) VALUES ($1, $2, $3, $4, $5);",
+                key.kid,
+                key.algorithm,
+                key.private_key,
+                key.state,
+                key.created_at
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| SigningKeyStoreError::UnexpectedError(e.into()))
+    }
+}
@@ -1,12 +1,14 @@
 use crate::domain::{
-    data_store::{UserStore, UserStoreError},
+    data_store::{TotpRecord, UserList, UserListQuery, UserStore, UserStoreError, UserUpdate},
     email::Email,
     password::Password,
-    user::User,
+    recovery_code::RecoveryCode,
+    totp_secret::EncryptedTotpSecret,
+    user::{User, UserRole},
 };
 use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
+    password_hash::{self, SaltString},
+    Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
 };
 use color_eyre::eyre::{Context, Result};
 use secrecy::{ExposeSecret, Secret};
@@ -66,6 +68,73 @@ impl PostgresUserStore {
 
         result?
     }
+
+    // Returns the row id of the matching recovery code, and how many codes the account has.
+    async fn find_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(i32, usize), UserStoreError> {
+        let rows = sqlx::query!(
+            "SELECT id, code_hash FROM recovery_codes WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        for row in &rows {
+            let hash = Secret::new(row.code_hash.clone());
+            if Self::verify_password_hash(hash, code.as_ref().to_owned())
+                .await
+                .is_ok()
+            {
+                return Ok((row.id, rows.len()));
+            }
+        }
+        Err(UserStoreError::InvalidCredentials)
+    }
+}
+
+struct UserRow {
+    email: String,
+    password_hash: String,
+    requires_2fa: bool,
+    email_verified: bool,
+    role: String,
+    disabled: bool,
+}
+
+impl TryFrom<UserRow> for User {
+    type Error = UserStoreError;
+
+    fn try_from(row: UserRow) -> Result<Self, Self::Error> {
+        let email =
+            Email::parse(Secret::new(row.email)).map_err(UserStoreError::UnexpectedError)?;
+        let password = Password::parse(Secret::new(row.password_hash))
+            .map_err(UserStoreError::UnexpectedError)?;
+        let role = row
+            .role
+            .parse::<UserRole>()
+            .map_err(UserStoreError::UnexpectedError)?;
+        Ok(User::new(
+            email,
+            password,
+            row.requires_2fa,
+            row.email_verified,
+            role,
+            row.disabled,
+        ))
+    }
+}
+
+// the search term is matched literally, LIKE wildcards in it don't count
+fn like_pattern(search: &str) -> String {
+    let escaped = search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
 }
 
 #[async_trait::async_trait]
@@ -79,10 +148,13 @@ impl UserStore for PostgresUserStore {
             .map_err(UserStoreError::UnexpectedError)?;
 
         sqlx::query!(
-            "INSERT INTO users (email, password_hash, requires_2FA) VALUES( $1, $2, $3);",
+            "INSERT INTO users (email, password_hash, requires_2FA, email_verified, role, disabled) VALUES( $1, $2, $3, $4, $5, $6);",
             email.as_ref().expose_secret(),
             password_hash.expose_secret(),
-            user.requires_2fa()
+            user.requires_2fa(),
+            user.email_verified(),
+            user.role().as_str(),
+            user.disabled()
         )
         .execute(&self.pool)
         .await
@@ -93,20 +165,18 @@ impl UserStore for PostgresUserStore {
 
     #[tracing::instrument(name = "Fetch user from PostgreSQL", skip_all)]
     async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        sqlx::query!(
-            r#"SELECT email, password_hash, requires_2fa FROM users WHERE email = $1"#,
+        sqlx::query_as!(
+            UserRow,
+            r#"SELECT email, password_hash, requires_2fa, email_verified, role, disabled FROM users WHERE email = $1"#,
             email.as_ref().expose_secret()
         )
         .fetch_one(&self.pool)
         .await
-        .map_err(|e| UserStoreError::UnexpectedError(e.into()))
-        .map(|row| {
-            let email =
-                Email::parse(Secret::new(row.email)).map_err(UserStoreError::UnexpectedError)?;
-            let password = Password::parse(Secret::new(row.password_hash))
-                .map_err(UserStoreError::UnexpectedError)?;
-            Ok(User::new(email, password, row.requires_2fa))
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+            e => UserStoreError::UnexpectedError(e.into()),
         })?
+        .try_into()
     }
 
     #[tracing::instrument(name = "Validate user from PostgreSQL", skip_all)]
@@ -123,21 +193,375 @@ impl UserStore for PostgresUserStore {
         let result =
             PostgresUserStore::verify_password_hash(pwd.to_owned(), pwd_str.to_owned()).await;
 
+        // only a password that doesn't match counts as wrong credentials, a broken hash is on us
         match result {
             Ok(_) => Ok(user),
+            Err(e) if matches!(e.downcast_ref(), Some(password_hash::Error::Password)) => {
+                Err(UserStoreError::InvalidCredentials)
+            }
             Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
         }
     }
 
     #[tracing::instrument(name = "Delete user from PostgreSQL", skip_all)]
     async fn delete_user(&mut self, email: Email) -> Result<(), UserStoreError> {
-        let sql = "DELETE FROM users WHERE email = ?";
-        if sqlx::query(sql)
-            .bind(email.as_ref().expose_secret())
+        let result = sqlx::query!(
+            "DELETE FROM users WHERE email = $1;",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Update user password in PostgreSQL", skip_all)]
+    async fn update_password(
+        &mut self,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = Self::compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            "UPDATE users SET password_hash = $1 WHERE email = $2;",
+            password_hash.expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Mark email as verified in PostgreSQL", skip_all)]
+    async fn verify_email(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET email_verified = TRUE WHERE email = $1;",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Update user email in PostgreSQL", skip_all)]
+    async fn update_email(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), UserStoreError> {
+        // the new address had to confirm the change, so it counts as verified.
+        let result = sqlx::query!(
+            "UPDATE users SET email = $1, email_verified = TRUE WHERE email = $2;",
+            new_email.as_ref().expose_secret(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                UserStoreError::UserAlreadyExists
+            }
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Set TOTP secret in PostgreSQL", skip_all)]
+    async fn set_totp_secret(
+        &mut self,
+        email: &Email,
+        secret: EncryptedTotpSecret,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET totp_secret = $1, totp_confirmed = FALSE, totp_last_step = NULL WHERE email = $2;",
+            secret.as_ref(),
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Fetch TOTP secret from PostgreSQL", skip_all)]
+    async fn get_totp(&self, email: &Email) -> Result<Option<TotpRecord>, UserStoreError> {
+        let row = sqlx::query!(
+            "SELECT totp_secret, totp_confirmed, totp_last_step FROM users WHERE email = $1",
+            email.as_ref().expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?;
+
+        let Some(secret) = row.totp_secret else {
+            return Ok(None);
+        };
+        let secret = EncryptedTotpSecret::parse(secret).map_err(UserStoreError::UnexpectedError)?;
+        let last_step = row
+            .totp_last_step
+            .map(u64::try_from)
+            .transpose()
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        Ok(Some(TotpRecord {
+            secret,
+            confirmed: row.totp_confirmed,
+            last_step,
+        }))
+    }
+
+    #[tracing::instrument(name = "Confirm TOTP secret in PostgreSQL", skip_all)]
+    async fn confirm_totp(&mut self, email: &Email) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET totp_confirmed = TRUE WHERE email = $1 AND totp_secret IS NOT NULL;",
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Use TOTP time step in PostgreSQL", skip_all)]
+    async fn use_totp_step(&mut self, email: &Email, step: u64) -> Result<(), UserStoreError> {
+        let step: i64 = step
+            .try_into()
+            .map_err(|e: std::num::TryFromIntError| UserStoreError::UnexpectedError(e.into()))?;
+
+        // compare and set in one statement, two requests racing with the same code can't both win
+        let result = sqlx::query!(
+            "UPDATE users SET totp_last_step = $1 WHERE email = $2 AND totp_secret IS NOT NULL AND (totp_last_step IS NULL OR totp_last_step < $1);",
+            step,
+            email.as_ref().expose_secret()
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::TotpCodeReused);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Set recovery codes in PostgreSQL", skip_all)]
+    async fn set_recovery_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<RecoveryCode>,
+    ) -> Result<(), UserStoreError> {
+        let mut hashes = Vec::with_capacity(codes.len());
+        for code in codes {
+            let hash = Self::compute_password_hash(code.as_ref().to_owned())
+                .await
+                .map_err(UserStoreError::UnexpectedError)?;
+            hashes.push(hash);
+        }
+
+        // the old set must never outlive the new one
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        sqlx::query!(
+            "DELETE FROM recovery_codes WHERE email = $1;",
+            email.as_ref().expose_secret()
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        for hash in hashes {
+            sqlx::query!(
+                "INSERT INTO recovery_codes (email, code_hash) VALUES ($1, $2);",
+                email.as_ref().expose_secret(),
+                hash.expose_secret()
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+                    UserStoreError::UserNotFound
+                }
+                e => UserStoreError::UnexpectedError(e.into()),
+            })?;
+        }
+        transaction
+            .commit()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Check recovery code in PostgreSQL", skip_all)]
+    async fn check_recovery_code(
+        &self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<(), UserStoreError> {
+        self.find_recovery_code(email, code).await.map(|_| ())
+    }
+
+    #[tracing::instrument(name = "Use recovery code in PostgreSQL", skip_all)]
+    async fn use_recovery_code(
+        &mut self,
+        email: &Email,
+        code: &RecoveryCode,
+    ) -> Result<usize, UserStoreError> {
+        let (id, count) = self.find_recovery_code(email, code).await?;
+
+        // someone else may have used the same code in the meantime, only one of us gets to delete it
+        let result = sqlx::query!("DELETE FROM recovery_codes WHERE id = $1;", id)
             .execute(&self.pool)
             .await
-            .is_err()
-        {
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        Ok(count - 1)
+    }
+
+    #[tracing::instrument(name = "Get token version from PostgreSQL", skip_all)]
+    async fn get_token_version(&self, email: &Email) -> Result<i32, UserStoreError> {
+        sqlx::query_scalar!(
+            "SELECT token_version FROM users WHERE email = $1;",
+            email.as_ref().expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+            e => UserStoreError::UnexpectedError(e.into()),
+        })
+    }
+
+    #[tracing::instrument(name = "Increment token version in PostgreSQL", skip_all)]
+    async fn increment_token_version(&mut self, email: &Email) -> Result<i32, UserStoreError> {
+        sqlx::query_scalar!(
+            "UPDATE users SET token_version = token_version + 1 WHERE email = $1 RETURNING token_version;",
+            email.as_ref().expose_secret()
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+            e => UserStoreError::UnexpectedError(e.into()),
+        })
+    }
+
+    #[tracing::instrument(name = "List users from PostgreSQL", skip_all)]
+    async fn list_users(&self, query: &UserListQuery) -> Result<UserList, UserStoreError> {
+        let pattern = like_pattern(query.search.as_deref().unwrap_or_default());
+        let limit = i64::try_from(query.limit)
+            .wrap_err("Fail to convert limit into i64")
+            .map_err(UserStoreError::UnexpectedError)?;
+        let offset = i64::try_from(query.offset)
+            .wrap_err("Fail to convert offset into i64")
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let total = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM users WHERE email ILIKE $1;"#,
+            pattern
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let rows = sqlx::query_as!(
+            UserRow,
+            "SELECT email, password_hash, requires_2fa, email_verified, role, disabled FROM users WHERE email ILIKE $1 ORDER BY email LIMIT $2 OFFSET $3;",
+            pattern,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        let users = rows
+            .into_iter()
+            .map(User::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(UserList {
+            users,
+            total: total as usize,
+        })
+    }
+
+    #[tracing::instrument(name = "Update user in PostgreSQL", skip_all)]
+    async fn update_user(
+        &mut self,
+        email: &Email,
+        update: UserUpdate,
+    ) -> Result<User, UserStoreError> {
+        sqlx::query_as!(
+            UserRow,
+            "UPDATE users SET requires_2fa = COALESCE($2, requires_2fa), role = COALESCE($3, role) WHERE email = $1 RETURNING email, password_hash, requires_2fa, email_verified, role, disabled;",
+            email.as_ref().expose_secret(),
+            update.requires_2fa,
+            update.role.map(|role| role.as_str())
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => UserStoreError::UserNotFound,
+            e => UserStoreError::UnexpectedError(e.into()),
+        })?
+        .try_into()
+    }
+
+    #[tracing::instrument(name = "Set user disabled in PostgreSQL", skip_all)]
+    async fn set_disabled(&mut self, email: &Email, disabled: bool) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            "UPDATE users SET disabled = $2 WHERE email = $1;",
+            email.as_ref().expose_secret(),
+            disabled
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
             return Err(UserStoreError::UserNotFound);
         }
 
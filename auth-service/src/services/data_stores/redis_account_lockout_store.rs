@@ -0,0 +1,157 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        account_lockout::LockoutRecord,
+        data_store::{AccountLockoutStore, AccountLockoutStoreError},
+        email::Email,
+        unlock_token::UnlockToken,
+    },
+    utils::constants::{LOCKOUT_RECORD_TTL_SECONDS, UNLOCK_TOKEN_TTL_SECONDS},
+};
+
+// a hash of the record's fields, so failures can be counted with HINCRBY
+const ACCOUNT_LOCKOUT_PREFIX: &str = "account_lockout_record:";
+const FAILED_ATTEMPTS_FIELD: &str = "failed_attempts";
+const LOCKED_UNTIL_FIELD: &str = "locked_until";
+const UNLOCK_TOKEN_PREFIX: &str = "unlock_token:";
+
+pub type ARWRedisAccountLockoutStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        ACCOUNT_LOCKOUT_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_token_key(token: &UnlockToken) -> String {
+    format!("{}{}", UNLOCK_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_ttl(seconds: i64) -> Result<u64, AccountLockoutStoreError> {
+    seconds
+        .try_into()
+        .wrap_err("Fail to convert account lockout ttl into u64")
+        .map_err(AccountLockoutStoreError::UnexpectedError)
+}
+
+pub struct RedisAccountLockoutStore {
+    client: ARWRedisAccountLockoutStoreType,
+}
+
+impl RedisAccountLockoutStore {
+    pub fn new(client: ARWRedisAccountLockoutStoreType) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountLockoutStore for RedisAccountLockoutStore {
+    #[tracing::instrument(name = "Get account lockout record from Redis", skip_all)]
+    async fn get_record(&self, email: &Email) -> Result<LockoutRecord, AccountLockoutStoreError> {
+        let fields: HashMap<String, i64> = self
+            .client
+            .write()
+            .await
+            .hgetall(get_key(email))
+            .wrap_err("Fail to fetch account lockout record from Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+
+        let failed_attempts = fields
+            .get(FAILED_ATTEMPTS_FIELD)
+            .copied()
+            .unwrap_or_default()
+            .try_into()
+            .wrap_err("Fail to convert failed attempts into u32")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+        Ok(LockoutRecord {
+            failed_attempts,
+            locked_until: fields.get(LOCKED_UNTIL_FIELD).copied(),
+        })
+    }
+
+    #[tracing::instrument(name = "Count failed login in Redis", skip_all)]
+    async fn count_failure(&mut self, email: &Email) -> Result<u32, AccountLockoutStoreError> {
+        let key = get_key(email);
+
+        // HINCRBY is atomic across instances, every failure keeps the record for another TTL
+        let (failed_attempts,): (u32,) = redis::pipe()
+            .atomic()
+            .hincr(&key, FAILED_ATTEMPTS_FIELD, 1)
+            .expire(&key, LOCKOUT_RECORD_TTL_SECONDS)
+            .ignore()
+            .query(&mut *self.client.write().await)
+            .wrap_err("Fail to count failed login in Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+        Ok(failed_attempts)
+    }
+
+    #[tracing::instrument(name = "Lock account in Redis", skip_all)]
+    async fn lock(&mut self, email: &Email, until: i64) -> Result<(), AccountLockoutStoreError> {
+        let key = get_key(email);
+
+        redis::pipe()
+            .atomic()
+            .hset(&key, LOCKED_UNTIL_FIELD, until)
+            .ignore()
+            .expire(&key, LOCKOUT_RECORD_TTL_SECONDS)
+            .ignore()
+            .query(&mut *self.client.write().await)
+            .wrap_err("Fail to lock account in Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove account lockout record from Redis", skip_all)]
+    async fn remove_record(&mut self, email: &Email) -> Result<(), AccountLockoutStoreError> {
+        self.client
+            .write()
+            .await
+            .del(get_key(email))
+            .wrap_err("Fail to remove account lockout record from Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Add unlock token to Redis", skip_all)]
+    async fn add_unlock_token(
+        &mut self,
+        email: Email,
+        token: UnlockToken,
+    ) -> Result<(), AccountLockoutStoreError> {
+        let ttl = get_ttl(UNLOCK_TOKEN_TTL_SECONDS)?;
+
+        self.client
+            .write()
+            .await
+            .set_ex(get_token_key(&token), email.as_ref().expose_secret(), ttl)
+            .wrap_err("Fail to set unlock token in Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Consume unlock token from Redis", skip_all)]
+    async fn consume_unlock_token(
+        &mut self,
+        token: &UnlockToken,
+    ) -> Result<Email, AccountLockoutStoreError> {
+        let data: Option<String> = self
+            .client
+            .write()
+            .await
+            .get_del(get_token_key(token))
+            .wrap_err("Fail to fetch unlock token from Redis")
+            .map_err(AccountLockoutStoreError::UnexpectedError)?;
+
+        match data {
+            Some(email) => {
+                Email::parse(Secret::new(email)).map_err(AccountLockoutStoreError::UnexpectedError)
+            }
+            None => Err(AccountLockoutStoreError::TokenNotFound),
+        }
+    }
+}
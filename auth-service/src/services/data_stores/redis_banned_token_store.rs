@@ -1,21 +1,17 @@
+use chrono::Utc;
 use color_eyre::eyre::{Context, Result};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use redis::{Commands, Connection};
 
-use crate::{
-    domain::data_store::{BannedTokenStore, BannedTokenStoreError},
-    utils::constants::TOKEN_TTL_SECONDS,
-};
+use crate::domain::data_store::{BannedTokenStore, BannedTokenStoreError};
 
 pub type ARWRedisClientType = Arc<RwLock<Connection>>;
 
-const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token";
-
-fn get_key(token: &str) -> String {
-    format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
-}
+// a sorted set of banned jti scored by the expiry of their token. Bans used to be kept as
+// `banned_token:<token>` keys, those are no longer read and expire on their own.
+const BANNED_TOKENS_KEY: &str = "banned_tokens";
 
 pub struct RedisBannedTokenStore {
     client: ARWRedisClientType,
@@ -30,25 +26,41 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     #[tracing::instrument(name = "Add banned token to Redis", skip_all)]
-    async fn add_token(&mut self, token: &str) -> Result<(), BannedTokenStoreError> {
-        let key = get_key(token);
-        let ttl: u64 = TOKEN_TTL_SECONDS
-            .try_into()
-            .wrap_err("Fail to convert token_ttl_seconds into u64")
-            .map_err(BannedTokenStoreError::UnexpectedError)?;
+    async fn add_token(&mut self, jti: &str, expires_at: i64) -> Result<(), BannedTokenStoreError> {
+        let now = Utc::now().timestamp();
+        // an expired token is rejected anyway. It is still taken during the second of its exp,
+        // so its ban has to last through that second too.
+        if expires_at < now {
+            return Ok(());
+        }
 
-        self.client
-            .write()
-            .await
-            .set_ex(key, true, ttl)
+        let mut db = self.client.write().await;
+        // bans run out along with their token, clear those on the way
+        let _: () = db
+            .zrembyscore(BANNED_TOKENS_KEY, "-inf", format!("({}", now))
+            .wrap_err("Fail to purge expired banned tokens in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+        db.zadd(BANNED_TOKENS_KEY, jti, expires_at)
             .wrap_err("Fail to store banned token in Redis")
             .map_err(BannedTokenStoreError::UnexpectedError)
     }
 
     #[tracing::instrument(name = "Check banned token in Redis", skip_all)]
-    async fn check_token(&self, token: &str) -> bool {
-        let key = get_key(token);
+    async fn check_token(&self, jti: &str) -> bool {
+        let now = Utc::now().timestamp();
         let mut db = self.client.write().await;
-        db.exists(key).is_ok_and(|f: u32| f > 0)
+        db.zscore(BANNED_TOKENS_KEY, jti)
+            .is_ok_and(|expires_at: Option<i64>| expires_at.is_some_and(|t| t >= now))
+    }
+
+    #[tracing::instrument(name = "Count banned tokens in Redis", skip_all)]
+    async fn count(&self) -> Result<usize, BannedTokenStoreError> {
+        let now = Utc::now().timestamp();
+        self.client
+            .write()
+            .await
+            .zcount(BANNED_TOKENS_KEY, now, "+inf")
+            .wrap_err("Fail to count banned tokens in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)
     }
 }
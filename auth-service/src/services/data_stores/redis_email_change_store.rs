@@ -0,0 +1,171 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_store::{EmailChangeRequest, EmailChangeStore, EmailChangeStoreError},
+        email::Email,
+        email_change_token::EmailChangeToken,
+    },
+    utils::constants::EMAIL_CHANGE_TOKEN_TTL_SECONDS,
+};
+
+const EMAIL_CHANGE_REQUEST_PREFIX: &str = "email_change_request:";
+const EMAIL_CHANGE_CONFIRM_PREFIX: &str = "email_change_confirm:";
+const EMAIL_CHANGE_CANCEL_PREFIX: &str = "email_change_cancel:";
+
+pub type ARWRedisEmailChangeStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        EMAIL_CHANGE_REQUEST_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_token_key(prefix: &str, token: &EmailChangeToken) -> String {
+    format!("{}{}", prefix, token.as_ref().expose_secret())
+}
+
+// (new email, confirm token, cancel token)
+#[derive(Serialize, Deserialize)]
+struct EmailChangeTuple(String, String, String);
+
+pub struct RedisEmailChangeStore {
+    client: ARWRedisEmailChangeStoreType,
+}
+
+impl RedisEmailChangeStore {
+    pub fn new(client: ARWRedisEmailChangeStoreType) -> Self {
+        Self { client }
+    }
+
+    // looks up the request through one of its tokens and removes everything belonging to it.
+    async fn take(
+        &mut self,
+        prefix: &str,
+        token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        let mut db = self.client.write().await;
+
+        let email: Option<String> = db
+            .get(get_token_key(prefix, token))
+            .wrap_err("Fail to fetch email change token from Redis")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        let email = match email {
+            Some(email) => {
+                Email::parse(Secret::new(email)).map_err(EmailChangeStoreError::UnexpectedError)?
+            }
+            None => return Err(EmailChangeStoreError::RequestNotFound),
+        };
+
+        let data: Option<String> = db
+            .get(get_key(&email))
+            .wrap_err("Fail to fetch email change request from Redis")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        let Some(data) = data else {
+            return Err(EmailChangeStoreError::RequestNotFound);
+        };
+        let EmailChangeTuple(new_email, confirm_token, cancel_token) = serde_json::from_str(&data)
+            .wrap_err("Fail to deserialize email change tuple")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+
+        let new_email =
+            Email::parse(Secret::new(new_email)).map_err(EmailChangeStoreError::UnexpectedError)?;
+        let confirm_token = EmailChangeToken::parse(Secret::new(confirm_token))
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        let cancel_token = EmailChangeToken::parse(Secret::new(cancel_token))
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+
+        // the token may belong to a request that has since been replaced
+        let expected = match prefix {
+            EMAIL_CHANGE_CONFIRM_PREFIX => &confirm_token,
+            _ => &cancel_token,
+        };
+        if expected.ne(token) {
+            return Err(EmailChangeStoreError::RequestNotFound);
+        }
+
+        let _: () = db
+            .del(&[
+                get_key(&email),
+                get_token_key(EMAIL_CHANGE_CONFIRM_PREFIX, &confirm_token),
+                get_token_key(EMAIL_CHANGE_CANCEL_PREFIX, &cancel_token),
+            ])
+            .wrap_err("Fail to delete email change request from Redis")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+
+        Ok(EmailChangeRequest {
+            email,
+            new_email,
+            confirm_token,
+            cancel_token,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailChangeStore for RedisEmailChangeStore {
+    #[tracing::instrument(name = "Add email change request to Redis", skip_all)]
+    async fn add_request(
+        &mut self,
+        request: EmailChangeRequest,
+    ) -> Result<(), EmailChangeStoreError> {
+        let email = request.email.as_ref().expose_secret().to_owned();
+        let instance = EmailChangeTuple(
+            request.new_email.as_ref().expose_secret().to_owned(),
+            request.confirm_token.as_ref().expose_secret().to_owned(),
+            request.cancel_token.as_ref().expose_secret().to_owned(),
+        );
+        let value = serde_json::to_string(&instance)
+            .wrap_err("Fail to serialize email change tuple")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        let ttl: u64 = EMAIL_CHANGE_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert email_change_token_ttl_seconds into u64")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+
+        // overwriting the request is enough to replace an older one, its tokens won't match anymore.
+        let mut db = self.client.write().await;
+        let _: () = db
+            .set_ex(get_key(&request.email), value, ttl)
+            .wrap_err("Fail to set email change request in Redis")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        let _: () = db
+            .set_ex(
+                get_token_key(EMAIL_CHANGE_CONFIRM_PREFIX, &request.confirm_token),
+                &email,
+                ttl,
+            )
+            .wrap_err("Fail to set email change confirm token in Redis")
+            .map_err(EmailChangeStoreError::UnexpectedError)?;
+        db.set_ex(
+            get_token_key(EMAIL_CHANGE_CANCEL_PREFIX, &request.cancel_token),
+            &email,
+            ttl,
+        )
+        .wrap_err("Fail to set email change cancel token in Redis")
+        .map_err(EmailChangeStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Take confirmed email change request from Redis", skip_all)]
+    async fn take_confirmed(
+        &mut self,
+        confirm_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        self.take(EMAIL_CHANGE_CONFIRM_PREFIX, confirm_token).await
+    }
+
+    #[tracing::instrument(name = "Take cancelled email change request from Redis", skip_all)]
+    async fn take_cancelled(
+        &mut self,
+        cancel_token: &EmailChangeToken,
+    ) -> Result<EmailChangeRequest, EmailChangeStoreError> {
+        self.take(EMAIL_CHANGE_CANCEL_PREFIX, cancel_token).await
+    }
+}
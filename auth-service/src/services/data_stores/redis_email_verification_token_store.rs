@@ -0,0 +1,120 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, Secret};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_store::{EmailVerificationTokenStore, EmailVerificationTokenStoreError},
+        email::Email,
+        email_verification_token::EmailVerificationToken,
+    },
+    utils::constants::{
+        EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS, EMAIL_VERIFICATION_TOKEN_TTL_SECONDS,
+    },
+};
+
+const EMAIL_VERIFICATION_TOKEN_PREFIX: &str = "email_verification_token:";
+const EMAIL_VERIFICATION_COOLDOWN_PREFIX: &str = "email_verification_cooldown:";
+
+pub type ARWRedisEmailVerificationTokenStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(token: &EmailVerificationToken) -> String {
+    format!(
+        "{}{}",
+        EMAIL_VERIFICATION_TOKEN_PREFIX,
+        token.as_ref().expose_secret()
+    )
+}
+
+fn get_cooldown_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        EMAIL_VERIFICATION_COOLDOWN_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+pub struct RedisEmailVerificationTokenStore {
+    client: ARWRedisEmailVerificationTokenStoreType,
+}
+
+impl RedisEmailVerificationTokenStore {
+    pub fn new(client: ARWRedisEmailVerificationTokenStoreType) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailVerificationTokenStore for RedisEmailVerificationTokenStore {
+    #[tracing::instrument(name = "Add email verification token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: EmailVerificationToken,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let key = get_key(&token);
+        let ttl: u64 = EMAIL_VERIFICATION_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert email_verification_token_ttl_seconds into u64")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        self.client
+            .write()
+            .await
+            .set_ex(key, email.as_ref().expose_secret(), ttl)
+            .wrap_err("Fail to set email verification token in Redis!")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Consume email verification token from Redis", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &EmailVerificationToken,
+    ) -> Result<Email, EmailVerificationTokenStoreError> {
+        let key = get_key(token);
+        let data: Option<String> = self
+            .client
+            .write()
+            .await
+            .get_del(key)
+            .wrap_err("Fail to fetch email verification token from Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        match data {
+            Some(email) => Email::parse(Secret::new(email))
+                .map_err(EmailVerificationTokenStoreError::UnexpectedError),
+            None => Err(EmailVerificationTokenStoreError::TokenNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Start email verification cooldown in Redis", skip_all)]
+    async fn start_cooldown(
+        &mut self,
+        email: &Email,
+    ) -> Result<(), EmailVerificationTokenStoreError> {
+        let key = get_cooldown_key(email);
+        let ttl: u64 = EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert email_verification_resend_cooldown_seconds into u64")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        // NX only sets the key if it isn't there yet, so a running cooldown comes back as nil.
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl));
+        let result: Option<String> = self
+            .client
+            .write()
+            .await
+            .set_options(key, true, options)
+            .wrap_err("Fail to set email verification cooldown in Redis")
+            .map_err(EmailVerificationTokenStoreError::UnexpectedError)?;
+
+        match result {
+            Some(_) => Ok(()),
+            None => Err(EmailVerificationTokenStoreError::CooldownActive),
+        }
+    }
+}
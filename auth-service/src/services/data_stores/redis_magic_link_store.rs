@@ -0,0 +1,56 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::data_store::{MagicLinkStore, MagicLinkStoreError};
+
+pub type ARWRedisMagicLinkStoreType = Arc<RwLock<Connection>>;
+
+const CONSUMED_MAGIC_LINK_KEY_PREFIX: &str = "consumed_magic_link:";
+
+fn get_key(link_id: &str) -> String {
+    format!("{}{}", CONSUMED_MAGIC_LINK_KEY_PREFIX, link_id)
+}
+
+pub struct RedisMagicLinkStore {
+    client: ARWRedisMagicLinkStoreType,
+}
+
+impl RedisMagicLinkStore {
+    pub fn new(client: ARWRedisMagicLinkStoreType) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for RedisMagicLinkStore {
+    #[tracing::instrument(name = "Consume magic link in Redis", skip_all)]
+    async fn consume_link(
+        &mut self,
+        link_id: &str,
+        expires_at: i64,
+    ) -> Result<(), MagicLinkStoreError> {
+        // the link is still taken during the second of its exp, the key has to outlive that.
+        // NX makes checking and marking one step, two clicks racing each other can't both win.
+        let expires_at: u64 = (expires_at + 1)
+            .try_into()
+            .wrap_err("Fail to convert magic link expiry into u64")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EXAT(expires_at));
+        let result: Option<String> = self
+            .client
+            .write()
+            .await
+            .set_options(get_key(link_id), true, options)
+            .wrap_err("Fail to store consumed magic link in Redis")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        match result {
+            Some(_) => Ok(()),
+            None => Err(MagicLinkStoreError::LinkAlreadyUsed),
+        }
+    }
+}
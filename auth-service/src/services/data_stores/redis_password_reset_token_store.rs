@@ -0,0 +1,89 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_store::{PasswordResetTokenStore, PasswordResetTokenStoreError},
+        email::Email,
+        password_reset_token::PasswordResetToken,
+    },
+    utils::constants::PASSWORD_RESET_TOKEN_TTL_SECONDS,
+};
+
+const PASSWORD_RESET_TOKEN_PREFIX: &str = "password_reset_token:";
+
+pub type ARWRedisPasswordResetTokenStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        PASSWORD_RESET_TOKEN_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+pub struct RedisPasswordResetTokenStore {
+    client: ARWRedisPasswordResetTokenStoreType,
+}
+
+impl RedisPasswordResetTokenStore {
+    pub fn new(client: ARWRedisPasswordResetTokenStoreType) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl PasswordResetTokenStore for RedisPasswordResetTokenStore {
+    #[tracing::instrument(name = "Add password reset token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        email: Email,
+        token: PasswordResetToken,
+    ) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(&email);
+        let ttl: u64 = PASSWORD_RESET_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert password_reset_token_ttl_seconds into u64")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        self.client
+            .write()
+            .await
+            .set_ex(key, token.as_ref().expose_secret(), ttl)
+            .wrap_err("Fail to set password reset token in Redis!")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Fetch password reset token from Redis", skip_all)]
+    async fn get_token(
+        &self,
+        email: &Email,
+    ) -> Result<PasswordResetToken, PasswordResetTokenStoreError> {
+        let key = get_key(email);
+        match self.client.write().await.get::<_, String>(key) {
+            Ok(data) => PasswordResetToken::parse(Secret::new(data))
+                .map_err(PasswordResetTokenStoreError::UnexpectedError),
+            Err(_) => Err(PasswordResetTokenStoreError::TokenNotFound),
+        }
+    }
+
+    #[tracing::instrument(name = "Remove password reset token from Redis", skip_all)]
+    async fn remove_token(&mut self, email: &Email) -> Result<(), PasswordResetTokenStoreError> {
+        let key = get_key(email);
+        let removed: u32 = self
+            .client
+            .write()
+            .await
+            .del(key)
+            .wrap_err("Fail to delete password reset token from Redis")
+            .map_err(PasswordResetTokenStoreError::UnexpectedError)?;
+
+        match removed {
+            0 => Err(PasswordResetTokenStoreError::TokenNotFound),
+            _ => Ok(()),
+        }
+    }
+}
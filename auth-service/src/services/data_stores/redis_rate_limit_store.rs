@@ -0,0 +1,88 @@
+use color_eyre::eyre::Context;
+use redis::{Connection, Script};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_store::{RateLimitStore, RateLimitStoreError},
+    rate_limit::{RateLimit, RateLimitDecision},
+};
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit:";
+
+// Keeps the new theoretical arrival time of every key only if the request fits all of them, in
+// one step so instances sharing a key can't both take its last request. Returns the times they
+// started from, the decisions themselves are worked out by RateLimit::check the same way.
+static GCRA_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        local now = tonumber(ARGV[1])
+        local tats = {}
+        local allowed = true
+        for i, key in ipairs(KEYS) do
+            local interval = tonumber(ARGV[2 * i])
+            local tolerance = tonumber(ARGV[2 * i + 1])
+            local tat = tonumber(redis.call('GET', key)) or now
+            if tat < now then
+                tat = now
+            end
+            if now < tat + interval - tolerance then
+                allowed = false
+            end
+            tats[i] = tat
+        end
+        if allowed then
+            for i, key in ipairs(KEYS) do
+                local new_tat = tats[i] + tonumber(ARGV[2 * i])
+                redis.call('SET', key, new_tat, 'PX', new_tat - now)
+            end
+        end
+        return tats
+        ",
+    )
+});
+
+pub type ARWRedisRateLimitStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(key: &str) -> String {
+    format!("{}{}", RATE_LIMIT_PREFIX, key)
+}
+
+pub struct RedisRateLimitStore {
+    client: ARWRedisRateLimitStoreType,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(client: ARWRedisRateLimitStoreType) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    #[tracing::instrument(name = "Check rate limit in Redis", skip_all)]
+    async fn check(
+        &mut self,
+        buckets: &[(&str, &RateLimit)],
+        now: i64,
+    ) -> Result<Vec<RateLimitDecision>, RateLimitStoreError> {
+        let mut invocation = GCRA_SCRIPT.prepare_invoke();
+        invocation.arg(now);
+        for (key, limit) in buckets {
+            invocation
+                .key(get_key(key))
+                .arg(limit.interval())
+                .arg(limit.tolerance());
+        }
+        let tats: Vec<i64> = invocation
+            .invoke(&mut *self.client.write().await)
+            .wrap_err("Fail to check rate limit in Redis")
+            .map_err(RateLimitStoreError::UnexpectedError)?;
+
+        Ok(buckets
+            .iter()
+            .zip(tats)
+            .map(|((_, limit), tat)| limit.check(Some(tat), now))
+            .collect())
+    }
+}
@@ -0,0 +1,263 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, Script};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, LazyLock};
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        auth_method::AuthMethod,
+        data_store::{RefreshTokenRecord, RefreshTokenStore, RefreshTokenStoreError},
+        email::Email,
+        refresh_token::RefreshToken,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token:";
+const REVOKED_FAMILY_PREFIX: &str = "refresh_token_family_revoked:";
+const USER_FAMILIES_PREFIX: &str = "refresh_token_user_families:";
+const FAMILY_OWNER_PREFIX: &str = "refresh_token_family_owner:";
+
+// Marks the token as used only if it still holds what was read, in one step so two refreshes
+// racing with the same token can't both get through. Returns whether it was marked.
+static CONSUME_SCRIPT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r"
+        if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+            return 0
+        end
+        redis.call('SET', KEYS[1], ARGV[2], 'XX', 'KEEPTTL')
+        return 1
+        ",
+    )
+});
+
+pub type ARWRedisRefreshTokenStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(token: &RefreshToken) -> String {
+    format!("{}{}", REFRESH_TOKEN_PREFIX, token.as_ref().expose_secret())
+}
+
+fn get_family_key(family_id: &str) -> String {
+    format!("{}{}", REVOKED_FAMILY_PREFIX, family_id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!("{}{}", USER_FAMILIES_PREFIX, email.as_ref().expose_secret())
+}
+
+fn get_owner_key(family_id: &str) -> String {
+    format!("{}{}", FAMILY_OWNER_PREFIX, family_id)
+}
+
+// (email, family id, used, amr), tokens stored before the amr was kept count as a password login
+#[derive(Serialize, Deserialize)]
+struct RefreshTokenTuple(
+    String,
+    String,
+    bool,
+    #[serde(default = "password_login")] Vec<AuthMethod>,
+);
+
+fn password_login() -> Vec<AuthMethod> {
+    vec![AuthMethod::Password]
+}
+
+pub struct RedisRefreshTokenStore {
+    client: ARWRedisRefreshTokenStoreType,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(client: ARWRedisRefreshTokenStoreType) -> Self {
+        Self { client }
+    }
+
+    fn ttl() -> Result<u64, RefreshTokenStoreError> {
+        REFRESH_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert refresh_token_ttl_seconds into u64")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    #[tracing::instrument(name = "Add refresh token to Redis", skip_all)]
+    async fn add_token(
+        &mut self,
+        token: &RefreshToken,
+        record: RefreshTokenRecord,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let key = get_key(token);
+        let user_key = get_user_key(&record.email);
+        let instance = RefreshTokenTuple(
+            record.email.as_ref().expose_secret().to_owned(),
+            record.family_id.clone(),
+            false,
+            record.amr,
+        );
+        let value = serde_json::to_string(&instance)
+            .wrap_err("Fail to serialize refresh token tuple")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let ttl = Self::ttl()?;
+
+        let mut db = self.client.write().await;
+        let _: () = db
+            .set_ex(key, value, ttl)
+            .wrap_err("Fail to store refresh token in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        // index the family by user so every session can be revoked at once.
+        let _: () = db
+            .sadd(&user_key, record.family_id)
+            .wrap_err("Fail to index refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        db.expire(&user_key, ttl as i64)
+            .wrap_err("Fail to set expiry on refresh token family index")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Consume refresh token from Redis", skip_all)]
+    async fn consume_token(
+        &mut self,
+        token: &RefreshToken,
+    ) -> Result<RefreshTokenRecord, RefreshTokenStoreError> {
+        let key = get_key(token);
+        let mut db = self.client.write().await;
+
+        let data: Option<String> = db
+            .get(&key)
+            .wrap_err("Fail to fetch refresh token from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let data = data.ok_or(RefreshTokenStoreError::TokenNotFound)?;
+        let RefreshTokenTuple(email, family_id, used, amr) = serde_json::from_str(&data)
+            .wrap_err("Fail to deserialize refresh token tuple")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        let revoked: bool = db
+            .exists(get_family_key(&family_id))
+            .wrap_err("Fail to check refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        if revoked {
+            return Err(RefreshTokenStoreError::FamilyRevoked);
+        }
+
+        if used {
+            return Err(RefreshTokenStoreError::TokenReused(family_id));
+        }
+
+        // keep the used marker around until the token would have expired, so a replay is still caught.
+        let value = serde_json::to_string(&RefreshTokenTuple(
+            email.clone(),
+            family_id.clone(),
+            true,
+            amr.clone(),
+        ))
+        .wrap_err("Fail to serialize refresh token tuple")
+        .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let marked: bool = CONSUME_SCRIPT
+            .key(&key)
+            .arg(&data)
+            .arg(value)
+            .invoke(&mut *db)
+            .wrap_err("Fail to mark refresh token as used in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        // another instance consumed it since it was read
+        if !marked {
+            return Err(RefreshTokenStoreError::TokenReused(family_id));
+        }
+
+        // the user may have moved to another email address since this token was issued
+        let owner: Option<String> = db
+            .get(get_owner_key(&family_id))
+            .wrap_err("Fail to fetch refresh token family owner from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let email = Email::parse(Secret::new(owner.unwrap_or(email)))
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        Ok(RefreshTokenRecord {
+            email,
+            family_id,
+            amr,
+        })
+    }
+
+    #[tracing::instrument(name = "Revoke refresh token family in Redis", skip_all)]
+    async fn revoke_family(&mut self, family_id: &str) -> Result<(), RefreshTokenStoreError> {
+        let key = get_family_key(family_id);
+        self.client
+            .write()
+            .await
+            .set_ex(key, true, Self::ttl()?)
+            .wrap_err("Fail to revoke refresh token family in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Revoke user refresh tokens in Redis", skip_all)]
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), RefreshTokenStoreError> {
+        let user_key = get_user_key(email);
+        let ttl = Self::ttl()?;
+        let mut db = self.client.write().await;
+
+        let families: Vec<String> = db
+            .smembers(&user_key)
+            .wrap_err("Fail to fetch refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+
+        for family_id in families {
+            let _: () = db
+                .set_ex(get_family_key(&family_id), true, ttl)
+                .wrap_err("Fail to revoke refresh token family in Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        }
+
+        db.del(&user_key)
+            .wrap_err("Fail to delete refresh token family index from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Move user refresh tokens in Redis", skip_all)]
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), RefreshTokenStoreError> {
+        let user_key = get_user_key(email);
+        let new_user_key = get_user_key(new_email);
+        let ttl = Self::ttl()?;
+        let mut db = self.client.write().await;
+
+        let families: Vec<String> = db
+            .smembers(&user_key)
+            .wrap_err("Fail to fetch refresh token families from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        if families.is_empty() {
+            return Ok(());
+        }
+
+        // tokens are stored under their own key, so the new owner is recorded per family instead
+        for family_id in &families {
+            let _: () = db
+                .set_ex(
+                    get_owner_key(family_id),
+                    new_email.as_ref().expose_secret(),
+                    ttl,
+                )
+                .wrap_err("Fail to set refresh token family owner in Redis")
+                .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        }
+
+        let _: () = db
+            .sadd(&new_user_key, families)
+            .wrap_err("Fail to index refresh token families in Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        let _: () = db
+            .expire(&new_user_key, ttl as i64)
+            .wrap_err("Fail to set expiry on refresh token family index")
+            .map_err(RefreshTokenStoreError::UnexpectedError)?;
+        db.del(&user_key)
+            .wrap_err("Fail to delete refresh token family index from Redis")
+            .map_err(RefreshTokenStoreError::UnexpectedError)
+    }
+}
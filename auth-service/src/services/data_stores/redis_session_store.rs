@@ -0,0 +1,256 @@
+use chrono::Utc;
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_store::{Session, SessionStore, SessionStoreError},
+        email::Email,
+    },
+    utils::constants::REFRESH_TOKEN_TTL_SECONDS,
+};
+
+const SESSION_PREFIX: &str = "session:";
+const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+
+pub type ARWRedisSessionStoreType = Arc<RwLock<Connection>>;
+
+fn get_key(id: &str) -> String {
+    format!("{}{}", SESSION_PREFIX, id)
+}
+
+fn get_user_key(email: &Email) -> String {
+    format!("{}{}", USER_SESSIONS_PREFIX, email.as_ref().expose_secret())
+}
+
+// (email, created at, last seen at, ip, user agent)
+#[derive(Serialize, Deserialize)]
+struct SessionTuple(String, i64, i64, Option<String>, Option<String>);
+
+impl SessionTuple {
+    fn from_session(session: &Session) -> Self {
+        Self(
+            session.email.as_ref().expose_secret().to_owned(),
+            session.created_at,
+            session.last_seen_at,
+            session.ip.clone(),
+            session.user_agent.clone(),
+        )
+    }
+
+    fn into_session(self, id: &str) -> Result<Session, SessionStoreError> {
+        let SessionTuple(email, created_at, last_seen_at, ip, user_agent) = self;
+        let email = Email::parse(Secret::new(email)).map_err(SessionStoreError::UnexpectedError)?;
+        Ok(Session {
+            id: id.to_owned(),
+            email,
+            created_at,
+            last_seen_at,
+            ip,
+            user_agent,
+        })
+    }
+}
+
+pub struct RedisSessionStore {
+    client: ARWRedisSessionStoreType,
+}
+
+impl RedisSessionStore {
+    pub fn new(client: ARWRedisSessionStoreType) -> Self {
+        Self { client }
+    }
+
+    // sessions expire once they went unused for as long as a refresh token lives
+    fn ttl() -> Result<u64, SessionStoreError> {
+        REFRESH_TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("Fail to convert refresh_token_ttl_seconds into u64")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    fn serialize(session: &Session) -> Result<String, SessionStoreError> {
+        serde_json::to_string(&SessionTuple::from_session(session))
+            .wrap_err("Fail to serialize session tuple")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    fn load(db: &mut Connection, id: &str) -> Result<Option<Session>, SessionStoreError> {
+        let data: Option<String> = db
+            .get(get_key(id))
+            .wrap_err("Fail to fetch session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        data.map(|data| {
+            serde_json::from_str::<SessionTuple>(&data)
+                .wrap_err("Fail to deserialize session tuple")
+                .map_err(SessionStoreError::UnexpectedError)?
+                .into_session(id)
+        })
+        .transpose()
+    }
+}
+
+#[async_trait::async_trait]
+impl SessionStore for RedisSessionStore {
+    #[tracing::instrument(name = "Add session to Redis", skip_all)]
+    async fn add_session(&mut self, session: Session) -> Result<(), SessionStoreError> {
+        let user_key = get_user_key(&session.email);
+        let value = Self::serialize(&session)?;
+        let ttl = Self::ttl()?;
+
+        let mut db = self.client.write().await;
+        let _: () = db
+            .set_ex(get_key(&session.id), value, ttl)
+            .wrap_err("Fail to store session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        // index the session by user so they can be listed and removed together.
+        let _: () = db
+            .sadd(&user_key, &session.id)
+            .wrap_err("Fail to index session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        db.expire(&user_key, ttl as i64)
+            .wrap_err("Fail to set expiry on session index")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Touch session in Redis", skip_all)]
+    async fn touch_session(&mut self, id: &str) -> Result<Session, SessionStoreError> {
+        let ttl = Self::ttl()?;
+        let mut db = self.client.write().await;
+
+        let mut session = Self::load(&mut db, id)?.ok_or(SessionStoreError::SessionNotFound)?;
+        session.last_seen_at = Utc::now().timestamp();
+
+        // only overwrite the session if it wasn't removed in the meantime
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::XX)
+            .with_expiration(SetExpiry::EX(ttl));
+        let _: () = db
+            .set_options(get_key(id), Self::serialize(&session)?, options)
+            .wrap_err("Fail to update session in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = db
+            .expire(get_user_key(&session.email), ttl as i64)
+            .wrap_err("Fail to set expiry on session index")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        Ok(session)
+    }
+
+    #[tracing::instrument(name = "Get sessions from Redis", skip_all)]
+    async fn get_sessions(&self, email: &Email) -> Result<Vec<Session>, SessionStoreError> {
+        let user_key = get_user_key(email);
+        let mut db = self.client.write().await;
+
+        let ids: Vec<String> = db
+            .smembers(&user_key)
+            .wrap_err("Fail to fetch sessions index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            match Self::load(&mut db, &id)? {
+                Some(session) => sessions.push(session),
+                // the session went idle and expired, drop it from the index too
+                None => {
+                    let _: () = db
+                        .srem(&user_key, &id)
+                        .wrap_err("Fail to remove expired session from index")
+                        .map_err(SessionStoreError::UnexpectedError)?;
+                }
+            }
+        }
+        sessions.sort_by_key(|session| session.created_at);
+        Ok(sessions)
+    }
+
+    #[tracing::instrument(name = "Remove session from Redis", skip_all)]
+    async fn remove_session(&mut self, email: &Email, id: &str) -> Result<(), SessionStoreError> {
+        let mut db = self.client.write().await;
+        match Self::load(&mut db, id)? {
+            Some(session) if session.email.eq(email) => {}
+            _ => return Err(SessionStoreError::SessionNotFound),
+        }
+
+        let _: () = db
+            .del(get_key(id))
+            .wrap_err("Fail to delete session from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        db.srem(get_user_key(email), id)
+            .wrap_err("Fail to remove session from index")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove user sessions from Redis", skip_all)]
+    async fn remove_user_sessions(&mut self, email: &Email) -> Result<usize, SessionStoreError> {
+        let user_key = get_user_key(email);
+        let mut db = self.client.write().await;
+
+        let ids: Vec<String> = db
+            .smembers(&user_key)
+            .wrap_err("Fail to fetch sessions index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        for id in &ids {
+            let _: () = db
+                .del(get_key(id))
+                .wrap_err("Fail to delete session from Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+        }
+
+        let _: () = db
+            .del(&user_key)
+            .wrap_err("Fail to delete sessions index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        Ok(ids.len())
+    }
+
+    #[tracing::instrument(name = "Move user sessions in Redis", skip_all)]
+    async fn move_user(
+        &mut self,
+        email: &Email,
+        new_email: &Email,
+    ) -> Result<(), SessionStoreError> {
+        let user_key = get_user_key(email);
+        let new_user_key = get_user_key(new_email);
+        let ttl = Self::ttl()?;
+        let mut db = self.client.write().await;
+
+        let ids: Vec<String> = db
+            .smembers(&user_key)
+            .wrap_err("Fail to fetch sessions index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        for id in &ids {
+            let Some(mut session) = Self::load(&mut db, id)? else {
+                continue;
+            };
+            session.email = new_email.clone();
+            let options = SetOptions::default()
+                .conditional_set(ExistenceCheck::XX)
+                .with_expiration(SetExpiry::KEEPTTL);
+            let _: () = db
+                .set_options(get_key(id), Self::serialize(&session)?, options)
+                .wrap_err("Fail to move session in Redis")
+                .map_err(SessionStoreError::UnexpectedError)?;
+        }
+
+        let _: () = db
+            .sadd(&new_user_key, ids)
+            .wrap_err("Fail to index sessions in Redis")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        let _: () = db
+            .expire(&new_user_key, ttl as i64)
+            .wrap_err("Fail to set expiry on session index")
+            .map_err(SessionStoreError::UnexpectedError)?;
+        db.del(&user_key)
+            .wrap_err("Fail to delete sessions index from Redis")
+            .map_err(SessionStoreError::UnexpectedError)
+    }
+}
@@ -1,30 +1,141 @@
-use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
-use secrecy::ExposeSecret;
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, Report};
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::domain::{
+    auth_method::AuthMethod,
     data_store::{TwoFACodeStore, TwoFACodeStoreError, TwoFARecord},
     email::Email,
     login_attempt_id::LoginAttemptId,
-    two_fa_code::TwoFACode,
+    two_fa_code::{StoredTwoFACode, TwoFACode},
 };
+use crate::utils::constants::{TWO_FACTOR_POLICY, TWO_FA_MAX_PENDING_ATTEMPTS};
 
-const TEN_MINUTE_TTL: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
+const TWO_FA_ATTEMPTS_PREFIX: &str = "two_fa_attempts:";
+const TWO_FA_GUESSES_PREFIX: &str = "two_fa_guesses:";
 
 pub type ARWRedisTwoFaCodeStoreType = Arc<RwLock<Connection>>;
 
-fn get_key(email: &Email) -> String {
-    // TODO: Ask Bogdan about this?
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_key(id: &str) -> String {
+    format!("{}{}", TWO_FA_CODE_PREFIX, id)
 }
 
-// what was the purpose for this?
+// the guesses taken for the login attempt live in their own key, so INCR can count them
+// without rewriting the record
+fn get_guesses_key(id: &str) -> String {
+    format!("{}{}", TWO_FA_GUESSES_PREFIX, id)
+}
+
+// sorted set of the user's pending login attempt IDs, scored by when they expire
+fn get_attempts_key(email: &Email) -> String {
+    format!(
+        "{}{}",
+        TWO_FA_ATTEMPTS_PREFIX,
+        email.as_ref().expose_secret()
+    )
+}
+
+// The record as it is kept in Redis. The guesses are kept apart from it, see `get_guesses_key`.
 #[derive(Serialize, Deserialize)]
-pub struct TwoFaTuple(pub String, pub String);
+struct StoredRecord {
+    id: String,
+    email: String,
+    code: String,
+    amr: Vec<AuthMethod>,
+    resends: u32,
+    last_sent_at: i64,
+    created_at: i64,
+    expires_at: i64,
+    ip: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl From<&TwoFARecord> for StoredRecord {
+    fn from(record: &TwoFARecord) -> Self {
+        Self {
+            id: record.id.as_ref().to_owned(),
+            email: record.email.as_ref().expose_secret().to_owned(),
+            code: record.code.as_stored(),
+            amr: record.amr.clone(),
+            resends: record.resends,
+            last_sent_at: record.last_sent_at,
+            created_at: record.created_at,
+            expires_at: record.expires_at,
+            ip: record.ip.clone(),
+            user_agent: record.user_agent.clone(),
+        }
+    }
+}
+
+impl StoredRecord {
+    fn into_record(self, failed_attempts: u32) -> Result<TwoFARecord, Report> {
+        Ok(TwoFARecord {
+            id: LoginAttemptId::parse(self.id)?,
+            email: Email::parse(Secret::new(self.email))?,
+            code: StoredTwoFACode::parse(self.code)?,
+            amr: self.amr,
+            failed_attempts,
+            resends: self.resends,
+            last_sent_at: self.last_sent_at,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            ip: self.ip,
+            user_agent: self.user_agent,
+        })
+    }
+}
+
+fn serialize_record(record: &TwoFARecord) -> Result<String, TwoFACodeStoreError> {
+    serde_json::to_string(&StoredRecord::from(record))
+        .wrap_err("Fail to serialize 2FA record")
+        .map_err(TwoFACodeStoreError::UnexpectedError)
+}
+
+fn fetch_record(db: &mut Connection, id: &str) -> Result<TwoFARecord, TwoFACodeStoreError> {
+    let (data, guesses): (Option<String>, Option<u32>) = db
+        .mget(&[get_key(id), get_guesses_key(id)])
+        .wrap_err("Fail to fetch 2FA code from Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+    let data = data.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+    serde_json::from_str::<StoredRecord>(&data)
+        .wrap_err("Fail to deserialize 2FA record")
+        .and_then(|stored| stored.into_record(guesses.unwrap_or_default()))
+        .map_err(TwoFACodeStoreError::UnexpectedError)
+}
+
+// IDs in the user's index, oldest first. Ones whose code expired are dropped on the way.
+fn fetch_attempts(db: &mut Connection, email: &Email) -> Result<Vec<String>, TwoFACodeStoreError> {
+    let key = get_attempts_key(email);
+    let _: () = db
+        .zrembyscore(&key, "-inf", Utc::now().timestamp())
+        .wrap_err("Fail to prune 2FA login attempts in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+    db.zrange(&key, 0, -1)
+        .wrap_err("Fail to fetch 2FA login attempts from Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)
+}
+
+// seconds Redis keeps the record, the record's own expiry is what the routes go by
+fn ttl(record: &TwoFARecord) -> u64 {
+    (record.expires_at - Utc::now().timestamp()).max(1) as u64
+}
+
+// puts the attempt in the user's index, the index lives as long as any code could
+fn index_attempt(db: &mut Connection, record: &TwoFARecord) -> Result<(), TwoFACodeStoreError> {
+    let key = get_attempts_key(&record.email);
+    let _: () = db
+        .zadd(&key, record.id.as_ref(), record.expires_at)
+        .wrap_err("Fail to index 2FA login attempt in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+    db.expire(&key, TWO_FACTOR_POLICY.ttl_seconds)
+        .wrap_err("Fail to set TTL of 2FA login attempts in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)
+}
 
 pub struct RedisTwoFaCodeStore {
     client: ARWRedisTwoFaCodeStoreType,
@@ -39,57 +150,167 @@ impl RedisTwoFaCodeStore {
 #[async_trait::async_trait]
 impl TwoFACodeStore for RedisTwoFaCodeStore {
     #[tracing::instrument(name = "Add 2FA code to Redis", skip_all)]
-    async fn add_code(
-        &mut self,
-        email: Email,
-        id: LoginAttemptId,
-        code: TwoFACode,
-    ) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(&email);
-        let instance = TwoFaTuple(
-            id.as_ref().to_owned(),
-            // TODO: Talk to Bogdan about this?
-            code.as_ref().expose_secret().to_string(),
-        );
-        let value = serde_json::to_string(&instance)
-            .wrap_err("Fail to serialize 2FA tuple")
-            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+    async fn add_code(&mut self, record: TwoFARecord) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(record.id.as_ref());
+        let value = serialize_record(&record)?;
 
-        // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
-        // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-        self.client
-            .write()
-            .await
-            .set_ex(key, value, TEN_MINUTE_TTL)
+        let mut db = self.client.write().await;
+        // NX so another login can't take over an existing attempt
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl(&record)));
+        let result: Option<String> = db
+            .set_options(&key, value, options)
             .wrap_err("Fail to set 2FA code in Redis!")
-            .map_err(TwoFACodeStoreError::UnexpectedError)
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        if result.is_none() {
+            return Err(TwoFACodeStoreError::UnexpectedError(eyre!(
+                "Key already exist!"
+            )));
+        }
+        index_attempt(&mut db, &record)?;
+
+        // past the cap the oldest logins are dropped, their codes stop working
+        let attempts = fetch_attempts(&mut db, &record.email)?;
+        if attempts.len() > TWO_FA_MAX_PENDING_ATTEMPTS {
+            let dropped = &attempts[..attempts.len() - TWO_FA_MAX_PENDING_ATTEMPTS];
+            let keys: Vec<String> = dropped
+                .iter()
+                .flat_map(|id| [get_key(id), get_guesses_key(id)])
+                .collect();
+            let _: () = db
+                .del(keys)
+                .wrap_err("Fail to delete 2FA codes from Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+            let _: () = db
+                .zrem(get_attempts_key(&record.email), dropped)
+                .wrap_err("Fail to unindex 2FA login attempts in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        }
+        Ok(())
     }
 
     #[tracing::instrument(name = "Remove 2FA code from Redis", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        let key = get_key(email);
-        let _: () = self
-            .client
-            .write()
-            .await
-            .del(key)
+    async fn remove_code(&mut self, id: &LoginAttemptId) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(id.as_ref());
+        let mut db = self.client.write().await;
+        let record = fetch_record(&mut db, id.as_ref())?;
+        // only one of two requests racing for the same code gets to remove it
+        let removed: u32 = db
+            .del(&key)
             .wrap_err("Fail to delete 2FA from Redis")
             .map_err(TwoFACodeStoreError::UnexpectedError)?;
-        Ok(())
+        if removed == 0 {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+        let _: () = db
+            .del(get_guesses_key(id.as_ref()))
+            .wrap_err("Fail to delete 2FA guesses from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        db.zrem(get_attempts_key(&record.email), id.as_ref())
+            .wrap_err("Fail to unindex 2FA login attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Remove 2FA codes of user from Redis", skip_all)]
+    async fn remove_codes(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
+        let mut db = self.client.write().await;
+        let mut keys: Vec<String> = fetch_attempts(&mut db, email)?
+            .iter()
+            .flat_map(|id| [get_key(id), get_guesses_key(id)])
+            .collect();
+        keys.push(get_attempts_key(email));
+        db.del(keys)
+            .wrap_err("Fail to delete 2FA codes from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)
     }
 
     #[tracing::instrument(name = "Fetch 2FA code from Redis", skip_all)]
-    async fn get_code(&self, email: &Email) -> Result<TwoFARecord, TwoFACodeStoreError> {
-        let key = get_key(email);
-        match self.client.write().await.get::<_, String>(key) {
-            Ok(data) => {
-                let code = serde_json::from_str(&data)
-                    .wrap_err("Fail to deserialize 2FA struct")
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
-                Ok(code)
-            }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+    async fn get_code(&self, id: &LoginAttemptId) -> Result<TwoFARecord, TwoFACodeStoreError> {
+        fetch_record(&mut *self.client.write().await, id.as_ref())
+    }
+
+    #[tracing::instrument(name = "Resend 2FA code in Redis", skip_all)]
+    async fn resend_code(
+        &mut self,
+        id: &LoginAttemptId,
+        code: TwoFACode,
+    ) -> Result<TwoFARecord, TwoFACodeStoreError> {
+        let key = get_key(id.as_ref());
+        let mut db = self.client.write().await;
+        let mut record = fetch_record(&mut db, id.as_ref())?;
+        let now = Utc::now().timestamp();
+        record.code = StoredTwoFACode::hash(&code);
+        record.resends += 1;
+        record.last_sent_at = now;
+        // the fresh code gets the full time to arrive
+        record.expires_at = now + TWO_FACTOR_POLICY.ttl_seconds;
+        let value = serialize_record(&record)?;
+
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::XX)
+            .with_expiration(SetExpiry::EX(ttl(&record)));
+        let result: Option<String> = db
+            .set_options(key, value, options)
+            .wrap_err("Fail to update 2FA code in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        if result.is_none() {
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
+        // guesses so far still count against the fresh code
+        let _: () = db
+            .expire_at(get_guesses_key(id.as_ref()), record.expires_at)
+            .wrap_err("Fail to set TTL of 2FA guesses in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        index_attempt(&mut db, &record)?;
+        Ok(record)
+    }
+
+    #[tracing::instrument(name = "Count 2FA attempt in Redis", skip_all)]
+    async fn count_attempt(&mut self, id: &LoginAttemptId) -> Result<u32, TwoFACodeStoreError> {
+        let mut db = self.client.write().await;
+        let record = fetch_record(&mut db, id.as_ref())?;
+
+        // INCR is atomic across instances, the count goes away along with the code
+        let key = get_guesses_key(id.as_ref());
+        let (attempts,): (u32,) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .expire_at(&key, record.expires_at)
+            .ignore()
+            .query(&mut *db)
+            .wrap_err("Fail to count 2FA attempt in Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        Ok(attempts)
+    }
+
+    #[tracing::instrument(name = "Move 2FA codes in Redis", skip_all)]
+    async fn move_codes(
+        &mut self,
+        email: &Email,
+        new_email: Email,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let mut db = self.client.write().await;
+        for id in fetch_attempts(&mut db, email)? {
+            let key = get_key(&id);
+            let mut record = match fetch_record(&mut db, &id) {
+                Ok(record) => record,
+                Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => continue,
+                Err(e) => return Err(e),
+            };
+            record.email = new_email.clone();
+            let value = serialize_record(&record)?;
+            let options = SetOptions::default()
+                .conditional_set(ExistenceCheck::XX)
+                .with_expiration(SetExpiry::KEEPTTL);
+            let _: Option<String> = db
+                .set_options(&key, value, options)
+                .wrap_err("Fail to move 2FA code in Redis")
+                .map_err(TwoFACodeStoreError::UnexpectedError)?;
+            index_attempt(&mut db, &record)?;
         }
+        db.del(get_attempts_key(email))
+            .wrap_err("Fail to delete 2FA login attempts from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)
     }
 }
@@ -0,0 +1,447 @@
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Context, Report, Result};
+use jsonwebtoken::jwk::JwkSet;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    domain::{
+        data_store::{KeyringEntry, SigningKeyStore, SigningKeyStoreError},
+        signing_key::{KeyState, SigningAlgorithm, SigningKey},
+    },
+    utils::{constants::SIGNING_KEY_ENCRYPTION_KEY, crypto},
+};
+
+#[derive(Debug, Error)]
+pub enum KeyringError {
+    #[error("Signing key not found")]
+    KeyNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for KeyringError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::KeyNotFound, Self::KeyNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// The JWT signing keys, cached from their store.
+// Changes are saved first and only then applied here, so a failed save leaves the keyring as it was.
+pub struct Keyring {
+    store: Box<dyn SigningKeyStore>,
+    entries: Vec<KeyringEntry>,
+}
+
+// how many times a change is made again over a keyring other instances keep changing
+const UPDATE_ATTEMPTS: usize = 3;
+
+impl Keyring {
+    // A store without keys is started with `initial` as the active key and a next key lined up.
+    #[tracing::instrument(name = "Load keyring", skip_all)]
+    pub async fn load(
+        store: Box<dyn SigningKeyStore>,
+        initial: SigningKey,
+    ) -> Result<Self, KeyringError> {
+        let mut keyring = Self {
+            store,
+            entries: Vec::new(),
+        };
+        keyring.reload().await?;
+        if keyring.entries.is_empty() {
+            keyring
+                .update(|entries| {
+                    // another instance started the keyring first
+                    if !entries.is_empty() {
+                        return Ok(entries);
+                    }
+                    let now = Utc::now().timestamp();
+                    let next = SigningKey::generate(initial.signing_algorithm())
+                        .map_err(KeyringError::UnexpectedError)?;
+                    Ok(vec![
+                        KeyringEntry {
+                            key: initial.clone(),
+                            state: KeyState::Active,
+                            created_at: now,
+                        },
+                        KeyringEntry {
+                            key: next,
+                            state: KeyState::Next,
+                            created_at: now,
+                        },
+                    ])
+                })
+                .await?;
+        }
+        Ok(keyring)
+    }
+
+    // Picks up changes made by other instances.
+    pub async fn reload(&mut self) -> Result<(), KeyringError> {
+        self.entries = self.fetch().await?;
+        Ok(())
+    }
+
+    // Reads the stored keyring without touching this one, so it can be done under a read lock
+    // and only swapped in with `replace`.
+    #[tracing::instrument(name = "Fetch keyring", skip_all)]
+    pub async fn fetch(&self) -> Result<Vec<KeyringEntry>, KeyringError> {
+        let entries = self
+            .store
+            .get_keys()
+            .await
+            .map_err(|e| KeyringError::UnexpectedError(e.into()))?;
+        if !entries.is_empty() && !entries.iter().any(|e| e.state == KeyState::Active) {
+            return Err(KeyringError::UnexpectedError(eyre!(
+                "Stored keyring has no active signing key"
+            )));
+        }
+        Ok(entries)
+    }
+
+    pub fn replace(&mut self, entries: Vec<KeyringEntry>) {
+        self.entries = entries;
+    }
+
+    pub fn entries(&self) -> &[KeyringEntry] {
+        &self.entries
+    }
+
+    // The key new tokens are signed with.
+    pub fn active(&self) -> Result<&SigningKey> {
+        self.entries
+            .iter()
+            .find(|entry| entry.state == KeyState::Active)
+            .map(|entry| &entry.key)
+            .ok_or_else(|| eyre!("Keyring has no active signing key"))
+    }
+
+    // A key tokens may still be checked with, by its kid.
+    pub fn find(&self, kid: &str) -> Option<&SigningKey> {
+        self.entries
+            .iter()
+            .find(|entry| entry.state.is_trusted() && entry.key.kid() == kid)
+            .map(|entry| &entry.key)
+    }
+
+    pub fn jwks(&self) -> JwkSet {
+        let keys = self
+            .entries
+            .iter()
+            .filter(|entry| entry.state.is_trusted())
+            .filter_map(|entry| entry.key.jwk().cloned())
+            .collect();
+        JwkSet { keys }
+    }
+
+    // next -> active -> retiring -> retired, and a new next key is generated.
+    // Without an algorithm, the new key uses the same one as the active key.
+    #[tracing::instrument(name = "Rotate signing keys", skip_all)]
+    pub async fn rotate(
+        &mut self,
+        algorithm: Option<SigningAlgorithm>,
+    ) -> Result<(), KeyringError> {
+        self.update(|mut entries| {
+            let algorithm = match algorithm {
+                Some(algorithm) => algorithm,
+                None => entries
+                    .iter()
+                    .find(|entry| entry.state == KeyState::Active)
+                    .map(|entry| entry.key.signing_algorithm())
+                    .ok_or_else(|| {
+                        KeyringError::UnexpectedError(eyre!("Keyring has no active signing key"))
+                    })?,
+            };
+
+            let mut promoted = false;
+            for entry in entries.iter_mut() {
+                entry.state = match entry.state {
+                    KeyState::Next if !promoted => {
+                        promoted = true;
+                        KeyState::Active
+                    }
+                    KeyState::Next => KeyState::Next,
+                    KeyState::Active => KeyState::Retiring,
+                    KeyState::Retiring | KeyState::Retired => KeyState::Retired,
+                };
+            }
+            if !promoted {
+                entries.push(generate_entry(algorithm, KeyState::Active)?);
+            }
+            entries.push(generate_entry(algorithm, KeyState::Next)?);
+            Ok(entries)
+        })
+        .await
+    }
+
+    // Stops trusting a key right away, e.g. after it leaked. Retiring the active key promotes the next one.
+    #[tracing::instrument(name = "Retire signing key", skip_all)]
+    pub async fn retire(&mut self, kid: &str) -> Result<(), KeyringError> {
+        self.update(|mut entries| {
+            let entry = entries
+                .iter_mut()
+                .find(|entry| entry.state.is_trusted() && entry.key.kid() == kid)
+                .ok_or(KeyringError::KeyNotFound)?;
+            let was_active = entry.state == KeyState::Active;
+            let algorithm = entry.key.signing_algorithm();
+            entry.state = KeyState::Retired;
+
+            if was_active {
+                match entries
+                    .iter_mut()
+                    .find(|entry| entry.state == KeyState::Next)
+                {
+                    Some(next) => next.state = KeyState::Active,
+                    None => entries.push(generate_entry(algorithm, KeyState::Active)?),
+                }
+            }
+            if !entries.iter().any(|entry| entry.state == KeyState::Next) {
+                entries.push(generate_entry(algorithm, KeyState::Next)?);
+            }
+            Ok(entries)
+        })
+        .await
+    }
+
+    // Makes `change` to the stored keyring. The cache may be behind what other instances saved,
+    // then the store turns the save down and `change` is made again to the keyring reloaded.
+    async fn update<F>(&mut self, change: F) -> Result<(), KeyringError>
+    where
+        F: Fn(Vec<KeyringEntry>) -> Result<Vec<KeyringEntry>, KeyringError>,
+    {
+        for _ in 0..UPDATE_ATTEMPTS {
+            let entries = change(self.entries.clone())?;
+            match self.store.save_keys(&self.entries, &entries).await {
+                Ok(()) => {
+                    self.entries = entries;
+                    return Ok(());
+                }
+                Err(SigningKeyStoreError::Conflict) => self.reload().await?,
+                Err(e) => return Err(KeyringError::UnexpectedError(e.into())),
+            }
+        }
+        Err(KeyringError::UnexpectedError(eyre!(
+            "Keyring kept changing while saving"
+        )))
+    }
+}
+
+fn generate_entry(
+    algorithm: SigningAlgorithm,
+    state: KeyState,
+) -> Result<KeyringEntry, KeyringError> {
+    let key = SigningKey::generate(algorithm).map_err(KeyringError::UnexpectedError)?;
+    Ok(KeyringEntry {
+        key,
+        state,
+        created_at: Utc::now().timestamp(),
+    })
+}
+
+// A keyring entry as the stores keep it, with the private key encrypted.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    pub algorithm: String,
+    pub private_key: String,
+    pub state: String,
+    pub created_at: i64,
+}
+
+impl StoredSigningKey {
+    pub fn seal(entry: &KeyringEntry) -> Result<Self> {
+        let private_key = crypto::encrypt(
+            &SIGNING_KEY_ENCRYPTION_KEY,
+            entry.key.material().expose_secret().as_bytes(),
+        )
+        .wrap_err("Fail to encrypt signing key")?;
+        Ok(Self {
+            kid: entry.key.kid().to_owned(),
+            algorithm: entry.key.signing_algorithm().to_string(),
+            private_key,
+            state: entry.state.to_string(),
+            created_at: entry.created_at,
+        })
+    }
+
+    pub fn open(self) -> Result<KeyringEntry> {
+        let material = crypto::decrypt(&SIGNING_KEY_ENCRYPTION_KEY, &self.private_key)
+            .wrap_err_with(|| format!("Fail to decrypt signing key {}", self.kid))?;
+        let material =
+            String::from_utf8(material).wrap_err("Decrypted signing key is not UTF-8")?;
+        let key =
+            SigningKey::from_material(self.algorithm.parse()?, &Secret::new(material), self.kid)?;
+        Ok(KeyringEntry {
+            key,
+            state: self.state.parse()?,
+            created_at: self.created_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::data_stores::{
+        file_signing_key_store::FileSigningKeyStore,
+        hashmap_signing_key_store::HashmapSigningKeyStore,
+    };
+
+    fn initial() -> SigningKey {
+        SigningKey::from_secret(&Secret::new("secret".to_owned()), None)
+    }
+
+    async fn keyring() -> Keyring {
+        Keyring::load(Box::new(HashmapSigningKeyStore::default()), initial())
+            .await
+            .unwrap()
+    }
+
+    fn state_of(keyring: &Keyring, kid: &str) -> KeyState {
+        keyring
+            .entries()
+            .iter()
+            .find(|entry| entry.key.kid() == kid)
+            .unwrap()
+            .state
+    }
+
+    fn next_kid(keyring: &Keyring) -> String {
+        keyring
+            .entries()
+            .iter()
+            .find(|entry| entry.state == KeyState::Next)
+            .unwrap()
+            .key
+            .kid()
+            .to_owned()
+    }
+
+    #[tokio::test]
+    async fn new_keyring_should_start_with_initial_key() {
+        let keyring = keyring().await;
+        assert_eq!(keyring.active().unwrap().kid(), initial().kid());
+        assert_eq!(keyring.entries().len(), 2);
+        assert!(keyring.find(&next_kid(&keyring)).is_some());
+    }
+
+    #[tokio::test]
+    async fn stored_keyring_should_be_kept() {
+        let mut store = HashmapSigningKeyStore::default();
+        let key = SigningKey::generate(SigningAlgorithm::ES256).unwrap();
+        let entry = KeyringEntry {
+            key: key.clone(),
+            state: KeyState::Active,
+            created_at: 0,
+        };
+        store.save_keys(&[], &[entry]).await.unwrap();
+
+        let keyring = Keyring::load(Box::new(store), initial()).await.unwrap();
+        assert_eq!(keyring.active().unwrap().kid(), key.kid());
+        assert!(keyring.find(initial().kid()).is_none());
+    }
+
+    #[tokio::test]
+    async fn rotate_should_move_every_key_one_state_along() {
+        let mut keyring = keyring().await;
+        let first = initial().kid().to_owned();
+        let second = next_kid(&keyring);
+
+        keyring.rotate(None).await.unwrap();
+        assert_eq!(keyring.active().unwrap().kid(), second);
+        assert_eq!(state_of(&keyring, &first), KeyState::Retiring);
+        let third = next_kid(&keyring);
+        // tokens signed before the rotation keep working
+        assert!(keyring.find(&first).is_some());
+
+        keyring.rotate(None).await.unwrap();
+        assert_eq!(keyring.active().unwrap().kid(), third);
+        assert_eq!(state_of(&keyring, &second), KeyState::Retiring);
+        assert_eq!(state_of(&keyring, &first), KeyState::Retired);
+        assert!(keyring.find(&first).is_none());
+    }
+
+    #[tokio::test]
+    async fn rotate_should_use_requested_algorithm_for_new_key() {
+        let mut keyring = keyring().await;
+        keyring.rotate(Some(SigningAlgorithm::EdDSA)).await.unwrap();
+
+        let next = keyring.find(&next_kid(&keyring)).unwrap();
+        assert_eq!(next.signing_algorithm(), SigningAlgorithm::EdDSA);
+        assert_eq!(keyring.jwks().keys.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retire_active_key_should_promote_next_key() {
+        let mut keyring = keyring().await;
+        let first = initial().kid().to_owned();
+        let second = next_kid(&keyring);
+
+        keyring.retire(&first).await.unwrap();
+        assert_eq!(state_of(&keyring, &first), KeyState::Retired);
+        assert_eq!(keyring.active().unwrap().kid(), second);
+        assert_ne!(next_kid(&keyring), second);
+    }
+
+    #[tokio::test]
+    async fn retire_unknown_key_should_fail() {
+        let mut keyring = keyring().await;
+        assert_eq!(
+            keyring.retire("unknown").await,
+            Err(KeyringError::KeyNotFound)
+        );
+
+        keyring.retire(initial().kid()).await.unwrap();
+        assert_eq!(
+            keyring.retire(initial().kid()).await,
+            Err(KeyringError::KeyNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn stale_keyring_should_not_undo_changes_saved_by_another() {
+        let path = std::env::temp_dir().join(format!("keyring-{}.json", uuid::Uuid::new_v4()));
+        let mut first = Keyring::load(Box::new(FileSigningKeyStore::new(&path)), initial())
+            .await
+            .unwrap();
+        let mut stale = Keyring::load(Box::new(FileSigningKeyStore::new(&path)), initial())
+            .await
+            .unwrap();
+        let leaked = initial().kid().to_owned();
+
+        first.retire(&leaked).await.unwrap();
+        stale.rotate(None).await.unwrap();
+
+        // the rotation was made again on top of the retired key
+        assert_eq!(state_of(&stale, &leaked), KeyState::Retired);
+        assert!(stale.find(&leaked).is_none());
+        first.reload().await.unwrap();
+        assert!(first.find(&leaked).is_none());
+        assert_eq!(first.active().unwrap().kid(), stale.active().unwrap().kid());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn stored_key_should_round_trip() {
+        let entry = KeyringEntry {
+            key: SigningKey::generate(SigningAlgorithm::ES256).unwrap(),
+            state: KeyState::Retiring,
+            created_at: 42,
+        };
+        let stored = StoredSigningKey::seal(&entry).unwrap();
+        assert!(!stored
+            .private_key
+            .contains(entry.key.material().expose_secret().as_str()));
+
+        let opened = stored.open().unwrap();
+        assert_eq!(opened.key.kid(), entry.key.kid());
+        assert_eq!(opened.key.jwk(), entry.key.jwk());
+        assert_eq!(opened.state, KeyState::Retiring);
+        assert_eq!(opened.created_at, 42);
+    }
+}
@@ -1,3 +1,5 @@
 pub mod data_stores;
+pub mod keyring;
 pub mod mock_email_client;
 pub mod postmark_email_client;
+pub mod token_version_cache;
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::domain::email::Email;
+
+// Token versions of recently seen users, so checking a token doesn't hit the user store every time.
+// A bump on this instance updates the cache right away, other instances pick it up once their entry goes stale.
+#[derive(Debug, Clone)]
+pub struct TokenVersionCache {
+    ttl: Duration,
+    entries: HashMap<Email, (i32, Instant)>,
+}
+
+impl TokenVersionCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, email: &Email) -> Option<i32> {
+        self.entries
+            .get(email)
+            .filter(|(_, cached_at)| cached_at.elapsed() < self.ttl)
+            .map(|(version, _)| *version)
+    }
+
+    pub fn insert(&mut self, email: Email, version: i32) {
+        // stale entries are only dropped here, the cache never grows past the users seen within one ttl
+        let ttl = self.ttl;
+        self.entries
+            .retain(|_, (_, cached_at)| cached_at.elapsed() < ttl);
+        self.entries.insert(email, (version, Instant::now()));
+    }
+
+    pub fn remove(&mut self, email: &Email) {
+        self.entries.remove(email);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secrecy::Secret;
+
+    fn email() -> Email {
+        Email::parse(Secret::new("test@test.com".to_owned())).unwrap()
+    }
+
+    #[test]
+    fn cached_version_should_be_returned() {
+        let mut cache = TokenVersionCache::new(Duration::from_secs(60));
+        assert_eq!(cache.get(&email()), None);
+
+        cache.insert(email(), 3);
+        assert_eq!(cache.get(&email()), Some(3));
+
+        cache.insert(email(), 4);
+        assert_eq!(cache.get(&email()), Some(4));
+
+        cache.remove(&email());
+        assert_eq!(cache.get(&email()), None);
+    }
+
+    #[test]
+    fn stale_version_should_be_ignored() {
+        let mut cache = TokenVersionCache::new(Duration::ZERO);
+        cache.insert(email(), 3);
+        assert_eq!(cache.get(&email()), None);
+    }
+}
@@ -1,27 +1,66 @@
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET, TOKEN_TTL_SECONDS};
-use crate::domain::email::Email;
+use super::constants::{
+    JWT_AUDIENCE, JWT_COOKIE_NAME, JWT_ISSUER, JWT_SECRET, MAGIC_LINK_TTL_SECONDS,
+    REFRESH_TOKEN_COOKIE_NAME, REFRESH_TOKEN_TTL_SECONDS, TOKEN_TTL_SECONDS,
+};
+use crate::domain::{
+    auth_method::AuthMethod,
+    data_store::{RefreshTokenRecord, RefreshTokenStore},
+    email::Email,
+    refresh_token::RefreshToken,
+    user::UserRole,
+};
+use crate::services::keyring::Keyring;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use chrono::Duration;
 use chrono::Utc;
 use color_eyre::eyre::{Context, ContextCompat, Result};
 use jsonwebtoken::{
-    decode, encode, errors::Error as JWTError, DecodingKey, EncodingKey, Validation,
+    decode, decode_header, encode,
+    errors::{Error as JWTError, ErrorKind},
+    DecodingKey, EncodingKey, Validation,
 };
 use secrecy::ExposeSecret;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // Task 4 requires me to update auth's claim to use secret, but encode needs to be able to serialize this input??
+    pub jti: String, // banned tokens are looked up by it
+    pub sid: String, // the server-side session, the token stops working once it is removed
+    pub iat: usize,
+    pub nbf: usize,
+    pub exp: usize,
+    pub iss: String,
+    pub aud: Vec<String>,
+    pub amr: Vec<AuthMethod>,
+    // the user's token version at issuance, tokens issued before a logout everywhere carry an older one
+    #[serde(default)]
+    pub ver: i32,
+    // the user's role at issuance, tokens from before roles existed have none
+    #[serde(default)]
+    pub role: UserRole,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkClaims {
+    pub sub: String,
+    pub jti: String, // marked as used in the magic link store so the link works once
     pub exp: usize,
 }
 
+// Magic links are signed with their own key, so a link can't be passed off as a JWT cookie or the other way around.
+fn magic_link_secret() -> Vec<u8> {
+    format!("magic-link:{}", JWT_SECRET.expose_secret()).into_bytes()
+}
+
 #[tracing::instrument(name = "Create new Json Web Token", skip_all)]
-fn create_token(claim: Claims) -> Result<String> {
+fn create_token(keyring: &Keyring, claim: Claims) -> Result<String> {
+    let key = keyring.active()?;
     encode(
-        &jsonwebtoken::Header::default(),
+        &key.header(),
         &claim, // I need to be able to serialize the claim???
-        &EncodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        key.encoding_key(),
     )
     .wrap_err("Fail to generate Json Web Token")
 }
@@ -36,10 +75,18 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
 }
 
 #[tracing::instrument(name = "Generate new Json Web Token", skip_all)]
-pub fn generate_auth_token(email: &Email) -> Result<String> {
+pub fn generate_auth_token(
+    keyring: &Keyring,
+    email: &Email,
+    session_id: &str,
+    token_version: i32,
+    role: UserRole,
+    amr: &[AuthMethod],
+) -> Result<String> {
     let delta =
         Duration::try_seconds(TOKEN_TTL_SECONDS).wrap_err("Fail to create 10 minute time delta")?;
-    let exp = Utc::now()
+    let now = Utc::now();
+    let exp = now
         .checked_add_signed(delta)
         .wrap_err("Date is out of range")?
         .timestamp();
@@ -47,39 +94,198 @@ pub fn generate_auth_token(email: &Email) -> Result<String> {
     let exp: usize = exp
         .try_into()
         .wrap_err("Unable to convert expiration type")?;
+    let iat: usize = now
+        .timestamp()
+        .try_into()
+        .wrap_err("Unable to convert issued at type")?;
 
     let sub = email.as_ref().expose_secret().to_owned();
-    let claims = Claims { sub, exp };
-    create_token(claims)
+    let claims = Claims {
+        sub,
+        jti: Uuid::new_v4().to_string(),
+        sid: session_id.to_owned(),
+        iat,
+        nbf: iat,
+        exp,
+        iss: JWT_ISSUER.to_owned(),
+        aud: JWT_AUDIENCE.clone(),
+        amr: amr.to_vec(),
+        ver: token_version,
+        role,
+    };
+    create_token(keyring, claims)
 }
 
 #[tracing::instrument(name = "Generate authentication cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    keyring: &Keyring,
+    email: &Email,
+    session_id: &str,
+    token_version: i32,
+    role: UserRole,
+    amr: &[AuthMethod],
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(keyring, email, session_id, token_version, role, amr)?;
     Ok(create_auth_cookie(token))
 }
 
-#[tracing::instrument(name = "Validate Json Web Token", skip_all)]
-pub async fn validate_token(token: &str) -> Result<Claims, JWTError> {
-    decode::<Claims>(
+#[tracing::instrument(name = "Create refresh token cookie", skip_all)]
+fn create_refresh_cookie(token: &RefreshToken) -> Cookie<'static> {
+    Cookie::build((
+        REFRESH_TOKEN_COOKIE_NAME,
+        token.as_ref().expose_secret().to_owned(),
+    ))
+    .path("/")
+    .http_only(true)
+    .same_site(SameSite::Lax)
+    // lasts as long as the token, rather than ending with the browser session
+    .max_age(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+    .build()
+}
+
+// Issue a new refresh token and save it to the store.
+// The family id is the id of the session the token belongs to, rotating a token keeps it.
+#[tracing::instrument(name = "Generate refresh token cookie", skip_all)]
+pub async fn generate_refresh_cookie(
+    store: &mut dyn RefreshTokenStore,
+    email: &Email,
+    amr: &[AuthMethod],
+    family_id: &str,
+) -> Result<Cookie<'static>> {
+    let token = RefreshToken::default();
+    let record = RefreshTokenRecord {
+        email: email.clone(),
+        family_id: family_id.to_owned(),
+        amr: amr.to_vec(),
+    };
+    store
+        .add_token(&token, record)
+        .await
+        .wrap_err("Fail to store refresh token")?;
+    Ok(create_refresh_cookie(&token))
+}
+
+#[tracing::instrument(name = "Generate magic link token", skip_all)]
+pub fn generate_magic_link_token(email: &Email) -> Result<String> {
+    let delta = Duration::try_seconds(MAGIC_LINK_TTL_SECONDS)
+        .wrap_err("Fail to create magic link time delta")?;
+    let exp: usize = Utc::now()
+        .checked_add_signed(delta)
+        .wrap_err("Date is out of range")?
+        .timestamp()
+        .try_into()
+        .wrap_err("Unable to convert expiration type")?;
+
+    let claims = MagicLinkClaims {
+        sub: email.as_ref().expose_secret().to_owned(),
+        jti: Uuid::new_v4().to_string(),
+        exp,
+    };
+    encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&magic_link_secret()),
+    )
+    .wrap_err("Fail to generate magic link token")
+}
+
+#[tracing::instrument(name = "Validate magic link token", skip_all)]
+pub fn validate_magic_link_token(token: &str) -> Result<MagicLinkClaims, JWTError> {
+    // no leeway, the magic link store only remembers a used link until its exp
+    let mut validation = Validation::default();
+    validation.leeway = 0;
+    decode::<MagicLinkClaims>(
         token,
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+        &DecodingKey::from_secret(&magic_link_secret()),
+        &validation,
     )
     .map(|data| data.claims)
 }
 
+// Validates a token presented to the auth service itself.
+#[tracing::instrument(name = "Validate Json Web Token", skip_all)]
+pub async fn validate_token(keyring: &Keyring, token: &str) -> Result<Claims, JWTError> {
+    validate_token_for_audience(keyring, token, &JWT_ISSUER).await
+}
+
+// Validates a token on behalf of another service, which has to be listed in the token's aud.
+#[tracing::instrument(name = "Validate Json Web Token for audience", skip(keyring, token))]
+pub async fn validate_token_for_audience(
+    keyring: &Keyring,
+    token: &str,
+    audience: &str,
+) -> Result<Claims, JWTError> {
+    // the kid has to name a key that is still trusted, and only that key's algorithm is accepted
+    let header = decode_header(token)?;
+    let key = header
+        .kid
+        .as_deref()
+        .and_then(|kid| keyring.find(kid))
+        .ok_or(JWTError::from(ErrorKind::InvalidToken))?;
+
+    let mut validation = Validation::new(key.algorithm());
+    validation.set_required_spec_claims(&["exp", "nbf", "iss", "aud", "sub"]);
+    validation.validate_nbf = true;
+    // no leeway, a banned token is only remembered until its exp
+    validation.leeway = 0;
+    validation.set_issuer(&[&*JWT_ISSUER]);
+    validation.set_audience(&[audience]);
+
+    decode::<Claims>(token, key.decoding_key(), &validation).map(|data| data.claims)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::domain::signing_key::SigningKey;
+    use crate::services::data_stores::{
+        hashmap_refresh_token_store::HashmapRefreshTokenStore,
+        hashmap_signing_key_store::HashmapSigningKeyStore,
+    };
     use secrecy::Secret;
 
+    fn claims() -> Claims {
+        Claims {
+            sub: "test@test.com".to_owned(),
+            jti: Uuid::new_v4().to_string(),
+            sid: Uuid::new_v4().to_string(),
+            iat: 0,
+            nbf: 0,
+            exp: 10_000_000_000,
+            iss: JWT_ISSUER.to_owned(),
+            aud: JWT_AUDIENCE.clone(),
+            amr: vec![AuthMethod::Password],
+            ver: 0,
+            role: UserRole::Member,
+        }
+    }
+
+    fn sign(keyring: &Keyring, claims: &Claims) -> String {
+        let active = keyring.active().unwrap();
+        encode(&active.header(), claims, active.encoding_key()).unwrap()
+    }
+
+    async fn keyring() -> Keyring {
+        let initial = SigningKey::from_secret(&Secret::new("secret".to_owned()), None);
+        Keyring::load(Box::new(HashmapSigningKeyStore::default()), initial)
+            .await
+            .unwrap()
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let input = "test@test.com".to_owned();
         let secret = Secret::new(input);
         let email = Email::parse(secret).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(
+            &keyring().await,
+            &email,
+            "session",
+            0,
+            UserRole::Member,
+            &[AuthMethod::Password],
+        )
+        .unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -98,13 +304,44 @@ mod tests {
         assert_eq!(cookie.same_site(), Some(SameSite::Lax));
     }
 
+    #[tokio::test]
+    async fn test_generate_refresh_cookie() {
+        let mut store = HashmapRefreshTokenStore::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let cookie =
+            generate_refresh_cookie(&mut store, &email, &[AuthMethod::Password], "session")
+                .await
+                .unwrap();
+        assert_eq!(cookie.name(), REFRESH_TOKEN_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(
+            cookie.max_age(),
+            Some(time::Duration::seconds(REFRESH_TOKEN_TTL_SECONDS))
+        );
+
+        let token = RefreshToken::parse(Secret::new(cookie.value().to_owned())).unwrap();
+        let record = store.consume_token(&token).await.unwrap();
+        assert_eq!(record.email, email);
+        assert_eq!(record.amr, vec![AuthMethod::Password]);
+        assert_eq!(record.family_id, "session");
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let account = "test@test.com".to_owned();
         let secret = Secret::new(account.clone());
         let email = Email::parse(secret).unwrap();
-        let token = generate_auth_token(&email).unwrap();
-        let result = validate_token(&token).await;
+        let keyring = keyring().await;
+        let token = generate_auth_token(
+            &keyring,
+            &email,
+            "session",
+            0,
+            UserRole::Member,
+            &[AuthMethod::Password],
+        )
+        .unwrap();
+        let result = validate_token(&keyring, &token).await;
 
         assert!(result.is_ok());
 
@@ -119,10 +356,183 @@ mod tests {
         assert!(result.exp > exp)
     }
 
+    #[tokio::test]
+    async fn auth_token_should_carry_standard_claims() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let keyring = keyring().await;
+        let amr = AuthMethod::with_second_factor(&[AuthMethod::Password]);
+        let token =
+            generate_auth_token(&keyring, &email, "session", 0, UserRole::Member, &amr).unwrap();
+        let claims = validate_token(&keyring, &token).await.unwrap();
+
+        assert!(Uuid::parse_str(&claims.jti).is_ok());
+        assert_eq!(claims.sid, "session");
+        assert_eq!(claims.nbf, claims.iat);
+        assert!(claims.exp > claims.iat);
+        assert_eq!(claims.iss, *JWT_ISSUER);
+        assert!(claims.aud.contains(&JWT_ISSUER));
+        assert_eq!(claims.amr, amr);
+
+        // every token gets its own id
+        let other =
+            generate_auth_token(&keyring, &email, "session", 0, UserRole::Member, &amr).unwrap();
+        let other = validate_token(&keyring, &other).await.unwrap();
+        assert_ne!(claims.jti, other.jti);
+    }
+
+    #[tokio::test]
+    async fn token_from_other_issuer_should_fail() {
+        let keyring = keyring().await;
+        let mut claims = claims();
+        claims.iss = "someone-else".to_owned();
+        assert!(validate_token(&keyring, &sign(&keyring, &claims))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn token_should_only_be_valid_for_its_audience() {
+        let keyring = keyring().await;
+        let mut claims = claims();
+        claims.aud = vec!["app-service".to_owned()];
+        let token = sign(&keyring, &claims);
+
+        assert!(validate_token_for_audience(&keyring, &token, "app-service")
+            .await
+            .is_ok());
+        assert!(validate_token_for_audience(&keyring, &token, "billing")
+            .await
+            .is_err());
+        // not meant for the auth service itself
+        assert!(validate_token(&keyring, &token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn token_used_before_nbf_should_fail() {
+        let keyring = keyring().await;
+        let mut claims = claims();
+        claims.nbf = (Utc::now().timestamp() + 3600) as usize;
+        assert!(validate_token(&keyring, &sign(&keyring, &claims))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn token_used_after_exp_should_fail() {
+        // banned tokens are only remembered until their exp, there must be no leeway past it
+        let keyring = keyring().await;
+        let mut claims = claims();
+        claims.exp = (Utc::now().timestamp() - 1) as usize;
+        assert!(validate_token(&keyring, &sign(&keyring, &claims))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_magic_link_token() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let token = generate_magic_link_token(&email).unwrap();
+        let claims = validate_magic_link_token(&token).unwrap();
+        assert_eq!(claims.sub, "test@test.com");
+        assert!(Uuid::parse_str(&claims.jti).is_ok());
+
+        // every link gets its own id
+        let other = validate_magic_link_token(&generate_magic_link_token(&email).unwrap()).unwrap();
+        assert_ne!(claims.jti, other.jti);
+    }
+
+    #[tokio::test]
+    async fn expired_magic_link_should_fail() {
+        let claims = MagicLinkClaims {
+            sub: "test@test.com".to_owned(),
+            jti: Uuid::new_v4().to_string(),
+            exp: (Utc::now().timestamp() - 1) as usize,
+        };
+        let token = encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &EncodingKey::from_secret(&magic_link_secret()),
+        )
+        .unwrap();
+        assert!(validate_magic_link_token(&token).is_err());
+    }
+
+    #[tokio::test]
+    async fn magic_link_and_auth_tokens_should_not_be_interchangeable() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let keyring = keyring().await;
+        let magic_link = generate_magic_link_token(&email).unwrap();
+        assert!(validate_token(&keyring, &magic_link).await.is_err());
+
+        let auth_token = generate_auth_token(
+            &keyring,
+            &email,
+            "session",
+            0,
+            UserRole::Member,
+            &[AuthMethod::Password],
+        )
+        .unwrap();
+        assert!(validate_magic_link_token(&auth_token).is_err());
+    }
+
+    #[tokio::test]
+    async fn auth_token_should_name_signing_key() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let keyring = keyring().await;
+        let token = generate_auth_token(
+            &keyring,
+            &email,
+            "session",
+            0,
+            UserRole::Member,
+            &[AuthMethod::Password],
+        )
+        .unwrap();
+        let header = decode_header(&token).unwrap();
+        let active = keyring.active().unwrap();
+        assert_eq!(header.kid.as_deref(), Some(active.kid()));
+        assert_eq!(header.alg, active.algorithm());
+    }
+
+    #[tokio::test]
+    async fn token_with_unknown_kid_should_fail() {
+        let claims = claims();
+        let keyring = keyring().await;
+        let active = keyring.active().unwrap();
+        let mut header = active.header();
+        header.kid = Some("unknown".to_owned());
+        let token = encode(&header, &claims, active.encoding_key()).unwrap();
+        assert!(validate_token(&keyring, &token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn token_should_stay_valid_until_its_key_is_retired() {
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let mut keyring = keyring().await;
+        let token = generate_auth_token(
+            &keyring,
+            &email,
+            "session",
+            0,
+            UserRole::Member,
+            &[AuthMethod::Password],
+        )
+        .unwrap();
+        let kid = keyring.active().unwrap().kid().to_owned();
+
+        keyring.rotate(None).await.unwrap();
+        assert_ne!(keyring.active().unwrap().kid(), kid);
+        assert!(validate_token(&keyring, &token).await.is_ok());
+
+        keyring.retire(&kid).await.unwrap();
+        assert!(validate_token(&keyring, &token).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = "invalid_token";
-        let result = validate_token(token).await;
+        let result = validate_token(&keyring().await, token).await;
         assert!(result.is_err());
     }
 }
@@ -0,0 +1,79 @@
+use std::marker::PhantomData;
+
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use axum_extra::extract::CookieJar;
+
+use crate::app_state::AppState;
+use crate::domain::{error::AuthAPIError, user::UserRole};
+use crate::routes::jwt::authenticated_claims;
+use crate::utils::auth::Claims;
+
+// The least role a route accepts, see the markers in `role`.
+pub trait RequiredRole {
+    const ROLE: UserRole;
+}
+
+pub mod role {
+    use super::{RequiredRole, UserRole};
+
+    pub struct Guest;
+    pub struct Member;
+    pub struct Admin;
+
+    impl RequiredRole for Guest {
+        const ROLE: UserRole = UserRole::Guest;
+    }
+
+    impl RequiredRole for Member {
+        const ROLE: UserRole = UserRole::Member;
+    }
+
+    impl RequiredRole for Admin {
+        const ROLE: UserRole = UserRole::Admin;
+    }
+}
+
+// Extracts the claims of an authenticated request, and rejects it with 403 when the token's role is below R.
+// e.g. `Authorized<role::Admin>` as a handler argument only lets admins in.
+pub struct Authorized<R: RequiredRole> {
+    pub claims: Claims,
+    role: PhantomData<R>,
+}
+
+#[async_trait]
+impl<R: RequiredRole> FromRequestParts<AppState> for Authorized<R> {
+    type Rejection = AuthAPIError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let jar = CookieJar::from_headers(&parts.headers);
+        let claims = authenticated_claims(state, &jar).await?;
+        if !has_role(claims.role, R::ROLE) {
+            return Err(AuthAPIError::Forbidden);
+        }
+        Ok(Self {
+            claims,
+            role: PhantomData,
+        })
+    }
+}
+
+pub fn has_role(role: UserRole, required: UserRole) -> bool {
+    role >= required
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn higher_roles_should_be_let_in() {
+        assert!(has_role(UserRole::Admin, role::Member::ROLE));
+        assert!(has_role(UserRole::Member, role::Member::ROLE));
+        assert!(!has_role(UserRole::Guest, role::Member::ROLE));
+        assert!(!has_role(UserRole::None, role::Guest::ROLE));
+        assert!(!has_role(UserRole::Member, role::Admin::ROLE));
+    }
+}
@@ -0,0 +1,109 @@
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{header::USER_AGENT, request::Parts, HeaderMap, HeaderName},
+};
+
+use crate::utils::constants::TRUSTED_PROXY_HOPS;
+
+// user agents are only shown back to the user, there's no point in keeping a huge one
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+// Who is on the other end of the request, recorded with the sessions they start.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientInfo {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip = client_ip(peer, &parts.headers, *TRUSTED_PROXY_HOPS).map(|ip| ip.to_string());
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.chars().take(MAX_USER_AGENT_LENGTH).collect());
+        Ok(Self { ip, user_agent })
+    }
+}
+
+// Behind `hops` proxies the peer is the last proxy, and the client is the address the first
+// proxy appended to X-Forwarded-For, `hops` entries from the right. Entries further left come
+// from the client and can't be trusted. Requests that didn't pass every proxy keep the peer.
+fn client_ip(peer: Option<IpAddr>, headers: &HeaderMap, hops: usize) -> Option<IpAddr> {
+    if hops == 0 {
+        return peer;
+    }
+    let forwarded: Vec<&str> = headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+    forwarded
+        .len()
+        .checked_sub(hops)
+        .and_then(|index| forwarded[index].parse().ok())
+        .or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn peer() -> Option<IpAddr> {
+        Some("10.0.0.2".parse().unwrap())
+    }
+
+    fn forwarded_for(values: &[&'static str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append(X_FORWARDED_FOR, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn without_proxies_forwarded_for_should_be_ignored() {
+        let headers = forwarded_for(&["203.0.113.7"]);
+        assert_eq!(client_ip(peer(), &headers, 0), peer());
+    }
+
+    #[test]
+    fn client_should_be_taken_hops_from_the_right() {
+        // the client made up the first entry, the proxies appended the other two
+        let headers = forwarded_for(&["198.51.100.1, 203.0.113.7", "10.0.0.1"]);
+        assert_eq!(
+            client_ip(peer(), &headers, 2),
+            Some("203.0.113.7".parse().unwrap())
+        );
+        assert_eq!(
+            client_ip(peer(), &headers, 1),
+            Some("10.0.0.1".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn request_that_skipped_the_proxies_should_keep_the_peer() {
+        assert_eq!(client_ip(peer(), &HeaderMap::new(), 1), peer());
+        let headers = forwarded_for(&["203.0.113.7"]);
+        assert_eq!(client_ip(peer(), &headers, 2), peer());
+        let headers = forwarded_for(&["not an address"]);
+        assert_eq!(client_ip(peer(), &headers, 1), peer());
+    }
+}
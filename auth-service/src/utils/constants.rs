@@ -1,6 +1,10 @@
+use crate::domain::{
+    account_lockout::LockoutPolicy, rate_limit::RateLimitConfig, signing_key::SigningKey,
+    two_factor_policy::TwoFactorPolicy, user::UnverifiedLoginPolicy,
+};
 use dotenvy::dotenv;
 use secrecy::Secret;
-use std::{env as std_env, sync::LazyLock};
+use std::{env as std_env, fs as std_fs, sync::LazyLock};
 
 pub static JWT_SECRET: LazyLock<Secret<String>> = LazyLock::new(|| {
     dotenv().ok();
@@ -11,6 +15,90 @@ pub static JWT_SECRET: LazyLock<Secret<String>> = LazyLock::new(|| {
     Secret::new(secret)
 });
 
+// The first active key of a new keyring, once the keyring is stored it is ignored.
+// Without JWT_SIGNING_KEY_PATH, tokens are signed with JWT_SECRET (HS256), which is never published in the JWKS.
+pub static JWT_SIGNING_KEY: LazyLock<SigningKey> = LazyLock::new(|| {
+    dotenv().ok();
+    let kid = std_env::var(env::JWT_SIGNING_KEY_ID_ENV_VAR)
+        .ok()
+        .filter(|kid| !kid.is_empty());
+    let path = match std_env::var(env::JWT_SIGNING_KEY_PATH_ENV_VAR) {
+        Ok(path) if !path.is_empty() => path,
+        _ => return SigningKey::from_secret(&JWT_SECRET, kid),
+    };
+
+    let algorithm = std_env::var(env::JWT_SIGNING_ALGORITHM_ENV_VAR)
+        .expect("JWT_SIGNING_ALGORITHM must be set along with JWT_SIGNING_KEY_PATH!")
+        .parse()
+        .expect("JWT_SIGNING_ALGORITHM must be either 'RS256', 'ES256' or 'EdDSA'!");
+    let pem = std_fs::read_to_string(&path).expect("Fail to read JWT_SIGNING_KEY_PATH!");
+    SigningKey::from_pem(algorithm, &pem, kid)
+        .expect("JWT_SIGNING_KEY_PATH must hold a PEM private key matching JWT_SIGNING_ALGORITHM!")
+});
+
+// should be a long random string, signing keys are encrypted at rest with a key derived from it.
+// It is separate from JWT_SECRET so changing that one doesn't lock the keyring.
+pub static SIGNING_KEY_ENCRYPTION_KEY: LazyLock<Secret<String>> = LazyLock::new(|| {
+    dotenv().ok();
+    let key = std_env::var(env::SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR)
+        .expect("SIGNING_KEY_ENCRYPTION_KEY must be set!");
+    if key.is_empty() {
+        panic!("SIGNING_KEY_ENCRYPTION_KEY must not be empty!");
+    }
+    Secret::new(key)
+});
+
+// keeps the keyring in this file instead of PostgreSQL
+pub static JWT_KEYRING_PATH: LazyLock<Option<String>> = LazyLock::new(|| {
+    dotenv().ok();
+    std_env::var(env::JWT_KEYRING_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+});
+
+// the admin API is turned off until a key is set
+pub static ADMIN_API_KEY: LazyLock<Option<Secret<String>>> = LazyLock::new(|| {
+    dotenv().ok();
+    std_env::var(env::ADMIN_API_KEY_ENV_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())
+        .map(Secret::new)
+});
+
+// should be a long random string, 2FA codes are kept as an HMAC under a key derived from it.
+// Changing it only invalidates the codes that are on their way.
+pub static TWO_FA_CODE_HASH_KEY: LazyLock<Secret<String>> = LazyLock::new(|| {
+    dotenv().ok();
+    let key =
+        std_env::var(env::TWO_FA_CODE_HASH_KEY_ENV_VAR).expect("TWO_FA_CODE_HASH_KEY must be set!");
+    if key.is_empty() {
+        panic!("TWO_FA_CODE_HASH_KEY must not be empty!");
+    }
+    Secret::new(key)
+});
+
+// should be a long random string, TOTP secrets are encrypted with a key derived from it
+pub static TOTP_ENCRYPTION_KEY: LazyLock<Secret<String>> = LazyLock::new(|| {
+    dotenv().ok();
+    let key =
+        std_env::var(env::TOTP_ENCRYPTION_KEY_ENV_VAR).expect("TOTP_ENCRYPTION_KEY must be set!");
+    if key.is_empty() {
+        panic!("TOTP_ENCRYPTION_KEY must not be empty!");
+    }
+    Secret::new(key)
+});
+
+// how many 30 second steps before and after now an authenticator app code is still accepted
+pub static TOTP_SKEW_STEPS: LazyLock<u8> = LazyLock::new(|| {
+    dotenv().ok();
+    match std_env::var(env::TOTP_SKEW_STEPS_ENV_VAR) {
+        Ok(skew) => skew
+            .parse()
+            .expect("TOTP_SKEW_STEPS must be a small positive number!"),
+        Err(_) => DEFAULT_TOTP_SKEW_STEPS,
+    }
+});
+
 pub static DATABASE_URL: LazyLock<Secret<String>> = LazyLock::new(|| {
     dotenv().ok();
     let url = std_env::var(env::DATABASE_URL_ENV_VAR).expect("DATABASE_URL must be set!");
@@ -25,6 +113,144 @@ pub static REDIS_HOST_NAME: LazyLock<String> = LazyLock::new(|| {
     std_env::var(env::REDIS_HOST_ENV_VAR).unwrap_or(DEFAULT_REDIS_HOSTNAME.to_owned())
 });
 
+pub static AUTH_SERVICE_URL: LazyLock<String> = LazyLock::new(|| {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+});
+
+// the iss claim of our JWTs, the auth service also accepts its own tokens under this audience
+pub static JWT_ISSUER: LazyLock<String> = LazyLock::new(|| {
+    dotenv().ok();
+    std_env::var(env::JWT_ISSUER_ENV_VAR)
+        .ok()
+        .filter(|issuer| !issuer.is_empty())
+        .unwrap_or(DEFAULT_JWT_ISSUER.to_owned())
+});
+
+// comma separated list of the services our JWTs are meant for, each one checks it is listed in aud
+pub static JWT_AUDIENCE: LazyLock<Vec<String>> = LazyLock::new(|| {
+    dotenv().ok();
+    let audience =
+        std_env::var(env::JWT_AUDIENCE_ENV_VAR).unwrap_or(DEFAULT_JWT_AUDIENCE.to_owned());
+    let mut audience: Vec<String> = audience
+        .split(',')
+        .map(str::trim)
+        .filter(|service| !service.is_empty())
+        .map(str::to_owned)
+        .collect();
+    if !audience.contains(&*JWT_ISSUER) {
+        audience.insert(0, JWT_ISSUER.to_owned());
+    }
+    audience
+});
+
+pub static UNVERIFIED_LOGIN_POLICY: LazyLock<UnverifiedLoginPolicy> = LazyLock::new(|| {
+    dotenv().ok();
+    match std_env::var(env::UNVERIFIED_LOGIN_POLICY_ENV_VAR) {
+        Ok(policy) => policy
+            .parse()
+            .expect("UNVERIFIED_LOGIN_POLICY must be either 'deny' or 'restricted'!"),
+        Err(_) => UnverifiedLoginPolicy::default(),
+    }
+});
+
+// failed logins before an account gets locked, and how long the first lock lasts
+pub static LOGIN_LOCKOUT_POLICY: LazyLock<LockoutPolicy> = LazyLock::new(|| {
+    dotenv().ok();
+    let threshold = match std_env::var(env::LOGIN_LOCKOUT_THRESHOLD_ENV_VAR) {
+        Ok(threshold) => threshold
+            .parse()
+            .ok()
+            .filter(|threshold| *threshold > 0)
+            .expect("LOGIN_LOCKOUT_THRESHOLD must be a positive number!"),
+        Err(_) => DEFAULT_LOGIN_LOCKOUT_THRESHOLD,
+    };
+    let base_seconds = match std_env::var(env::LOGIN_LOCKOUT_BASE_SECONDS_ENV_VAR) {
+        Ok(seconds) => seconds
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .expect("LOGIN_LOCKOUT_BASE_SECONDS must be a positive number!"),
+        Err(_) => DEFAULT_LOGIN_LOCKOUT_BASE_SECONDS,
+    };
+    LockoutPolicy {
+        threshold,
+        base_seconds,
+        max_seconds: LOGIN_LOCKOUT_MAX_SECONDS,
+    }
+});
+
+// RATE_LIMITS replaces the limits of single routes, e.g. "/login=ip:30/60,email:10/60;/signup=ip:10/60"
+pub static RATE_LIMITS: LazyLock<RateLimitConfig> = LazyLock::new(|| {
+    dotenv().ok();
+    let mut config = RateLimitConfig::default();
+    if let Ok(overrides) = std_env::var(env::RATE_LIMITS_ENV_VAR) {
+        config
+            .apply_overrides(&overrides)
+            .expect("RATE_LIMITS must look like '/login=ip:30/60,email:10/60;/signup=ip:10/60'!");
+    }
+    config
+});
+
+// reverse proxies in front of the service, each one appending the address it got the request from
+// to X-Forwarded-For. With 0 the client is whoever connected, the service is reached directly.
+pub static TRUSTED_PROXY_HOPS: LazyLock<usize> = LazyLock::new(|| {
+    dotenv().ok();
+    match std_env::var(env::TRUSTED_PROXY_HOPS_ENV_VAR) {
+        Ok(hops) => hops.parse().expect("TRUSTED_PROXY_HOPS must be a number!"),
+        Err(_) => 0,
+    }
+});
+
+// how emailed 2FA codes look, how long they last, and the wrong codes and resends a login
+// attempt may get before the user has to log in again
+pub static TWO_FACTOR_POLICY: LazyLock<TwoFactorPolicy> = LazyLock::new(|| {
+    dotenv().ok();
+    let defaults = TwoFactorPolicy::default();
+    let code_length = match std_env::var(env::TWO_FA_CODE_LENGTH_ENV_VAR) {
+        Ok(length) => length
+            .parse()
+            .expect("TWO_FA_CODE_LENGTH must be a number!"),
+        Err(_) => defaults.code_length,
+    };
+    let alphabet = match std_env::var(env::TWO_FA_CODE_ALPHABET_ENV_VAR) {
+        Ok(alphabet) => alphabet
+            .parse()
+            .expect("TWO_FA_CODE_ALPHABET must be either 'digits' or 'crockford-base32'!"),
+        Err(_) => defaults.alphabet,
+    };
+    let ttl_seconds = match std_env::var(env::TWO_FA_CODE_TTL_SECONDS_ENV_VAR) {
+        Ok(seconds) => seconds
+            .parse()
+            .expect("TWO_FA_CODE_TTL_SECONDS must be a number!"),
+        Err(_) => defaults.ttl_seconds,
+    };
+    let max_attempts = match std_env::var(env::TWO_FA_MAX_ATTEMPTS_ENV_VAR) {
+        Ok(attempts) => attempts
+            .parse()
+            .expect("TWO_FA_MAX_ATTEMPTS must be a number!"),
+        Err(_) => defaults.max_attempts,
+    };
+    let max_resends = match std_env::var(env::TWO_FA_MAX_RESENDS_ENV_VAR) {
+        Ok(resends) => resends
+            .parse()
+            .expect("TWO_FA_MAX_RESENDS must be a number!"),
+        Err(_) => defaults.max_resends,
+    };
+
+    let policy = TwoFactorPolicy {
+        code_length,
+        alphabet,
+        ttl_seconds,
+        max_attempts,
+        max_resends,
+    };
+    if let Err(e) = policy.validate() {
+        panic!("Invalid 2FA policy: {e}");
+    }
+    policy
+});
+
 pub static POSTMARK_AUTH_TOKEN: LazyLock<Secret<String>> = LazyLock::new(|| {
     dotenv().ok();
     Secret::new(
@@ -34,13 +260,72 @@ pub static POSTMARK_AUTH_TOKEN: LazyLock<Secret<String>> = LazyLock::new(|| {
 
 pub const JWT_COOKIE_NAME: &str = "jwt";
 pub const TOKEN_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const REFRESH_TOKEN_COOKIE_NAME: &str = "refresh_token";
+pub const REFRESH_TOKEN_TTL_SECONDS: i64 = 1209600; // 14 days
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+pub const DEFAULT_JWT_ISSUER: &str = "auth-service";
+pub const DEFAULT_JWT_AUDIENCE: &str = "app-service";
+pub const PASSWORD_RESET_TOKEN_TTL_SECONDS: i64 = 900; // 15 minutes
+pub const EMAIL_VERIFICATION_TOKEN_TTL_SECONDS: i64 = 86400; // 24 hours
+pub const EMAIL_VERIFICATION_RESEND_COOLDOWN_SECONDS: i64 = 60;
+pub const EMAIL_CHANGE_TOKEN_TTL_SECONDS: i64 = 3600; // 1 hour
+pub const TOTP_ISSUER: &str = "Let's Get Rusty";
+pub const TOTP_STEP_SECONDS: u64 = 30;
+pub const DEFAULT_TOTP_SKEW_STEPS: u8 = 1;
+pub const RECOVERY_CODE_COUNT: usize = 10;
+pub const MAGIC_LINK_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const KEYRING_RELOAD_INTERVAL_SECONDS: u64 = 60;
+// how long other instances may keep accepting tokens after a logout everywhere
+pub const TOKEN_VERSION_CACHE_TTL_SECONDS: u64 = 30;
+pub const ADMIN_API_KEY_HEADER: &str = "x-admin-api-key";
+pub const ADMIN_USERS_PAGE_SIZE: usize = 20;
+pub const ADMIN_USERS_MAX_PAGE_SIZE: usize = 100;
+pub const DEFAULT_LOGIN_LOCKOUT_THRESHOLD: u32 = 5;
+pub const DEFAULT_TWO_FA_CODE_LENGTH: usize = 6;
+pub const DEFAULT_TWO_FA_CODE_TTL_SECONDS: i64 = 600; // 10 minutes
+pub const DEFAULT_TWO_FA_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_TWO_FA_MAX_RESENDS: u32 = 3;
+pub const TWO_FA_RESEND_COOLDOWN_SECONDS: i64 = 30;
+// logins waiting on a 2FA code per user, starting another drops the oldest
+pub const TWO_FA_MAX_PENDING_ATTEMPTS: usize = 5;
+pub const DEFAULT_LOGIN_LOCKOUT_BASE_SECONDS: i64 = 60;
+pub const LOGIN_LOCKOUT_MAX_SECONDS: i64 = 86400; // 24 hours
+
+// failed attempts are forgotten after a week without any, and locks start small again
+pub const LOCKOUT_RECORD_TTL_SECONDS: i64 = 604800; // 7 days
+pub const UNLOCK_TOKEN_TTL_SECONDS: i64 = 86400; // 24 hours
+
+// same as the default body limit of axum, bodies are read ahead of the handler to find the email
+pub const RATE_LIMIT_MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
 
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
+    pub const JWT_SIGNING_KEY_PATH_ENV_VAR: &str = "JWT_SIGNING_KEY_PATH";
+    pub const JWT_SIGNING_ALGORITHM_ENV_VAR: &str = "JWT_SIGNING_ALGORITHM";
+    pub const JWT_SIGNING_KEY_ID_ENV_VAR: &str = "JWT_SIGNING_KEY_ID";
+    pub const JWT_KEYRING_PATH_ENV_VAR: &str = "JWT_KEYRING_PATH";
+    pub const SIGNING_KEY_ENCRYPTION_KEY_ENV_VAR: &str = "SIGNING_KEY_ENCRYPTION_KEY";
+    pub const ADMIN_API_KEY_ENV_VAR: &str = "ADMIN_API_KEY";
+    pub const JWT_ISSUER_ENV_VAR: &str = "JWT_ISSUER";
+    pub const JWT_AUDIENCE_ENV_VAR: &str = "JWT_AUDIENCE";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const UNVERIFIED_LOGIN_POLICY_ENV_VAR: &str = "UNVERIFIED_LOGIN_POLICY";
+    pub const TOTP_ENCRYPTION_KEY_ENV_VAR: &str = "TOTP_ENCRYPTION_KEY";
+    pub const TWO_FA_CODE_HASH_KEY_ENV_VAR: &str = "TWO_FA_CODE_HASH_KEY";
+    pub const TOTP_SKEW_STEPS_ENV_VAR: &str = "TOTP_SKEW_STEPS";
+    pub const LOGIN_LOCKOUT_THRESHOLD_ENV_VAR: &str = "LOGIN_LOCKOUT_THRESHOLD";
+    pub const LOGIN_LOCKOUT_BASE_SECONDS_ENV_VAR: &str = "LOGIN_LOCKOUT_BASE_SECONDS";
+    pub const RATE_LIMITS_ENV_VAR: &str = "RATE_LIMITS";
+    pub const TRUSTED_PROXY_HOPS_ENV_VAR: &str = "TRUSTED_PROXY_HOPS";
+    pub const TWO_FA_CODE_LENGTH_ENV_VAR: &str = "TWO_FA_CODE_LENGTH";
+    pub const TWO_FA_CODE_ALPHABET_ENV_VAR: &str = "TWO_FA_CODE_ALPHABET";
+    pub const TWO_FA_CODE_TTL_SECONDS_ENV_VAR: &str = "TWO_FA_CODE_TTL_SECONDS";
+    pub const TWO_FA_MAX_ATTEMPTS_ENV_VAR: &str = "TWO_FA_MAX_ATTEMPTS";
+    pub const TWO_FA_MAX_RESENDS_ENV_VAR: &str = "TWO_FA_MAX_RESENDS";
 }
 
 pub mod prod {
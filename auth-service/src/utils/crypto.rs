@@ -0,0 +1,111 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, OsRng},
+    Aes256Gcm, KeyInit, Nonce,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+const NONCE_LENGTH: usize = 12;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// AES-256-GCM key derived from a configured secret
+fn cipher(key: &Secret<String>) -> Aes256Gcm {
+    let key = Sha256::digest(key.expose_secret().as_bytes());
+    Aes256Gcm::new(&key)
+}
+
+// Encrypts secrets kept at rest, the result is base64 of the nonce followed by the AES-GCM ciphertext.
+pub fn encrypt(key: &Secret<String>, plaintext: &[u8]) -> Result<String> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher(key)
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| eyre!("Fail to encrypt secret"))?;
+
+    let mut data = nonce.to_vec();
+    data.extend(ciphertext);
+    Ok(STANDARD.encode(data))
+}
+
+pub fn decrypt(key: &Secret<String>, data: &str) -> Result<Vec<u8>> {
+    let bytes = check_encrypted(data)?;
+    let (nonce, ciphertext) = bytes.split_at(NONCE_LENGTH);
+    cipher(key)
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| eyre!("Fail to decrypt secret"))
+}
+
+// Checks the data looks like something `encrypt` returned, without decrypting it.
+pub fn check_encrypted(data: &str) -> Result<Vec<u8>> {
+    let bytes = STANDARD
+        .decode(data)
+        .wrap_err("Encrypted secret is not valid base64")?;
+    if bytes.len() <= NONCE_LENGTH {
+        return Err(eyre!("Encrypted secret is too short!"));
+    }
+    Ok(bytes)
+}
+
+fn mac(key: &Secret<String>, data: &[u8]) -> HmacSha256 {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size");
+    mac.update(data);
+    mac
+}
+
+// Hashes secrets that are only ever compared against, the result is base64 of their HMAC-SHA256.
+pub fn keyed_hash(key: &Secret<String>, data: &[u8]) -> String {
+    STANDARD.encode(mac(key, data).finalize().into_bytes())
+}
+
+// Checks data against what `keyed_hash` returned for it, in constant time.
+pub fn verify_keyed_hash(key: &Secret<String>, data: &[u8], hash: &str) -> bool {
+    match STANDARD.decode(hash) {
+        Ok(hash) => mac(key, data).verify_slice(&hash).is_ok(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> Secret<String> {
+        Secret::new("test-encryption-key".to_owned())
+    }
+
+    #[test]
+    fn encrypted_data_should_round_trip() {
+        let encrypted = encrypt(&key(), b"plaintext").unwrap();
+        assert!(check_encrypted(&encrypted).is_ok());
+        assert_eq!(decrypt(&key(), &encrypted).unwrap(), b"plaintext");
+    }
+
+    #[test]
+    fn wrong_key_should_fail_to_decrypt() {
+        let encrypted = encrypt(&key(), b"plaintext").unwrap();
+        assert!(decrypt(&Secret::new("another-key".to_owned()), &encrypted).is_err());
+    }
+
+    #[test]
+    fn keyed_hash_should_only_match_its_data_and_key() {
+        let hash = keyed_hash(&key(), b"123456");
+        assert!(verify_keyed_hash(&key(), b"123456", &hash));
+        assert!(!verify_keyed_hash(&key(), b"654321", &hash));
+        assert!(!verify_keyed_hash(
+            &Secret::new("another-key".to_owned()),
+            b"123456",
+            &hash
+        ));
+        assert!(!verify_keyed_hash(&key(), b"123456", "123456"));
+    }
+
+    #[test]
+    fn invalid_data_should_fail() {
+        assert!(check_encrypted("not base64!").is_err());
+        assert!(check_encrypted(&STANDARD.encode([0u8; NONCE_LENGTH])).is_err());
+    }
+}
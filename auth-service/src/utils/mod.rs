@@ -1,3 +1,7 @@
 pub mod auth;
+pub mod authorized;
+pub mod client_info;
 pub mod constants;
+pub mod crypto;
+pub mod rate_limit;
 pub mod tracing;
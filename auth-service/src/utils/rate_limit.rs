@@ -0,0 +1,125 @@
+use axum::body::{to_bytes, Body};
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::{header::RETRY_AFTER, HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::domain::email::Email;
+use crate::domain::error::AuthAPIError;
+use crate::domain::rate_limit::{RateLimit, RateLimitDecision};
+use crate::utils::client_info::ClientInfo;
+use crate::utils::constants::{RATE_LIMITS, RATE_LIMIT_MAX_BODY_BYTES};
+
+const RATE_LIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+const RATE_LIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+const RATE_LIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+
+#[derive(Deserialize)]
+struct EmailBody {
+    email: Secret<String>,
+}
+
+// Takes a request from the per IP and per email buckets of the routes listed in RATE_LIMITS,
+// and turns it away with 429 once one of them is empty, without taking from the others. If the
+// store fails, requests go through.
+#[tracing::instrument(name = "Rate limit", skip_all)]
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_owned();
+    let Some(limits) = RATE_LIMITS.route(&path) else {
+        return next.run(request).await;
+    };
+
+    let (mut parts, body) = request.into_parts();
+    let mut buckets: Vec<(String, &RateLimit)> = Vec::new();
+    if let Some(limit) = &limits.per_ip {
+        let client = ClientInfo::from_request_parts(&mut parts, &state)
+            .await
+            .unwrap_or_default();
+        if let Some(ip) = client.ip {
+            buckets.push((format!("{path}:ip:{ip}"), limit));
+        }
+    }
+
+    // the body has to be read to find the email, and is handed on to the route as it was
+    let body = match &limits.per_email {
+        Some(limit) => {
+            let Ok(bytes) = to_bytes(body, RATE_LIMIT_MAX_BODY_BYTES).await else {
+                return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+            };
+            // malformed bodies are left for the route to reject
+            if let Some(email) = serde_json::from_slice::<EmailBody>(&bytes)
+                .ok()
+                .and_then(|body| Email::parse(body.email).ok())
+            {
+                let email = email.as_ref().expose_secret().to_lowercase();
+                buckets.push((format!("{path}:email:{email}"), limit));
+            }
+            Body::from(bytes)
+        }
+        None => body,
+    };
+
+    let now = Utc::now().timestamp_millis();
+    let buckets: Vec<(&str, &RateLimit)> = buckets
+        .iter()
+        .map(|(key, limit)| (key.as_str(), *limit))
+        .collect();
+    let result = state
+        .rate_limit_store
+        .write()
+        .await
+        .check(&buckets, now)
+        .await;
+    let decisions = result.unwrap_or_else(|e| {
+        tracing::error!("Fail to check rate limit: {:?}", e);
+        Vec::new()
+    });
+
+    // the client is told to come back once every bucket has room again
+    if let Some(decision) = decisions
+        .iter()
+        .filter(|decision| !decision.allowed)
+        .max_by_key(|decision| decision.retry_after)
+    {
+        tracing::warn!("Request was rate limited");
+        return too_many_requests(decision);
+    }
+    let tightest = decisions
+        .into_iter()
+        .min_by_key(|decision| decision.remaining);
+
+    let mut response = next.run(Request::from_parts(parts, body)).await;
+    if let Some(decision) = tightest {
+        insert_headers(response.headers_mut(), &decision);
+    }
+    response
+}
+
+fn too_many_requests(decision: &RateLimitDecision) -> Response {
+    let mut response = AuthAPIError::TooManyRequests.into_response();
+    let headers = response.headers_mut();
+    insert_headers(headers, decision);
+    headers.insert(
+        RETRY_AFTER,
+        HeaderValue::from(seconds(decision.retry_after)),
+    );
+    response
+}
+
+fn insert_headers(headers: &mut HeaderMap, decision: &RateLimitDecision) {
+    headers.insert(RATE_LIMIT_LIMIT, HeaderValue::from(decision.limit));
+    headers.insert(RATE_LIMIT_REMAINING, HeaderValue::from(decision.remaining));
+    headers.insert(
+        RATE_LIMIT_RESET,
+        HeaderValue::from(seconds(decision.reset_after)),
+    );
+}
+
+// headers count whole seconds, rounded up so clients don't come back too early
+fn seconds(milliseconds: i64) -> i64 {
+    (milliseconds + 999) / 1000
+}
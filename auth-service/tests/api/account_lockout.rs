@@ -0,0 +1,103 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::LOGIN_LOCKOUT_POLICY;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use test_helpers::api_test;
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let body = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &Secret<String>, password: &str) -> StatusCode {
+    let body = json!({
+        "email": email.expose_secret(),
+        "password": password
+    });
+    app.post_login(&body).await.status()
+}
+
+// fails to log in until the account gets locked
+async fn lock(app: &TestApp, email: &Secret<String>) {
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        assert_eq!(
+            login(app, email, "WrongPassword123!").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        login(app, email, "WrongPassword123!").await,
+        StatusCode::LOCKED
+    );
+}
+
+#[api_test]
+async fn too_many_failed_logins_should_lock_the_account() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    lock(&app, &email).await;
+
+    // even the right password is turned away while it is locked
+    assert_eq!(
+        login(&app, &email, "Password123!").await,
+        StatusCode::LOCKED
+    );
+}
+
+#[api_test]
+async fn unlock_link_should_let_the_owner_back_in() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    lock(&app, &email).await;
+
+    let token = app.get_emailed_token(&email).await;
+    let response = app.get_unlock_account(&token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(login(&app, &email, "Password123!").await, StatusCode::OK);
+
+    // links are single use
+    let response = app.get_unlock_account(&token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn successful_login_should_reset_failed_attempts() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        assert_eq!(
+            login(&app, &email, "WrongPassword123!").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(login(&app, &email, "Password123!").await, StatusCode::OK);
+    assert_eq!(
+        login(&app, &email, "WrongPassword123!").await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[api_test]
+async fn unknown_email_should_lock_like_an_account() {
+    // otherwise the accounts that get locked are the ones that exist
+    let email = TestApp::get_random_email();
+    lock(&app, &email).await;
+
+    // there is nobody to send an unlock link to
+    let requests = app.email_server.received_requests().await.unwrap();
+    assert!(requests.is_empty());
+}
+
+#[api_test]
+async fn malformed_unlock_token_should_return_401() {
+    let response = app.get_unlock_account("not-a-token").await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
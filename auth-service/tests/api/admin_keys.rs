@@ -0,0 +1,117 @@
+use crate::helpers::TestApp;
+use auth_service::{domain::email::Email, routes::SigningKeyResponse};
+use reqwest::StatusCode;
+use serde_json::json;
+use test_helpers::api_test;
+
+async fn keys(response: reqwest::Response) -> Vec<SigningKeyResponse> {
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .json::<Vec<SigningKeyResponse>>()
+        .await
+        .expect("Fail to deserialize signing keys")
+}
+
+fn kid_in_state(keys: &[SigningKeyResponse], state: &str) -> String {
+    keys.iter()
+        .find(|key| key.state == state)
+        .map(|key| key.kid.clone())
+        .unwrap_or_else(|| panic!("No {state} key"))
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    app.post_verify_token(&json!({ "token": token }))
+        .await
+        .status()
+}
+
+#[api_test]
+async fn new_keyring_should_have_active_and_next_key() {
+    let keys = keys(app.get_admin_keys().await).await;
+    assert_eq!(keys.len(), 2);
+    kid_in_state(&keys, "active");
+    kid_in_state(&keys, "next");
+}
+
+#[api_test]
+async fn rotate_should_promote_next_key() {
+    let before = keys(app.get_admin_keys().await).await;
+    let api_key = TestApp::admin_api_key();
+    let after = keys(app.post_admin_rotate_keys(&json!({}), Some(&api_key)).await).await;
+
+    assert_eq!(
+        kid_in_state(&after, "active"),
+        kid_in_state(&before, "next")
+    );
+    assert_eq!(
+        kid_in_state(&after, "retiring"),
+        kid_in_state(&before, "active")
+    );
+    assert_ne!(kid_in_state(&after, "next"), kid_in_state(&before, "next"));
+}
+
+#[api_test]
+async fn tokens_should_stay_valid_until_their_key_is_retired() {
+    let email = Email::parse(TestApp::get_random_email()).expect("Unable to parse email");
+    let old_token = app.generate_auth_token(&email).await;
+
+    let api_key = TestApp::admin_api_key();
+    let keys = keys(app.post_admin_rotate_keys(&json!({}), Some(&api_key)).await).await;
+    let new_token = app.generate_auth_token(&email).await;
+    assert_eq!(verify(&app, &old_token).await, StatusCode::OK);
+    assert_eq!(verify(&app, &new_token).await, StatusCode::OK);
+
+    let response = app
+        .post_admin_retire_key(&kid_in_state(&keys, "retiring"))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(verify(&app, &old_token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(verify(&app, &new_token).await, StatusCode::OK);
+}
+
+#[api_test]
+async fn rotate_should_use_requested_algorithm() {
+    let api_key = TestApp::admin_api_key();
+    let keys = keys(
+        app.post_admin_rotate_keys(&json!({ "algorithm": "ES256" }), Some(&api_key))
+            .await,
+    )
+    .await;
+    let next = keys
+        .iter()
+        .find(|key| key.state == "next")
+        .expect("No next key");
+    assert_eq!(next.algorithm, "ES256");
+
+    // the new next key is published ahead of its activation
+    let jwks = app
+        .get_jwks()
+        .await
+        .json::<jsonwebtoken::jwk::JwkSet>()
+        .await
+        .expect("Fail to deserialize JWKS");
+    assert!(jwks.find(&next.kid).is_some());
+}
+
+#[api_test]
+async fn unknown_algorithm_should_return_400() {
+    let api_key = TestApp::admin_api_key();
+    let response = app
+        .post_admin_rotate_keys(&json!({ "algorithm": "none" }), Some(&api_key))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn retire_unknown_key_should_return_404() {
+    let response = app.post_admin_retire_key("unknown").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[api_test]
+async fn missing_or_wrong_api_key_should_return_401() {
+    for api_key in [None, Some("wrong")] {
+        let response = app.post_admin_rotate_keys(&json!({}), api_key).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
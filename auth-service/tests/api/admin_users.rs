@@ -0,0 +1,208 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::{data_store::UserUpdate, email::Email, user::UserRole},
+    routes::{jwt::JWToken, AdminUserDetailResponse, AdminUserListResponse, AdminUserResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use test_helpers::api_test;
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let signup = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+// signs up a verified account and logs it in, returning its JWT
+async fn login(app: &TestApp, email: &Secret<String>) -> String {
+    signup(app, email).await;
+    app.verify_email(email).await;
+
+    let login = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!")
+        .value()
+        .to_owned();
+    token
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    let body = JWToken {
+        token: token.to_owned(),
+        audience: None,
+        role: None,
+    };
+    app.post_verify_token(&body).await.status()
+}
+
+async fn list(app: &TestApp, query: &str) -> AdminUserListResponse {
+    let response = app
+        .get_admin_users(query, Some(&TestApp::admin_api_key()))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response.json().await.expect("Unable to deserialize users")
+}
+
+#[api_test]
+async fn missing_credentials_should_return_401() {
+    let response = app.get_admin_users("", None).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.get_admin_users("", Some("wrong")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn members_should_get_403_and_admins_should_get_in() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+    let response = app.get_admin_users("", None).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let update = UserUpdate {
+        role: Some(UserRole::Admin),
+        ..Default::default()
+    };
+    let parsed = Email::parse(email.clone()).expect("Unable to parse email");
+    app.user_store
+        .write()
+        .await
+        .update_user(&parsed, update)
+        .await
+        .expect("Unable to make the user an admin");
+
+    // the role only shows up in a new token
+    let login = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.get_admin_users("", None).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn users_should_be_searched_and_paginated() {
+    let tag = uuid::Uuid::new_v4().simple().to_string();
+    for name in ["carol", "alice", "bob"] {
+        signup(&app, &Secret::new(format!("{name}.{tag}@example.com"))).await;
+    }
+    signup(&app, &TestApp::get_random_email()).await;
+
+    let page = list(&app, &format!("search={tag}&perPage=2")).await;
+    assert_eq!(page.total, 3);
+    assert_eq!(page.page, 1);
+    let emails: Vec<&str> = page.users.iter().map(|user| user.email.as_str()).collect();
+    assert_eq!(
+        emails,
+        vec![
+            format!("alice.{tag}@example.com"),
+            format!("bob.{tag}@example.com")
+        ]
+    );
+
+    let page = list(&app, &format!("search={tag}&perPage=2&page=2")).await;
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email, format!("carol.{tag}@example.com"));
+
+    let response = app
+        .get_admin_users("page=0", Some(&TestApp::admin_api_key()))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn user_should_be_viewed_and_updated() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    let response = app.get_admin_user(email.expose_secret()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: AdminUserDetailResponse = response.json().await.expect("Unable to deserialize");
+    assert!(!user.user.requires_2fa);
+    assert!(user.user.email_verified);
+    assert_eq!(user.user.role, UserRole::Member);
+    assert_eq!(user.sessions, 1);
+
+    let response = app
+        .patch_admin_user(email.expose_secret(), &json!({ "requires2FA": true }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let user: AdminUserResponse = response.json().await.expect("Unable to deserialize");
+    assert!(user.requires_2fa);
+
+    let response = app
+        .patch_admin_user(email.expose_secret(), &json!({ "role": "root" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app.get_admin_user("nobody@example.com").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[api_test]
+async fn disabled_user_should_be_logged_out_and_not_log_in() {
+    let email = TestApp::get_random_email();
+    let token = login(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(email.expose_secret(), "disable")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+
+    let login = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .post_admin_user_action(email.expose_secret(), "enable")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn revoke_tokens_should_log_the_user_out() {
+    let email = TestApp::get_random_email();
+    let token = login(&app, &email).await;
+
+    let response = app
+        .post_admin_user_action(email.expose_secret(), "revoke-tokens")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn deleted_user_should_be_gone() {
+    let email = TestApp::get_random_email();
+    let token = login(&app, &email).await;
+
+    let response = app.delete_admin_user(email.expose_secret()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+
+    let response = app.get_admin_user(email.expose_secret()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let response = app.delete_admin_user(email.expose_secret()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
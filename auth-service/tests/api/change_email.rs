@@ -0,0 +1,258 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, LOGIN_LOCKOUT_POLICY};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &Secret<String>) -> reqwest::Response {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    app.post_login(&body).await
+}
+
+async fn request_change(app: &TestApp, new_email: &Secret<String>) {
+    let body = serde_json::json!({
+        "newEmail": new_email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn change_email_should_email_both_addresses() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    request_change(&app, &TestApp::get_random_email()).await;
+}
+
+#[api_test]
+async fn confirmed_change_should_move_account_and_sessions() {
+    let email = TestApp::get_random_email();
+    let new_email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    request_change(&app, &new_email).await;
+
+    // nothing changes until the new address confirms
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let token = app.get_emailed_token(&new_email).await;
+    let response = app.post_change_email_token("confirm", &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the session that was open keeps working under the new address
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(login(&app, &email).await.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login(&app, &new_email).await.status(), StatusCode::OK);
+
+    // links are single use
+    let response = app.post_change_email_token("confirm", &token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn old_tokens_should_not_pass_for_a_new_account_at_the_old_address() {
+    let email = TestApp::get_random_email();
+    let new_email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!")
+        .value()
+        .to_owned();
+
+    request_change(&app, &new_email).await;
+    let confirm_token = app.get_emailed_token(&new_email).await;
+    let response = app.post_change_email_token("confirm", &confirm_token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the token still names the old address, whose new owner starts over at token version 0
+    signup(&app, &email).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn cancelled_change_should_keep_account_and_revoke_sessions() {
+    let email = TestApp::get_random_email();
+    let new_email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    request_change(&app, &new_email).await;
+
+    let cancel_token = app.get_emailed_token(&email).await;
+    let response = app.post_change_email_token("cancel", &cancel_token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let confirm_token = app.get_emailed_token(&new_email).await;
+    let response = app.post_change_email_token("confirm", &confirm_token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn cancel_token_should_not_confirm_change() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    request_change(&app, &TestApp::get_random_email()).await;
+
+    let cancel_token = app.get_emailed_token(&email).await;
+    let response = app.post_change_email_token("confirm", &cancel_token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn change_to_existing_email_should_return_409() {
+    let email = TestApp::get_random_email();
+    let other = TestApp::get_random_email();
+    signup(&app, &other).await;
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({
+        "newEmail": other.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[api_test]
+async fn wrong_password_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({
+        "newEmail": TestApp::get_random_email().expose_secret(),
+        "password": "WrongPassword123!"
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn wrong_passwords_should_lock_the_account() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({
+        "newEmail": TestApp::get_random_email().expose_secret(),
+        "password": "WrongPassword123!"
+    });
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        let response = app.post_change_email(&body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+    assert_eq!(login(&app, &email).await.status(), StatusCode::LOCKED);
+}
+
+#[api_test]
+async fn missing_jwt_should_return_400() {
+    let body = serde_json::json!({
+        "newEmail": TestApp::get_random_email().expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_change_email(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn invalid_input_should_return_400() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let test_cases = [
+        serde_json::json!({
+            "newEmail": "test.test.com",
+            "password": "Password123!"
+        }),
+        serde_json::json!({
+            // same address
+            "newEmail": email.expose_secret(),
+            "password": "Password123!"
+        }),
+    ];
+
+    for test in test_cases {
+        let response = app.post_change_email(&test).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[api_test]
+async fn invalid_change_token_should_return_401() {
+    for action in ["confirm", "cancel"] {
+        let response = app.post_change_email_token(action, &"a".repeat(32)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[api_test]
+async fn opening_change_link_should_not_change_anything() {
+    // scanners and prefetchers follow links in emails, only the page they open may act on them
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+    let new_email = TestApp::get_random_email();
+    request_change(&app, &new_email).await;
+    let token = app.get_emailed_token(&new_email).await;
+
+    let response = app
+        .http_client
+        .get(format!("{}/change-email/confirm", &app.address))
+        .query(&[("token", &token)])
+        .send()
+        .await
+        .expect("Fail to get change email confirm request!");
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let response = app.post_change_email_token("confirm", &token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,230 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::{
+    JWT_COOKIE_NAME, LOGIN_LOCKOUT_POLICY, REFRESH_TOKEN_COOKIE_NAME,
+};
+use reqwest::{StatusCode, Url};
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// signs up a verified account and logs it in, returning the refresh token of that session
+async fn login(app: &TestApp, email: &Secret<String>) -> String {
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found!");
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
+    let cookie = format!("{}={}; Path=/", REFRESH_TOKEN_COOKIE_NAME, token);
+    app.cookie_jar.add_cookie_str(&cookie, &url);
+}
+
+#[api_test]
+async fn change_password_should_replace_password_and_notify() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "NewPassword123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn change_password_should_revoke_other_sessions() {
+    let email = TestApp::get_random_email();
+    let old_session = login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the current session got a new refresh token and keeps working
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    set_refresh_cookie(&app, &old_session);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn change_password_should_reject_old_access_tokens() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    // another session, whose access token is still within its lifetime
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let old_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No JWT cookie found!")
+        .value()
+        .to_owned();
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let new_token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No JWT cookie found!")
+        .value()
+        .to_owned();
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": old_token }))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_token }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn wrong_current_password_should_return_401() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "currentPassword": "WrongPassword123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn wrong_current_passwords_should_lock_the_account() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "currentPassword": "WrongPassword123!",
+        "newPassword": "NewPassword123!"
+    });
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        let response = app.post_change_password(&body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+
+    // the lock is shared with /login
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+}
+
+#[api_test]
+async fn missing_jwt_should_return_400() {
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn invalid_jwt_should_return_401() {
+    let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
+    app.cookie_jar.add_cookie_str("jwt=invalid; Path=/", &url);
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn invalid_new_password_should_return_400() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "password"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn malformed_input_should_return_422() {
+    let email = TestApp::get_random_email();
+    let _ = login(&app, &email).await;
+
+    let test_cases = [
+        serde_json::json!({ "currentPassword": "Password123!" }),
+        serde_json::json!({ "newPassword": "NewPassword123!" }),
+        serde_json::json!({ "currentPassword": true, "newPassword": "NewPassword123!" }),
+    ];
+
+    for test in test_cases {
+        let response = app.post_change_password(&test).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
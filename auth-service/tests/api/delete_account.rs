@@ -0,0 +1,188 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::login_attempt_id::LoginAttemptId, routes::TwoFactorAuthResponse,
+    utils::constants::LOGIN_LOCKOUT_POLICY,
+};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &Secret<String>, requires_2fa: bool) {
+    // 2FA logins fail when their code can't be emailed
+    if requires_2fa {
+        Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&app.email_server)
+            .await;
+    }
+
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &Secret<String>) -> reqwest::Response {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    app.post_login(&body).await
+}
+
+// logs in a 2FA account and leaves a pending 2FA code behind for the next login attempt.
+async fn login_with_2fa(app: &TestApp, email: &Secret<String>) -> (String, String) {
+    let response = login(app, email).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+
+    let response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Fail to deserialize 2FA response");
+    let code = app.get_emailed_2fa_code(email).await;
+    (response.login_attempt_id, code)
+}
+
+#[api_test]
+async fn delete_with_password_should_remove_account() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(login(&app, &email).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn delete_should_revoke_tokens() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == auth_service::utils::constants::JWT_COOKIE_NAME)
+        .unwrap()
+        .value()
+        .to_owned();
+
+    let claims = app.get_claims(&token).await;
+
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert!(app.banned_store.read().await.check_token(&claims.jti).await);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn delete_should_remove_pending_2fa_logins() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, true).await;
+
+    let (id, code) = login_with_2fa(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "2FACode": code
+    });
+    assert_eq!(app.post_verify_2fa(&body).await.status(), StatusCode::OK);
+
+    let (id, _) = login_with_2fa(&app, &email).await;
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // the pending 2FA code went away with the account
+    let id = LoginAttemptId::parse(id).unwrap();
+    assert!(app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&id)
+        .await
+        .is_err());
+    assert_eq!(login(&app, &email).await.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn wrong_password_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({ "password": "WrongPassword123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn missing_jwt_should_return_400() {
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn wrong_passwords_should_lock_the_account() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({ "password": "WrongPassword123!" });
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        let response = app.delete_account(&body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+
+    // even the right password is turned away while it is locked
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+}
+
+#[api_test]
+async fn invalid_password_should_return_400() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let body = serde_json::json!({ "password": "short" });
+    let response = app.delete_account(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn missing_password_should_return_422() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let test_cases = [
+        serde_json::json!({}),
+        serde_json::json!({ "loginAttemptId": LoginAttemptId::default().as_ref(), "2FACode": "123456" }),
+    ];
+
+    for test in test_cases {
+        let response = app.delete_account(&test).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
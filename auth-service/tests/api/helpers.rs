@@ -1,17 +1,40 @@
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType},
-    domain::{data_store::TwoFACodeStore, email::Email},
+    app_state::{AppState, BannedTokenStoreType, KeyringType, SessionStoreType, UserStoreType},
+    domain::{
+        auth_method::AuthMethod,
+        data_store::{PasswordResetTokenStore, Session, TwoFACodeStore},
+        email::Email,
+        user::UserRole,
+    },
     services::{
         data_stores::{
+            hashmap_account_lockout_store::HashmapAccountLockoutStore,
+            hashmap_email_change_store::HashmapEmailChangeStore,
+            hashmap_email_verification_token_store::HashmapEmailVerificationTokenStore,
+            hashmap_password_reset_token_store::HashmapPasswordResetTokenStore,
+            hashmap_rate_limit_store::HashmapRateLimitStore,
+            hashmap_refresh_token_store::HashmapRefreshTokenStore,
             hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+            hashset_magic_link_store::HashsetMagicLinkStore,
+            postgres_session_store::PostgresSessionStore,
+            postgres_signing_key_store::PostgresSigningKeyStore,
             postgres_user_store::PostgresUserStore,
             redis_banned_token_store::RedisBannedTokenStore,
         },
+        keyring::Keyring,
         postmark_email_client::PostmarkEmailClient,
+        token_version_cache::TokenVersionCache,
+    },
+    utils::{
+        auth::{generate_auth_token, validate_token, Claims},
+        constants::{
+            test, ADMIN_API_KEY, ADMIN_API_KEY_HEADER, DATABASE_URL, JWT_SIGNING_KEY,
+            REDIS_HOST_NAME, TOKEN_VERSION_CACHE_TTL_SECONDS,
+        },
     },
-    utils::constants::{test, DATABASE_URL, REDIS_HOST_NAME},
     Application,
 };
+use chrono::Utc;
 use reqwest::{cookie::Jar, Client};
 use secrecy::{ExposeSecret, Secret};
 use serde::Serialize;
@@ -30,6 +53,10 @@ pub struct TestApp {
     pub http_client: Client,
     pub banned_store: BannedTokenStoreType,
     pub two_fa_code_store: Arc<RwLock<dyn TwoFACodeStore>>,
+    pub password_reset_token_store: Arc<RwLock<dyn PasswordResetTokenStore>>,
+    pub keyring: KeyringType,
+    pub session_store: SessionStoreType,
+    pub user_store: UserStoreType,
     pub email_server: MockServer,
     db_name: String,
     clean_up_called: bool,
@@ -133,18 +160,48 @@ impl TestApp {
             .expect("Failed to get Redis connection");
         let redis_wrap = Arc::new(RwLock::new(redis_conn));
         let banned_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_wrap)));
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+        let keyring = Keyring::load(
+            Box::new(PostgresSigningKeyStore::new(pg_pool.clone())),
+            JWT_SIGNING_KEY.clone(),
+        )
+        .await
+        .expect("Failed to load signing keyring");
+        let keyring = Arc::new(RwLock::new(keyring));
+        let session_store = Arc::new(RwLock::new(PostgresSessionStore::new(pg_pool.clone())));
+        let user_store: UserStoreType = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
         let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let refresh_token_store = Arc::new(RwLock::new(HashmapRefreshTokenStore::default()));
+        let password_reset_token_store =
+            Arc::new(RwLock::new(HashmapPasswordResetTokenStore::default()));
+        let email_verification_token_store =
+            Arc::new(RwLock::new(HashmapEmailVerificationTokenStore::default()));
+        let email_change_store = Arc::new(RwLock::new(HashmapEmailChangeStore::default()));
+        let magic_link_store = Arc::new(RwLock::new(HashsetMagicLinkStore::default()));
+        let token_version_cache = Arc::new(RwLock::new(TokenVersionCache::new(
+            Duration::from_secs(TOKEN_VERSION_CACHE_TTL_SECONDS),
+        )));
+        let account_lockout_store = Arc::new(RwLock::new(HashmapAccountLockoutStore::default()));
+        let rate_limit_store = Arc::new(RwLock::new(HashmapRateLimitStore::default()));
 
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
         let email_client = Arc::new(Self::configure_postmark_email_client(base_url));
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_store.clone(),
             two_fa_code_store.clone(),
             email_client, // do I need to include this in the struct?
+            refresh_token_store,
+            password_reset_token_store.clone(),
+            email_verification_token_store,
+            email_change_store,
+            magic_link_store,
+            keyring.clone(),
+            session_store.clone(),
+            token_version_cache,
+            account_lockout_store,
+            rate_limit_store,
         );
         let duration = Duration::from_secs(2);
 
@@ -168,6 +225,10 @@ impl TestApp {
             http_client,
             banned_store,
             two_fa_code_store,
+            password_reset_token_store,
+            keyring,
+            session_store,
+            user_store,
             email_server,
             db_name,
             clean_up_called: false,
@@ -182,6 +243,158 @@ impl TestApp {
             .expect("Failed to get root!")
     }
 
+    pub async fn get_jwks(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/.well-known/jwks.json", &self.address))
+            .send()
+            .await
+            .expect("Failed to get JWKS!")
+    }
+
+    // banned tokens are looked up by their jti
+    pub async fn get_claims(&self, token: &str) -> Claims {
+        validate_token(&*self.keyring.read().await, token)
+            .await
+            .expect("Unable to validate token")
+    }
+
+    // a JWT for a session registered straight in the store, without logging in
+    pub async fn generate_auth_token(&self, email: &Email) -> String {
+        let now = Utc::now().timestamp();
+        let session = Session {
+            id: Uuid::new_v4().to_string(),
+            email: email.clone(),
+            created_at: now,
+            last_seen_at: now,
+            ip: None,
+            user_agent: None,
+        };
+        let id = session.id.clone();
+        self.session_store
+            .write()
+            .await
+            .add_session(session)
+            .await
+            .expect("Unable to add dummy session");
+        // users that never signed up are on version 0 and get to be members
+        let user_store = self.user_store.read().await;
+        let version = user_store
+            .get_token_version(email)
+            .await
+            .unwrap_or_default();
+        let role = user_store
+            .get_user(email)
+            .await
+            .map(|user| user.role())
+            .unwrap_or(UserRole::Member);
+        drop(user_store);
+        generate_auth_token(
+            &*self.keyring.read().await,
+            email,
+            &id,
+            version,
+            role,
+            &[AuthMethod::Password],
+        )
+        .expect("Unable to generate dummy token")
+    }
+
+    pub fn admin_api_key() -> String {
+        ADMIN_API_KEY
+            .as_ref()
+            .expect("ADMIN_API_KEY must be set to test the admin routes")
+            .expose_secret()
+            .to_owned()
+    }
+
+    pub async fn get_admin_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/keys", &self.address))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .send()
+            .await
+            .expect("Failed to get signing keys!")
+    }
+
+    pub async fn post_admin_rotate_keys<T: Serialize>(
+        &self,
+        body: &T,
+        api_key: Option<&str>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin/keys/rotate", &self.address))
+            .json(body);
+        if let Some(api_key) = api_key {
+            request = request.header(ADMIN_API_KEY_HEADER, api_key);
+        }
+        request
+            .send()
+            .await
+            .expect("Failed to rotate signing keys!")
+    }
+
+    pub async fn post_admin_retire_key(&self, kid: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/admin/keys/{}/retire", &self.address, kid))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .send()
+            .await
+            .expect("Failed to retire signing key!")
+    }
+
+    // without an API key the request goes through on the JWT cookie, if there is one
+    pub async fn get_admin_users(&self, query: &str, api_key: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin/users?{}", &self.address, query));
+        if let Some(api_key) = api_key {
+            request = request.header(ADMIN_API_KEY_HEADER, api_key);
+        }
+        request.send().await.expect("Failed to list users!")
+    }
+
+    pub async fn get_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/admin/users/{}", &self.address, email))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .send()
+            .await
+            .expect("Failed to get user!")
+    }
+
+    pub async fn patch_admin_user<T: Serialize>(&self, email: &str, body: &T) -> reqwest::Response {
+        self.http_client
+            .patch(format!("{}/admin/users/{}", &self.address, email))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to update user!")
+    }
+
+    // action is one of disable, enable or revoke-tokens
+    pub async fn post_admin_user_action(&self, email: &str, action: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/admin/users/{}/{}",
+                &self.address, email, action
+            ))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .send()
+            .await
+            .expect("Failed to post user action!")
+    }
+
+    pub async fn delete_admin_user(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/admin/users/{}", &self.address, email))
+            .header(ADMIN_API_KEY_HEADER, Self::admin_api_key())
+            .send()
+            .await
+            .expect("Failed to delete user!")
+    }
+
     pub async fn post_signup<T: Serialize>(&self, body: &T) -> reqwest::Response {
         self.post(&format!("{}/signup", &self.address), body).await
     }
@@ -198,15 +411,217 @@ impl TestApp {
             .expect("Fail to post logout request!")
     }
 
+    pub async fn post_logout_all(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout-all", &self.address))
+            .send()
+            .await
+            .expect("Fail to post logout all request!")
+    }
+
     pub async fn post_verify_2fa<T: Serialize>(&self, body: &T) -> reqwest::Response {
         self.post(&format!("{}/verify-2fa", &self.address), body)
             .await
     }
 
+    pub async fn post_resend_2fa<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/resend-2fa", &self.address), body)
+            .await
+    }
+
     pub async fn post_verify_token<T: Serialize>(&self, body: &T) -> reqwest::Response {
         self.post(&format!("{}/verify-token", &self.address), body)
             .await
     }
+
+    pub async fn post_forgot_password<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/forgot-password", &self.address), body)
+            .await
+    }
+
+    pub async fn post_reset_password<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/reset-password", &self.address), body)
+            .await
+    }
+
+    pub async fn post_change_password<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/change-password", &self.address), body)
+            .await
+    }
+
+    pub async fn post_change_email<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/change-email", &self.address), body)
+            .await
+    }
+
+    pub async fn delete_account<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/account", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Fail to send delete account request!")
+    }
+
+    pub async fn get_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/sessions", &self.address))
+            .send()
+            .await
+            .expect("Fail to get sessions!")
+    }
+
+    pub async fn delete_session(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/sessions/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Fail to send delete session request!")
+    }
+
+    pub async fn post_revoke_other_sessions(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/sessions/revoke-others", &self.address))
+            .send()
+            .await
+            .expect("Fail to post revoke other sessions request!")
+    }
+
+    pub async fn post_totp_enroll<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/totp/enroll", &self.address), body)
+            .await
+    }
+
+    pub async fn post_totp_confirm<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/totp/confirm", &self.address), body)
+            .await
+    }
+
+    pub async fn post_recovery_codes<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/recovery-codes", &self.address), body)
+            .await
+    }
+
+    pub async fn post_verify_recovery_code<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/verify-2fa/recovery", &self.address), body)
+            .await
+    }
+
+    pub async fn post_magic_link<T: Serialize>(&self, body: &T) -> reqwest::Response {
+        self.post(&format!("{}/login/magic-link", &self.address), body)
+            .await
+    }
+
+    pub async fn get_magic_link_callback(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/magic-link/callback", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Fail to get magic link callback request!")
+    }
+
+    pub async fn post_change_email_token(&self, action: &str, token: &str) -> reqwest::Response {
+        let body = serde_json::json!({ "token": token });
+        self.post(&format!("{}/change-email/{}", &self.address, action), &body)
+            .await
+    }
+
+    pub async fn get_verify_email(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/verify-email", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Fail to get verify email request!")
+    }
+
+    pub async fn get_unlock_account(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/unlock-account", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Fail to get unlock account request!")
+    }
+
+    pub async fn post_resend_verification_email<T: Serialize>(
+        &self,
+        body: &T,
+    ) -> reqwest::Response {
+        self.post(
+            &format!("{}/resend-verification-email", &self.address),
+            body,
+        )
+        .await
+    }
+
+    // Digs the token out of the last link the mock email server sent to this address.
+    pub async fn get_emailed_token(&self, email: &Secret<String>) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled on the mock email server!");
+
+        requests
+            .iter()
+            .rev()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .filter(|body| body["To"] == email.expose_secret().as_str())
+            .find_map(|body| {
+                let text = body["TextBody"].as_str()?;
+                let (_, token) = text.split_once("token=")?;
+                // signed tokens are JWTs, so keep their url safe base64 and dots as well
+                Some(
+                    token
+                        .chars()
+                        .take_while(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+                        .collect(),
+                )
+            })
+            .expect("No email with a token was sent to this address!")
+    }
+
+    // Finds the 2FA code in the last one the mock email server sent to this address, the stores
+    // only keep a hash of it.
+    pub async fn get_emailed_2fa_code(&self, email: &Secret<String>) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled on the mock email server!");
+
+        requests
+            .iter()
+            .rev()
+            .filter_map(|request| request.body_json::<serde_json::Value>().ok())
+            .filter(|body| body["To"] == email.expose_secret().as_str())
+            .find_map(|body| {
+                let text = body["TextBody"].as_str()?;
+                let (_, code) = text.split_once("log into the website: ")?;
+                Some(
+                    code.chars()
+                        .take_while(|c| c.is_ascii_alphanumeric())
+                        .collect(),
+                )
+            })
+            .expect("No email with a 2FA code was sent to this address!")
+    }
+
+    pub async fn verify_email(&self, email: &Secret<String>) {
+        let token = self.get_emailed_token(email).await;
+        let response = self.get_verify_email(&token).await;
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    pub async fn post_refresh(&self) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/refresh", &self.address))
+            .send()
+            .await
+            .expect("Fail to post refresh request!")
+    }
 }
 
 impl Drop for TestApp {
@@ -0,0 +1,24 @@
+use crate::helpers::TestApp;
+use jsonwebtoken::jwk::JwkSet;
+use reqwest::StatusCode;
+use test_helpers::api_test;
+
+#[api_test]
+async fn jwks_should_publish_public_signing_keys() {
+    let response = app.get_jwks().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let jwks = response
+        .json::<JwkSet>()
+        .await
+        .expect("Fail to deserialize JWKS");
+    assert_eq!(jwks, app.keyring.read().await.jwks());
+
+    let keyring = app.keyring.read().await;
+    let active = keyring.active().expect("No active signing key");
+    match active.jwk() {
+        Some(jwk) => assert_eq!(jwks.find(active.kid()), Some(jwk)),
+        // a shared secret must never be published
+        None => assert!(jwks.find(active.kid()).is_none()),
+    }
+}
@@ -1,6 +1,8 @@
 use crate::helpers::TestApp;
 use auth_service::{
-    domain::email::Email, routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME,
+    domain::{email::Email, login_attempt_id::LoginAttemptId},
+    routes::TwoFactorAuthResponse,
+    utils::constants::JWT_COOKIE_NAME,
 };
 use reqwest::StatusCode;
 use secrecy::{ExposeSecret, Secret};
@@ -22,6 +24,7 @@ pub async fn should_return_200_if_valid_cred_no_2fa() {
 
     let response = app.post_signup(&signup).await;
     assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(&email).await;
 
     let login = serde_json::json!({
         "email": email.expose_secret(),
@@ -52,6 +55,7 @@ pub async fn should_return_206_if_valid_cred_with_2fa() {
     });
     let new_account = app.post_signup(&body).await;
     assert_eq!(new_account.status(), StatusCode::CREATED);
+    app.verify_email(&Secret::new(input.clone())).await;
 
     // then, log into test account
     let test = serde_json::json!({
@@ -76,12 +80,14 @@ pub async fn should_return_206_if_valid_cred_with_2fa() {
         .expect("Should receive a Login response!");
     {
         let two_fa_code_store = &app.two_fa_code_store.read().await;
+        let id = LoginAttemptId::parse(body.login_attempt_id)
+            .expect("Should receive a valid login attempt ID!");
         let code = two_fa_code_store
-            .get_code(&email)
+            .get_code(&id)
             .await
             .expect("Could not find entry in twoFACodeStore db!");
 
-        assert_eq!(body.login_attempt_id, code.id.as_ref());
+        assert_eq!(code.email, email);
     }
 }
 
@@ -185,3 +191,27 @@ async fn unauthorize_user_should_return_401() {
     let response = app.post_login(&invalid_user).await;
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
+
+#[api_test]
+async fn unverified_email_should_return_403() {
+    let email = TestApp::get_random_email();
+
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .is_none());
+}
@@ -1,8 +1,5 @@
 use crate::helpers::TestApp;
-use auth_service::{
-    domain::email::Email,
-    utils::{auth::generate_auth_cookie, constants::JWT_COOKIE_NAME},
-};
+use auth_service::{domain::email::Email, utils::constants::JWT_COOKIE_NAME};
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use reqwest::{cookie::CookieStore, StatusCode, Url};
 use secrecy::Secret;
@@ -14,11 +11,8 @@ fn get_fake_email() -> Email {
     Email::parse(secret).expect("Unable to parse test email account!")
 }
 
-fn generate_valid_token() -> String {
-    generate_auth_cookie(&get_fake_email())
-        .expect("Unable to generate dummy token to test!")
-        .value()
-        .to_owned()
+async fn generate_valid_token(app: &TestApp) -> String {
+    app.generate_auth_token(&get_fake_email()).await
 }
 
 fn generate_default_cookie<'c>(token: String) -> Cookie<'c> {
@@ -32,7 +26,7 @@ fn generate_default_cookie<'c>(token: String) -> Cookie<'c> {
 
 #[api_test]
 async fn valid_jwt_should_return_200() {
-    let token = generate_valid_token();
+    let token = generate_valid_token(&app).await;
 
     let cookie = generate_default_cookie(token.clone());
     let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
@@ -43,8 +37,9 @@ async fn valid_jwt_should_return_200() {
 
     // verify that the banned token have a new entry in the banned list.
     {
+        let claims = app.get_claims(&token).await;
         let store = app.banned_store.read().await;
-        let result = store.check_token(&token).await;
+        let result = store.check_token(&claims.jti).await;
         assert_eq!(result, true);
     }
 }
@@ -59,7 +54,7 @@ async fn missing_jwt_should_return_400() {
 async fn logout_twice_should_return_400() {
     // log in and verify that first.
     // then log out again.
-    let token = generate_valid_token();
+    let token = generate_valid_token(&app).await;
     let cookie = generate_default_cookie(token);
     let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
     let _ = &app.cookie_jar.add_cookie_str(&cookie.to_string(), &url);
@@ -83,7 +78,7 @@ async fn invalid_jwt_should_return_401() {
 
 #[api_test]
 async fn banned_token_should_return_401() {
-    let token = generate_valid_token();
+    let token = generate_valid_token(&app).await;
     let cookie = generate_default_cookie(token.clone());
 
     let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
@@ -91,9 +86,10 @@ async fn banned_token_should_return_401() {
 
     {
         // using this scope hack to ensure the Arc nand RwLock gets dropped at the end of the call?
+        let claims = app.get_claims(&token).await;
         let store = app.banned_store.clone();
         let mut ban_list = store.write().await;
-        let result = ban_list.add_token(&token).await;
+        let result = ban_list.add_token(&claims.jti, claims.exp as i64).await;
         assert!(result.is_ok());
     }
 
@@ -107,8 +103,8 @@ async fn banned_token_should_return_401() {
 #[api_test]
 async fn ensure_cookie_is_clear_after_success_logout() {
     let email = get_fake_email();
-    let token = generate_auth_cookie(&email).expect("Unable to generate dummy token to test");
-    let cookie = generate_default_cookie(token.value().to_owned());
+    let token = app.generate_auth_token(&email).await;
+    let cookie = generate_default_cookie(token);
     let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
     let _ = &app.cookie_jar.add_cookie_str(&cookie.to_string(), &url);
 
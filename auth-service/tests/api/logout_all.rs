@@ -0,0 +1,112 @@
+use crate::helpers::TestApp;
+use auth_service::{domain::email::Email, routes::jwt::JWToken, utils::constants::JWT_COOKIE_NAME};
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+// signs up and logs in, the app's cookie jar then holds the session cookies
+async fn login(app: &TestApp) -> (Email, String) {
+    let email = TestApp::get_random_email();
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(&email).await;
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!")
+        .value()
+        .to_owned();
+    let email = Email::parse(email).expect("Unable to parse email");
+    (email, token)
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    let body = JWToken {
+        token: token.to_owned(),
+        audience: None,
+        role: None,
+    };
+    app.post_verify_token(&body).await.status()
+}
+
+async fn token_version(app: &TestApp, email: &Email) -> i32 {
+    app.user_store
+        .read()
+        .await
+        .get_token_version(email)
+        .await
+        .expect("Unable to get token version")
+}
+
+#[api_test]
+async fn missing_token_should_return_400() {
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn logout_all_should_invalidate_every_token() {
+    let (email, token) = login(&app).await;
+    let other = app.generate_auth_token(&email).await;
+    assert_eq!(verify(&app, &other).await, StatusCode::OK);
+
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(token_version(&app, &email).await, 1);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(verify(&app, &other).await, StatusCode::UNAUTHORIZED);
+
+    // the refresh token went with the sessions
+    let response = app.post_refresh().await;
+    assert_ne!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn tokens_issued_after_logout_all_should_be_accepted() {
+    let (email, _) = login(&app).await;
+    let response = app.post_logout_all().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = app.generate_auth_token(&email).await;
+    let claims = app.get_claims(&token).await;
+    assert_eq!(claims.ver, 1);
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+}
+
+#[api_test]
+async fn change_password_should_bump_token_version() {
+    let (email, token) = login(&app).await;
+
+    let body = serde_json::json!({
+        "currentPassword": "Password123!",
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(token_version(&app, &email).await, 1);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+
+    // the session the password was changed from carries on with a fresh token
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!")
+        .value()
+        .to_owned();
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+}
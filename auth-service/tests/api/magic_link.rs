@@ -0,0 +1,140 @@
+use crate::helpers::TestApp;
+use auth_service::{routes::TwoFactorAuthResponse, utils::constants::JWT_COOKIE_NAME};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &Secret<String>, requires_2fa: bool) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": requires_2fa
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+async fn request_link(app: &TestApp, email: &Secret<String>) -> String {
+    let body = serde_json::json!({ "email": email.expose_secret() });
+    let response = app.post_magic_link(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    app.get_emailed_token(email).await
+}
+
+#[api_test]
+async fn magic_link_should_set_auth_cookie() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+
+    let token = request_link(&app, &email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!");
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[api_test]
+async fn magic_link_should_only_work_once() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+
+    let token = request_link(&app, &email).await;
+    assert_eq!(
+        app.get_magic_link_callback(&token).await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        app.get_magic_link_callback(&token).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[api_test]
+async fn magic_link_should_verify_email() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+
+    let token = request_link(&app, &email).await;
+    assert_eq!(
+        app.get_magic_link_callback(&token).await.status(),
+        StatusCode::OK
+    );
+
+    // only verified accounts get a refresh token
+    assert_eq!(app.post_refresh().await.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn magic_link_should_still_require_2fa() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, true).await;
+
+    let token = request_link(&app, &email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert!(response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .is_none());
+
+    let response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Fail to deserialize 2FA response");
+    assert_eq!(response.message, "2FA required");
+}
+
+#[api_test]
+async fn unknown_email_should_return_200_without_sending_email() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": TestApp::get_random_email().expose_secret() });
+    let response = app.post_magic_link(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn invalid_email_should_return_400() {
+    let body = serde_json::json!({ "email": "test.test.com" });
+    let response = app.post_magic_link(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn invalid_magic_link_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email, false).await;
+
+    // a session token is not a login link
+    let token = request_link(&app, &email).await;
+    let response = app.get_magic_link_callback(&token).await;
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!");
+
+    let test_cases = ["invalid_token".to_owned(), auth_cookie.value().to_owned()];
+    for test in test_cases {
+        let response = app.get_magic_link_callback(&test).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -1,7 +1,24 @@
+mod account_lockout;
+mod admin_keys;
+mod admin_users;
+mod change_email;
+mod change_password;
+mod delete_account;
 mod helpers;
+mod jwks;
 mod login;
 mod logout;
+mod logout_all;
+mod magic_link;
+mod rate_limit;
+mod recovery_codes;
+mod refresh;
+mod resend_2fa;
+mod reset_password;
 mod root;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
@@ -0,0 +1,110 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::RATE_LIMITS;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use serde_json::json;
+use test_helpers::api_test;
+
+async fn login(app: &TestApp, email: &Secret<String>) -> reqwest::Response {
+    let body = json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    app.post_login(&body).await
+}
+
+fn header(response: &reqwest::Response, name: &str) -> Option<u64> {
+    response.headers().get(name)?.to_str().ok()?.parse().ok()
+}
+
+#[api_test]
+async fn responses_should_carry_rate_limit_headers() {
+    let limit = RATE_LIMITS
+        .route("/login")
+        .and_then(|limits| limits.per_email)
+        .expect("/login should be limited per email");
+
+    let response = login(&app, &TestApp::get_random_email()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        header(&response, "ratelimit-limit"),
+        Some(u64::from(limit.burst))
+    );
+    assert_eq!(
+        header(&response, "ratelimit-remaining"),
+        Some(u64::from(limit.burst - 1))
+    );
+    assert!(header(&response, "ratelimit-reset").is_some());
+}
+
+#[api_test]
+async fn too_many_logins_for_one_email_should_return_429() {
+    let limit = RATE_LIMITS
+        .route("/login")
+        .and_then(|limits| limits.per_email)
+        .expect("/login should be limited per email");
+    let email = TestApp::get_random_email();
+
+    // unknown emails get locked like accounts along the way, that happens behind the limiter
+    for _ in 0..limit.burst {
+        let response = login(&app, &email).await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert!(header(&response, "retry-after").is_some_and(|seconds| seconds > 0));
+    assert_eq!(header(&response, "ratelimit-remaining"), Some(0));
+
+    // other emails have a bucket of their own
+    let response = login(&app, &TestApp::get_random_email()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn rejected_requests_should_not_spend_the_ip_bucket() {
+    let limits = RATE_LIMITS
+        .route("/login")
+        .expect("/login should be limited");
+    let per_email = limits
+        .per_email
+        .expect("/login should be limited per email");
+    let per_ip = limits.per_ip.expect("/login should be limited per IP");
+    let email = TestApp::get_random_email();
+
+    for _ in 0..per_email.burst {
+        let response = login(&app, &email).await;
+        assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+    // more than the IP allows, all turned away by the email bucket
+    for _ in 0..per_ip.burst {
+        let response = login(&app, &email).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    let response = login(&app, &TestApp::get_random_email()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn too_many_reset_links_for_one_email_should_return_429() {
+    let limit = RATE_LIMITS
+        .route("/forgot-password")
+        .and_then(|limits| limits.per_email)
+        .expect("/forgot-password should be limited per email");
+    let body = json!({ "email": TestApp::get_random_email().expose_secret() });
+
+    for _ in 0..limit.burst {
+        let response = app.post_forgot_password(&body).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    let response = app.post_forgot_password(&body).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[api_test]
+async fn verify_token_should_not_be_limited() {
+    assert_eq!(RATE_LIMITS.route("/verify-token"), None);
+
+    let response = app.post_verify_token(&json!({ "token": "invalid" })).await;
+    assert!(response.headers().get("ratelimit-limit").is_none());
+}
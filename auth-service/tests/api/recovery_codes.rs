@@ -0,0 +1,223 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    routes::{RecoveryCodesResponse, TwoFactorAuthResponse},
+    utils::constants::LOGIN_LOCKOUT_POLICY,
+};
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// signs up a 2FA account and goes through its first login
+async fn signup_and_login(app: &TestApp, email: &Secret<String>) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+
+    let id = start_login(app, email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "2FACode": app.get_emailed_2fa_code(email).await
+    });
+    assert_eq!(app.post_verify_2fa(&body).await.status(), StatusCode::OK);
+}
+
+async fn start_login(app: &TestApp, email: &Secret<String>) -> String {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Fail to deserialize 2FA response")
+        .login_attempt_id
+}
+
+async fn regenerate(app: &TestApp) -> Vec<String> {
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.post_recovery_codes(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .json::<RecoveryCodesResponse>()
+        .await
+        .expect("Fail to deserialize recovery codes response")
+        .recovery_codes
+}
+
+async fn use_code(
+    app: &TestApp,
+    email: &Secret<String>,
+    id: &str,
+    code: &str,
+) -> reqwest::Response {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "recoveryCode": code
+    });
+    app.post_verify_recovery_code(&body).await
+}
+
+#[api_test]
+async fn regenerate_should_return_ten_distinct_codes() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let mut codes = regenerate(&app).await;
+    assert_eq!(codes.len(), 10);
+    codes.sort();
+    codes.dedup();
+    assert_eq!(codes.len(), 10);
+}
+
+#[api_test]
+async fn recovery_code_should_log_in_once() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+    let codes = regenerate(&app).await;
+
+    let id = start_login(&app, &email).await;
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let id = start_login(&app, &email).await;
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // codes are accepted the way people tend to type them back in
+    let response = use_code(&app, &email, &id, &codes[1].to_uppercase()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn recovery_code_use_should_send_alert() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+    let codes = regenerate(&app).await;
+
+    let id = start_login(&app, &email).await;
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let alert = requests
+        .last()
+        .unwrap()
+        .body_json::<serde_json::Value>()
+        .unwrap();
+    assert_eq!(alert["To"], email.expose_secret().as_str());
+    assert!(alert["TextBody"]
+        .as_str()
+        .unwrap()
+        .contains("you have 9 left"));
+}
+
+#[api_test]
+async fn regenerate_should_invalidate_old_codes() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+    let old = regenerate(&app).await;
+    let new = regenerate(&app).await;
+
+    let id = start_login(&app, &email).await;
+    let response = use_code(&app, &email, &id, &old[0]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let response = use_code(&app, &email, &id, &new[0]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn recovery_code_should_need_login_attempt() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+    let codes = regenerate(&app).await;
+
+    // the last login attempt was already completed
+    let id = uuid::Uuid::new_v4().to_string();
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn regenerate_with_wrong_password_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let body = serde_json::json!({ "password": "WrongPassword123!" });
+    let response = app.post_recovery_codes(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn regenerate_with_wrong_passwords_should_lock_the_account() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let body = serde_json::json!({ "password": "WrongPassword123!" });
+    for _ in 1..LOGIN_LOCKOUT_POLICY.threshold {
+        let response = app.post_recovery_codes(&body).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_recovery_codes(&body).await;
+    assert_eq!(response.status(), StatusCode::LOCKED);
+}
+
+#[api_test]
+async fn recovery_code_should_not_be_burned_for_disabled_account() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+    let codes = regenerate(&app).await;
+
+    let id = start_login(&app, &email).await;
+    let response = app
+        .post_admin_user_action(email.expose_secret(), "disable")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app
+        .post_admin_user_action(email.expose_secret(), "enable")
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let id = start_login(&app, &email).await;
+    let response = use_code(&app, &email, &id, &codes[0]).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn regenerate_without_jwt_should_return_400() {
+    let body = serde_json::json!({ "password": "Password123!" });
+    let response = app.post_recovery_codes(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn malformed_recovery_code_should_return_400() {
+    let email = TestApp::get_random_email();
+    signup_and_login(&app, &email).await;
+
+    let id = start_login(&app, &email).await;
+    for code in ["", "abcde12345", "abcde-1234!"] {
+        let response = use_code(&app, &email, &id, code).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
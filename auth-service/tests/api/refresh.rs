@@ -0,0 +1,109 @@
+use crate::helpers::TestApp;
+use auth_service::utils::constants::{JWT_COOKIE_NAME, REFRESH_TOKEN_COOKIE_NAME};
+use reqwest::{StatusCode, Url};
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+async fn login_without_2fa(app: &TestApp) -> String {
+    let email = TestApp::get_random_email();
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(&email).await;
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found!");
+    refresh_cookie.value().to_owned()
+}
+
+fn set_refresh_cookie(app: &TestApp, token: &str) {
+    let url = Url::parse("http://127.0.0.1").expect("Unable to parse the url for testing purposes");
+    let cookie = format!("{}={}; Path=/", REFRESH_TOKEN_COOKIE_NAME, token);
+    app.cookie_jar.add_cookie_str(&cookie, &url);
+}
+
+#[api_test]
+async fn missing_refresh_token_should_return_400() {
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn invalid_refresh_token_should_return_401() {
+    set_refresh_cookie(&app, "invalid");
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn valid_refresh_token_should_return_200_and_rotate() {
+    let token = login_without_2fa(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!");
+    assert!(!auth_cookie.value().is_empty());
+
+    let refresh_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found!");
+    assert_ne!(refresh_cookie.value(), token);
+
+    // the rotated token should be usable as well
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn reused_refresh_token_should_revoke_family() {
+    let stolen = login_without_2fa(&app).await;
+
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let current = response
+        .cookies()
+        .find(|cookie| cookie.name() == REFRESH_TOKEN_COOKIE_NAME)
+        .expect("No refresh cookie found!")
+        .value()
+        .to_owned();
+
+    // replay the token that was already rotated
+    set_refresh_cookie(&app, &stolen);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // the legitimate token from the same family must be revoked as well
+    set_refresh_cookie(&app, &current);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn refresh_token_should_not_work_after_logout() {
+    let token = login_without_2fa(&app).await;
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    set_refresh_cookie(&app, &token);
+    let response = app.post_refresh().await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
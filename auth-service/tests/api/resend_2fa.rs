@@ -0,0 +1,84 @@
+use crate::helpers::TestApp;
+use auth_service::domain::{
+    auth_method::AuthMethod, data_store::TwoFARecord, email::Email,
+    login_attempt_id::LoginAttemptId, two_fa_code::TwoFACode,
+};
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+// a pending login attempt whose code was just sent
+async fn add_login_attempt(app: &TestApp) -> (Email, LoginAttemptId, TwoFACode) {
+    let email = Email::parse(TestApp::get_random_email())
+        .expect("Unable to parse dummy email for unit test!");
+    let id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+    app.two_fa_code_store
+        .write()
+        .await
+        .add_code(TwoFARecord::new(
+            email.clone(),
+            id.clone(),
+            &code,
+            vec![AuthMethod::Password],
+        ))
+        .await
+        .expect("Unable to add 2FA code");
+    (email, id, code)
+}
+
+#[api_test]
+async fn resend_right_after_the_code_was_sent_should_return_429() {
+    let (email, id, code) = add_login_attempt(&app).await;
+
+    let body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": id.as_ref(),
+    });
+    let response = app.post_resend_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // the code that was sent still works
+    let record = app.two_fa_code_store.read().await.get_code(&id).await;
+    let record = record.expect("2FA code should still be there");
+    assert!(record.code.matches(&code));
+    assert_eq!(record.resends, 0);
+}
+
+#[api_test]
+async fn unknown_login_attempt_should_return_401() {
+    let (email, _, _) = add_login_attempt(&app).await;
+
+    let body = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": LoginAttemptId::default().as_ref(),
+    });
+    let response = app.post_resend_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let body = serde_json::json!({
+        "email": TestApp::get_random_email().expose_secret(),
+        "loginAttemptId": LoginAttemptId::default().as_ref(),
+    });
+    let response = app.post_resend_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn invalid_input_should_return_400() {
+    let test_cases = [
+        serde_json::json!({
+            "email": "test_test_com",
+            "loginAttemptId": LoginAttemptId::default().as_ref(),
+        }),
+        serde_json::json!({
+            "email": TestApp::get_random_email().expose_secret(),
+            "loginAttemptId": "not-an-id",
+        }),
+    ];
+
+    for test in test_cases {
+        let response = app.post_resend_2fa(&test).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::helpers::TestApp;
+use auth_service::domain::email::Email;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+async fn request_reset_token(app: &TestApp, email: &Secret<String>) -> String {
+    let body = serde_json::json!({ "email": email.expose_secret() });
+    let response = app.post_forgot_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let email = Email::parse(email.clone()).unwrap();
+    let store = app.password_reset_token_store.read().await;
+    let token = store
+        .get_token(&email)
+        .await
+        .expect("Could not find entry in password reset token store!");
+    token.as_ref().expose_secret().to_owned()
+}
+
+#[api_test]
+async fn forgot_password_should_send_email() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": email.expose_secret() });
+    let response = app.post_forgot_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn forgot_password_unknown_email_should_return_200_without_email() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": TestApp::get_random_email().expose_secret() });
+    let response = app.post_forgot_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn reset_password_should_replace_password() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let token = request_reset_token(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "resetToken": token,
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "NewPassword123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn reset_token_should_only_work_once() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let token = request_reset_token(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "resetToken": token,
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn wrong_reset_token_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let _ = request_reset_token(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "resetToken": "a".repeat(32),
+        "newPassword": "NewPassword123!"
+    });
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn invalid_input_should_return_400() {
+    let email = TestApp::get_random_email();
+    let test_cases = [
+        serde_json::json!({
+            // weak password
+            "email": email.expose_secret(),
+            "resetToken": "a".repeat(32),
+            "newPassword": "password"
+        }),
+        serde_json::json!({
+            // malformed token
+            "email": email.expose_secret(),
+            "resetToken": "token",
+            "newPassword": "NewPassword123!"
+        }),
+        serde_json::json!({
+            // invalid email
+            "email": "test.test.com",
+            "resetToken": "a".repeat(32),
+            "newPassword": "NewPassword123!"
+        }),
+    ];
+
+    for test in test_cases {
+        let response = app.post_reset_password(&test).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
+
+#[api_test]
+async fn malformed_input_should_return_422() {
+    let test_cases = [
+        serde_json::json!({
+            "email": "test@test.com",
+            "newPassword": "NewPassword123!"
+        }),
+        serde_json::json!({
+            "resetToken": "a".repeat(32),
+            "newPassword": "NewPassword123!"
+        }),
+        serde_json::json!({
+            "email": "test@test.com",
+            "resetToken": "a".repeat(32),
+        }),
+    ];
+
+    for test in test_cases {
+        let response = app.post_reset_password(&test).await;
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -0,0 +1,156 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::email::Email,
+    routes::{jwt::JWToken, RevokeSessionsResponse, SessionResponse},
+    utils::constants::JWT_COOKIE_NAME,
+};
+use reqwest::StatusCode;
+use secrecy::ExposeSecret;
+use test_helpers::api_test;
+
+// signs up and logs in, the app's cookie jar then holds the session cookies
+async fn login(app: &TestApp) -> (Email, String) {
+    let email = TestApp::get_random_email();
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(&email).await;
+
+    let login = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&login).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found!")
+        .value()
+        .to_owned();
+    let email = Email::parse(email).expect("Unable to parse email");
+    (email, token)
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    let body = JWToken {
+        token: token.to_owned(),
+        audience: None,
+        role: None,
+    };
+    app.post_verify_token(&body).await.status()
+}
+
+async fn sessions(app: &TestApp) -> Vec<SessionResponse> {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .json()
+        .await
+        .expect("Unable to deserialize sessions")
+}
+
+#[api_test]
+async fn missing_token_should_return_400() {
+    let response = app.get_sessions().await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn login_should_register_a_session() {
+    let (_, token) = login(&app).await;
+    let claims = app.get_claims(&token).await;
+
+    let sessions = sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].id, claims.sid);
+    assert!(sessions[0].current);
+    assert_eq!(sessions[0].ip.as_deref(), Some("127.0.0.1"));
+    assert!(sessions[0].last_seen_at >= sessions[0].created_at);
+}
+
+#[api_test]
+async fn revoked_session_token_should_be_rejected() {
+    let (email, token) = login(&app).await;
+    let other = app.generate_auth_token(&email).await;
+    let other_sid = app.get_claims(&other).await.sid;
+    assert_eq!(verify(&app, &other).await, StatusCode::OK);
+
+    let response = app.delete_session(&other_sid).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert_eq!(verify(&app, &other).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+    assert_eq!(sessions(&app).await.len(), 1);
+}
+
+#[api_test]
+async fn revoking_session_of_other_user_should_return_404() {
+    let _ = login(&app).await;
+    let stranger = Email::parse(TestApp::get_random_email()).expect("Unable to parse email");
+    let token = app.generate_auth_token(&stranger).await;
+    let sid = app.get_claims(&token).await.sid;
+
+    let response = app.delete_session(&sid).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+}
+
+#[api_test]
+async fn revoking_current_session_should_log_out() {
+    let (_, token) = login(&app).await;
+    let sid = app.get_claims(&token).await.sid;
+
+    let response = app.delete_session(&sid).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+
+    // the refresh token went with the session
+    let response = app.post_refresh().await;
+    assert_ne!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn revoke_others_should_keep_current_session() {
+    let (email, token) = login(&app).await;
+    let first = app.generate_auth_token(&email).await;
+    let second = app.generate_auth_token(&email).await;
+
+    let response = app.post_revoke_other_sessions().await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: RevokeSessionsResponse = response
+        .json()
+        .await
+        .expect("Unable to deserialize revoke response");
+    assert_eq!(body.revoked, 2);
+
+    assert_eq!(verify(&app, &first).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(verify(&app, &second).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(verify(&app, &token).await, StatusCode::OK);
+
+    let sessions = sessions(&app).await;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
+
+#[api_test]
+async fn logout_should_end_the_session() {
+    let (email, token) = login(&app).await;
+    let response = app.post_logout().await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let remaining = app
+        .session_store
+        .read()
+        .await
+        .get_sessions(&email)
+        .await
+        .expect("Unable to get sessions");
+    assert!(remaining.is_empty());
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+}
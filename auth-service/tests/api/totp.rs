@@ -0,0 +1,228 @@
+use crate::helpers::TestApp;
+use auth_service::{
+    domain::{login_attempt_id::LoginAttemptId, totp_secret::TotpSecret, two_fa_code::TwoFACode},
+    routes::{TotpEnrollResponse, TwoFactorAuthResponse},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+async fn login(app: &TestApp, email: &Secret<String>) -> reqwest::Response {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    app.post_login(&body).await
+}
+
+async fn enroll(app: &TestApp) -> TotpSecret {
+    let response = app
+        .post_totp_enroll(&serde_json::json!({ "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = response
+        .json::<TotpEnrollResponse>()
+        .await
+        .expect("Fail to deserialize TOTP enroll response");
+    TotpSecret::parse(Secret::new(response.secret)).unwrap()
+}
+
+fn now() -> u64 {
+    Utc::now().timestamp() as u64
+}
+
+fn generate(secret: &TotpSecret, time: u64) -> String {
+    secret.generate(time).unwrap().expose_secret().to_owned()
+}
+
+// signs up, logs in and turns on the authenticator app, returns the code used to confirm it
+async fn enable_totp(app: &TestApp, email: &Secret<String>) -> (TotpSecret, String) {
+    signup(app, email).await;
+    assert_eq!(login(app, email).await.status(), StatusCode::OK);
+
+    let secret = enroll(app).await;
+    let code = generate(&secret, now());
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    (secret, code)
+}
+
+async fn login_attempt_id(app: &TestApp, email: &Secret<String>) -> String {
+    let response = login(app, email).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Fail to deserialize 2FA response");
+    assert_eq!(response.message, "Authenticator app code required");
+    response.login_attempt_id
+}
+
+#[api_test]
+async fn enroll_should_return_uri_and_qr_code() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let response = app
+        .post_totp_enroll(&serde_json::json!({ "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = response
+        .json::<TotpEnrollResponse>()
+        .await
+        .expect("Fail to deserialize TOTP enroll response");
+
+    assert!(response.otpauth_uri.starts_with("otpauth://totp/"));
+    assert!(response
+        .otpauth_uri
+        .contains(&format!("secret={}", response.secret)));
+    let png = STANDARD.decode(response.qr_code).unwrap();
+    assert!(png.starts_with(&[0x89, b'P', b'N', b'G']));
+
+    // nothing changes until the enrollment is confirmed
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn confirmed_totp_should_be_required_at_login() {
+    let email = TestApp::get_random_email();
+    let (secret, _) = enable_totp(&app, &email).await;
+
+    let id = login_attempt_id(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "2FACode": generate(&secret, now() + 30)
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn totp_code_should_not_be_replayed() {
+    let email = TestApp::get_random_email();
+    let (_, code) = enable_totp(&app, &email).await;
+
+    // the code that confirmed the enrollment already burned its time step
+    let id = login_attempt_id(&app, &email).await;
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "2FACode": code
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn emailed_code_should_not_work_with_totp() {
+    let email = TestApp::get_random_email();
+    enable_totp(&app, &email).await;
+
+    let id = login_attempt_id(&app, &email).await;
+    // nothing is emailed for authenticator app logins, so the attempt is given a known code
+    let code = TwoFACode::default();
+    app.two_fa_code_store
+        .write()
+        .await
+        .resend_code(&LoginAttemptId::parse(id.clone()).unwrap(), code.clone())
+        .await
+        .unwrap();
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": id,
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn wrong_code_should_not_confirm() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+    let secret = enroll(&app).await;
+
+    let time = now();
+    let valid: Vec<String> = (0..=4)
+        .map(|step| generate(&secret, time - 60 + step * 30))
+        .collect();
+    let wrong = ["000000", "111111", "222222"]
+        .into_iter()
+        .find(|code| !valid.iter().any(|valid| valid == code))
+        .unwrap();
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": wrong }))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn enroll_with_wrong_password_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let response = app
+        .post_totp_enroll(&serde_json::json!({ "password": "WrongPassword123!" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // the wrong password counts as a failed login, the right one clears it again
+    let _ = enroll(&app).await;
+}
+
+#[api_test]
+async fn enroll_after_confirm_should_return_409() {
+    let email = TestApp::get_random_email();
+    enable_totp(&app, &email).await;
+
+    let response = app
+        .post_totp_enroll(&serde_json::json!({ "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+#[api_test]
+async fn confirm_without_enroll_should_return_400() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    assert_eq!(login(&app, &email).await.status(), StatusCode::OK);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "123456" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn missing_jwt_should_return_400() {
+    let response = app
+        .post_totp_enroll(&serde_json::json!({ "password": "Password123!" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": "123456" }))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
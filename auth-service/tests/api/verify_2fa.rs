@@ -1,10 +1,55 @@
 use crate::helpers::TestApp;
-use auth_service::domain::{
-    email::Email, login_attempt_id::LoginAttemptId, two_fa_code::TwoFACode,
+use auth_service::{
+    domain::{
+        auth_method::AuthMethod, data_store::TwoFARecord, email::Email, error::ErrorResponse,
+        login_attempt_id::LoginAttemptId, two_fa_code::TwoFACode,
+    },
+    routes::TwoFactorAuthResponse,
+    utils::constants::{TWO_FACTOR_POLICY, TWO_FA_MAX_PENDING_ATTEMPTS},
 };
 use reqwest::StatusCode;
 use secrecy::{ExposeSecret, Secret};
 use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+// signs up a 2FA account whose codes can be emailed
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    app.verify_email(email).await;
+}
+
+// logs in and returns the login attempt ID along with the code that was sent for it
+async fn login(app: &TestApp, email: &Secret<String>) -> (LoginAttemptId, TwoFACode) {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let response = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Fail to deserialize 2FA response");
+
+    let id = LoginAttemptId::parse(response.login_attempt_id).unwrap();
+    let code = TwoFACode::parse(Secret::new(app.get_emailed_2fa_code(email).await)).unwrap();
+    (id, code)
+}
 
 /*
     400: Invalid Input
@@ -24,7 +69,12 @@ async fn verify_2fa_should_pass() {
     {
         let mut store = app.two_fa_code_store.write().await;
         let _ = store
-            .add_code(email.clone(), id.clone(), code.clone())
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
             .await;
     }
 
@@ -47,7 +97,9 @@ async fn malform_field_input_should_return_400() {
            2FACode: "<String>"
        }
     */
+    // the code is only parsed once the account is found
     let email = Email::parse(TestApp::get_random_email()).unwrap();
+    signup(&app, email.as_ref()).await;
     let code = TwoFACode::default();
     let id = LoginAttemptId::default();
 
@@ -149,7 +201,12 @@ async fn non_existing_email_should_return_401() {
     {
         let mut store = app.two_fa_code_store.write().await;
         let _ = store
-            .add_code(email.clone(), id.clone(), code.clone())
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
             .await;
     }
 
@@ -175,7 +232,12 @@ async fn invalid_id_should_return_401() {
     {
         let mut store = app.two_fa_code_store.write().await;
         let _ = store
-            .add_code(email.clone(), id.clone(), code.clone())
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
             .await;
     }
 
@@ -201,7 +263,12 @@ async fn invalid_code_should_return_401() {
     {
         let mut store = app.two_fa_code_store.write().await;
         let _ = store
-            .add_code(email.clone(), id.clone(), code.clone())
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
             .await;
     }
 
@@ -215,6 +282,66 @@ async fn invalid_code_should_return_401() {
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
 
+#[api_test]
+async fn too_many_wrong_codes_should_invalidate_the_code() {
+    let email = Email::parse(TestApp::get_random_email())
+        .expect("Unable to parse dummy email for unit test!");
+    let id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    {
+        let mut store = app.two_fa_code_store.write().await;
+        let _ = store
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
+            .await;
+    }
+
+    let wrong_code = match code.as_ref().expose_secret().as_str() {
+        "000000" => "111111",
+        _ => "000000",
+    };
+    let context = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": id.as_ref(),
+        "2FACode": wrong_code
+    });
+    for _ in 1..TWO_FACTOR_POLICY.max_attempts {
+        let response = app.post_verify_2fa(&context).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    let record = app.two_fa_code_store.read().await.get_code(&id).await;
+    assert_eq!(
+        record.unwrap().failed_attempts,
+        TWO_FACTOR_POLICY.max_attempts - 1
+    );
+
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let error: ErrorResponse = response.json().await.expect("Unable to deserialize error");
+    assert!(error.error.contains("log in again"));
+
+    // the right code is too late now
+    let context = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": id.as_ref(),
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&id)
+        .await
+        .is_err());
+}
+
 #[api_test]
 async fn using_same_2fa_code_twice_should_return_401() {
     // we need to provide a invalid data input somehow?
@@ -226,7 +353,12 @@ async fn using_same_2fa_code_twice_should_return_401() {
     {
         let mut store = app.two_fa_code_store.write().await;
         let _ = store
-            .add_code(email.clone(), id.clone(), code.clone())
+            .add_code(TwoFARecord::new(
+                email.clone(),
+                id.clone(),
+                &code,
+                vec![AuthMethod::Password],
+            ))
             .await;
     }
 
@@ -242,3 +374,130 @@ async fn using_same_2fa_code_twice_should_return_401() {
     let response = app.post_verify_2fa(&context).await;
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
+
+#[api_test]
+async fn parallel_logins_should_each_verify() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    // two tabs log in before either enters its code
+    let (first_id, first_code) = login(&app, &email).await;
+    let (second_id, second_code) = login(&app, &email).await;
+    assert_ne!(first_id, second_id);
+
+    for (id, code) in [(&second_id, &second_code), (&first_id, &first_code)] {
+        let context = serde_json::json!({
+            "email": email.expose_secret(),
+            "loginAttemptId": id.as_ref(),
+            "2FACode": code.as_ref().expose_secret()
+        });
+        let response = app.post_verify_2fa(&context).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
+
+#[api_test]
+async fn code_of_another_login_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    let (first_id, first_code) = login(&app, &email).await;
+    let (second_id, second_code) = login(&app, &email).await;
+    if first_code == second_code {
+        return;
+    }
+
+    let context = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": second_id.as_ref(),
+        "2FACode": first_code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // the wrong guess only counts against the login it was made for
+    let store = app.two_fa_code_store.read().await;
+    assert_eq!(store.get_code(&first_id).await.unwrap().failed_attempts, 0);
+    assert_eq!(store.get_code(&second_id).await.unwrap().failed_attempts, 1);
+}
+
+#[api_test]
+async fn login_attempt_of_another_user_should_return_401() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+    let (id, code) = login(&app, &email).await;
+
+    let context = serde_json::json!({
+        "email": TestApp::get_random_email().expose_secret(),
+        "loginAttemptId": id.as_ref(),
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn oldest_login_should_be_dropped_past_the_cap() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    let mut logins = Vec::new();
+    for _ in 0..=TWO_FA_MAX_PENDING_ATTEMPTS {
+        logins.push(login(&app, &email).await);
+    }
+
+    let (oldest_id, oldest_code) = &logins[0];
+    let context = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": oldest_id.as_ref(),
+        "2FACode": oldest_code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let (newest_id, newest_code) = &logins[TWO_FA_MAX_PENDING_ATTEMPTS];
+    let context = serde_json::json!({
+        "email": email.expose_secret(),
+        "loginAttemptId": newest_id.as_ref(),
+        "2FACode": newest_code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn expired_code_should_return_401() {
+    let email = Email::parse(TestApp::get_random_email())
+        .expect("Unable to parse dummy email for unit test!");
+    let id = LoginAttemptId::default();
+    let code = TwoFACode::default();
+
+    {
+        let record = TwoFARecord::new(email.clone(), id.clone(), &code, vec![AuthMethod::Password]);
+        let record = TwoFARecord {
+            expires_at: record.created_at - 1,
+            ..record
+        };
+        let mut store = app.two_fa_code_store.write().await;
+        let _ = store.add_code(record).await;
+    }
+
+    let context = serde_json::json!({
+        "email": email.as_ref().expose_secret(),
+        "loginAttemptId": id.as_ref(),
+        "2FACode": code.as_ref().expose_secret()
+    });
+    let response = app.post_verify_2fa(&context).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let error: ErrorResponse = response.json().await.expect("Unable to deserialize error");
+    assert!(error.error.contains("expired"));
+
+    // the expired login attempt is gone
+    assert!(app
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&id)
+        .await
+        .is_err());
+}
@@ -0,0 +1,137 @@
+use crate::helpers::TestApp;
+use reqwest::StatusCode;
+use secrecy::{ExposeSecret, Secret};
+use test_helpers::api_test;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &Secret<String>) {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+}
+
+async fn login(app: &TestApp, email: &Secret<String>) -> reqwest::Response {
+    let body = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!"
+    });
+    app.post_login(&body).await
+}
+
+#[api_test]
+async fn signup_should_send_verification_email() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+}
+
+#[api_test]
+async fn signup_should_succeed_when_verification_email_fails() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+}
+
+#[api_test]
+async fn verified_email_should_allow_login() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let token = app.get_emailed_token(&email).await;
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = login(&app, &email).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[api_test]
+async fn verification_token_should_only_work_once() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    let token = app.get_emailed_token(&email).await;
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.get_verify_email(&token).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[api_test]
+async fn invalid_verification_token_should_return_401() {
+    let test_cases = ["a".repeat(32), "token".to_owned()];
+
+    for token in test_cases {
+        let response = app.get_verify_email(&token).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[api_test]
+async fn missing_verification_token_should_return_400() {
+    let response = app
+        .http_client
+        .get(format!("{}/verify-email", &app.address))
+        .send()
+        .await
+        .expect("Fail to get verify email request!");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[api_test]
+async fn resend_right_after_signup_should_return_429() {
+    let email = TestApp::get_random_email();
+    signup(&app, &email).await;
+
+    let body = serde_json::json!({ "email": email.expose_secret() });
+    let response = app.post_resend_verification_email(&body).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[api_test]
+async fn resend_unknown_email_should_return_200_without_email() {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let body = serde_json::json!({ "email": TestApp::get_random_email().expose_secret() });
+    let response = app.post_resend_verification_email(&body).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // asking again straight away is throttled, whether the account exists or not
+    let response = app.post_resend_verification_email(&body).await;
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[api_test]
+async fn resend_invalid_email_should_return_400() {
+    let body = serde_json::json!({ "email": "test.test.com" });
+    let response = app.post_resend_verification_email(&body).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -1,22 +1,100 @@
 use crate::helpers::TestApp;
-use auth_service::domain::email::Email;
+use auth_service::domain::{data_store::UserUpdate, email::Email, user::UserRole};
 use auth_service::routes::jwt::JWToken;
-use auth_service::utils::auth::generate_auth_token;
 use reqwest::StatusCode;
+use secrecy::ExposeSecret;
 use test_helpers::api_test;
 
 #[api_test]
 async fn valid_token_should_return_200() {
     let random_email = TestApp::get_random_email();
     let email = Email::parse(random_email).expect("Unable to parse email");
-    let jwt = generate_auth_token(&email)
-        .expect("dummy token is not valid! Please provide a valid token!");
-    let body = JWToken { token: jwt };
+    let jwt = app.generate_auth_token(&email).await;
+    let body = JWToken {
+        token: jwt,
+        audience: None,
+        role: None,
+    };
 
     let result = app.post_verify_token(&body).await;
     assert_eq!(result.status(), StatusCode::OK);
 }
 
+#[api_test]
+async fn token_should_only_verify_for_its_audience() {
+    let email = Email::parse(TestApp::get_random_email()).expect("Unable to parse email");
+    let jwt = app.generate_auth_token(&email).await;
+
+    let body = JWToken {
+        token: jwt.clone(),
+        audience: Some("app-service".to_owned()),
+        role: None,
+    };
+    assert_eq!(app.post_verify_token(&body).await.status(), StatusCode::OK);
+
+    let body = JWToken {
+        token: jwt,
+        audience: Some("unknown-service".to_owned()),
+        role: None,
+    };
+    assert_eq!(
+        app.post_verify_token(&body).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[api_test]
+async fn token_below_required_role_should_return_403() {
+    let email = TestApp::get_random_email();
+    let signup = serde_json::json!({
+        "email": email.expose_secret(),
+        "password": "Password123!",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup).await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let email = Email::parse(email).expect("Unable to parse email");
+
+    let member = app.generate_auth_token(&email).await;
+    let body = JWToken {
+        token: member.clone(),
+        audience: None,
+        role: Some(UserRole::Member),
+    };
+    assert_eq!(app.post_verify_token(&body).await.status(), StatusCode::OK);
+
+    let body = JWToken {
+        token: member,
+        audience: None,
+        role: Some(UserRole::Admin),
+    };
+    assert_eq!(
+        app.post_verify_token(&body).await.status(),
+        StatusCode::FORBIDDEN
+    );
+
+    // the role only makes it into tokens issued after it was granted
+    app.user_store
+        .write()
+        .await
+        .update_user(
+            &email,
+            UserUpdate {
+                role: Some(UserRole::Admin),
+                ..Default::default()
+            },
+        )
+        .await
+        .expect("Unable to set role");
+    let admin = app.generate_auth_token(&email).await;
+    let body = JWToken {
+        token: admin,
+        audience: None,
+        role: Some(UserRole::Admin),
+    };
+    assert_eq!(app.post_verify_token(&body).await.status(), StatusCode::OK);
+}
+
 #[api_test]
 async fn malformed_input_should_return_422() {
     // an error 422 returns unprocessable content. Fill in invalid token type.